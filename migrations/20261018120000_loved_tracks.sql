@@ -0,0 +1,18 @@
+-- the tracks a user has loved, mirrored from `user.getLovedTracks`
+create table if not exists loved_tracks
+(
+    id            integer not null
+        constraint loved_tracks_pk
+            primary key autoincrement,
+    track         text    not null,
+    artist        text    not null,
+    -- when the track was loved
+    timestamp_utc integer not null
+);
+
+create index if not exists loved_tracks_timestamp_utc_index
+    on loved_tracks (timestamp_utc desc);
+
+-- loved tracks are matched to scrobbles case-insensitively
+create index if not exists loved_tracks_artists_tracks
+    on loved_tracks (lower(artist), lower(track));
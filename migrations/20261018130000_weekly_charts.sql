@@ -0,0 +1,32 @@
+-- weekly charts mirrored from Last.fm, one row per week
+create table if not exists weekly_charts
+(
+    id             integer not null
+        constraint weekly_charts_pk
+            primary key autoincrement,
+    timestamp_from integer not null,
+    timestamp_to   integer not null
+);
+
+create unique index if not exists ux_weekly_charts_timestamp_from
+    on weekly_charts (timestamp_from);
+
+-- the artists, albums and tracks in each weekly chart
+create table if not exists weekly_chart_entries
+(
+    id        integer not null
+        constraint weekly_chart_entries_pk
+            primary key autoincrement,
+    chart_id  integer not null
+        references weekly_charts (id),
+    kind      text    not null
+        check (kind in ('artist', 'album', 'track')),
+    rank      integer not null,
+    artist    text    not null,
+    -- album or track name, null for artists
+    name      text,
+    scrobbles integer not null
+);
+
+create index if not exists weekly_chart_entries_chart_id
+    on weekly_chart_entries (chart_id);
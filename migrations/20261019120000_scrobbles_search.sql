@@ -0,0 +1,46 @@
+-- full-text index of track, artist and album names, reading the names from `scrobbles`
+create virtual table if not exists scrobbles_search using fts5
+(
+    track,
+    artist,
+    album,
+    content = 'scrobbles',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- every distinct word in the index, used to correct misspelled searches
+create virtual table if not exists scrobbles_search_terms using fts5vocab(scrobbles_search, 'row');
+
+-- keep the index in sync with `scrobbles`
+create trigger if not exists scrobbles_search_insert
+    after insert
+    on scrobbles
+begin
+    insert into scrobbles_search (rowid, track, artist, album)
+    values (new.id, new.track, new.artist, new.album);
+end;
+
+create trigger if not exists scrobbles_search_delete
+    after delete
+    on scrobbles
+begin
+    insert into scrobbles_search (scrobbles_search, rowid, track, artist, album)
+    values ('delete', old.id, old.track, old.artist, old.album);
+end;
+
+create trigger if not exists scrobbles_search_update
+    after update of track, artist, album
+    on scrobbles
+begin
+    insert into scrobbles_search (scrobbles_search, rowid, track, artist, album)
+    values ('delete', old.id, old.track, old.artist, old.album);
+    insert into scrobbles_search (rowid, track, artist, album)
+    values (new.id, new.track, new.artist, new.album);
+end;
+
+-- index scrobbles saved before the index existed, which only happens the first time
+insert into scrobbles_search (rowid, track, artist, album)
+select id, track, artist, album
+from scrobbles
+where not exists(select 1 from scrobbles_search_docsize);
@@ -0,0 +1,7 @@
+-- the names scrobbles had before name rules changed them, null when the rules changed nothing
+alter table scrobbles
+    add column original_track text;
+alter table scrobbles
+    add column original_artist text;
+alter table scrobbles
+    add column original_album text;
@@ -0,0 +1,31 @@
+-- a scrobble is identified by its time, artist and track as scrobbled, matching
+-- `SavedScrobble::identity`
+
+-- keep the copies that are merged away, so they can be looked at or restored
+create table merged_scrobbles as
+select *
+from scrobbles
+where id not in (select min(id)
+                 from scrobbles
+                 group by timestamp_utc, coalesce(original_artist, artist), coalesce(original_track, track));
+
+-- merge copies of the same scrobble into the first one saved, which is loved if any copy is
+update scrobbles
+set loved = 1
+where loved = 0
+  and exists(select 1
+             from scrobbles copy
+             where copy.timestamp_utc = scrobbles.timestamp_utc
+               and coalesce(copy.original_artist, copy.artist) = coalesce(scrobbles.original_artist, scrobbles.artist)
+               and coalesce(copy.original_track, copy.track) = coalesce(scrobbles.original_track, scrobbles.track)
+               and copy.loved = 1);
+
+delete
+from scrobbles
+where id not in (select min(id)
+                 from scrobbles
+                 group by timestamp_utc, coalesce(original_artist, artist), coalesce(original_track, track));
+
+-- make sure no more copies can be saved
+create unique index scrobbles_identity
+    on scrobbles (timestamp_utc, coalesce(original_artist, artist), coalesce(original_track, track));
@@ -0,0 +1,4 @@
+-- scrobbles in chronological order, with ties in the order they were saved, so a scrobble can be
+-- found by its number reading only this index
+create index if not exists scrobbles_chronological
+    on scrobbles (timestamp_utc, id);
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use num_format::ToFormattedString;
+use sqlx::SqlitePool;
+
+use crate::config::Config;
+use crate::data::db;
+use crate::models::saved_scrobbles::{SavedScrobble, SavedScrobbles};
+use crate::reports::anomalies::{Anomalies, AnomalyKind, Thresholds};
+use crate::{app, data, output, utils};
+
+/// Where the checked scrobbles were loaded from, so suspicious ones can be removed
+enum Storage<'a> {
+    File(&'a str),
+    Database(SqlitePool, Vec<i64>),
+}
+
+pub async fn anomalies(a: app::Anomalies, config: Config) -> Result<()> {
+    let username = a
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+
+    // Files are checked before the database, the same as when scrobbles are loaded for reports
+    let (mut saved_scrobbles, storage) =
+        if let Some(file_format) = data::find_which_file_exists(&username)? {
+            let saved_scrobbles = data::load_from_file(&username, file_format)?;
+            (saved_scrobbles, Storage::File(file_format))
+        } else if app::uses_sqlite_database(&username, &config)
+            && db::check_if_sqlite_database_exists()?
+        {
+            eprintln!("Loading saved scrobbles from database...");
+            let pool = db::get_sqlite_pool().await?;
+            let (ids, scrobbles): (Vec<i64>, Vec<SavedScrobble>) =
+                db::get_numbered_scrobbles(&pool).await?.into_iter().unzip();
+            (SavedScrobbles::new(scrobbles), Storage::Database(pool, ids))
+        } else {
+            // Explains why nothing could be loaded
+            app::load_saved_scrobbles(&username, &config).await?;
+            return Ok(());
+        };
+
+    let kinds = if a.kind.is_empty() {
+        AnomalyKind::ALL.to_vec()
+    } else {
+        a.kind
+    };
+    let thresholds = Thresholds {
+        duplicate_seconds: a.duplicate_seconds,
+        min_gap_seconds: a.min_gap_seconds,
+    };
+
+    let anomalies = Anomalies::new(
+        saved_scrobbles.as_slice(),
+        thresholds,
+        utils::get_current_unix_timestamp(),
+        &kinds,
+        a.limit,
+    );
+    output::print_report(&anomalies, a.output)?;
+
+    if anomalies.is_empty() {
+        return Ok(());
+    }
+    if !a.quarantine {
+        eprintln!(
+            "Run again with --quarantine to move the suspicious scrobbles into a quarantine file."
+        );
+        return Ok(());
+    }
+
+    // The quarantine file is written first, so no scrobble is ever lost
+    let file = data::append_to_quarantine(&anomalies.anomalies, &username)?;
+    let removed = match storage {
+        Storage::File(file_format) => {
+            let removed = saved_scrobbles.remove(&anomalies.indices());
+            data::overwrite_file(&saved_scrobbles, &username, file_format)?;
+            removed as u64
+        }
+        Storage::Database(pool, ids) => {
+            let ids = anomalies
+                .indices()
+                .into_iter()
+                .map(|i| ids[i])
+                .collect::<Vec<i64>>();
+            db::delete_scrobbles(&pool, &ids).await?
+        }
+    };
+    eprintln!(
+        "Moved {} suspicious scrobbles to `{}`",
+        removed.to_formatted_string(&utils::get_locale()),
+        file.display()
+    );
+
+    Ok(())
+}
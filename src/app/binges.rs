@@ -0,0 +1,31 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::reports::binges::{Binges, Thresholds};
+use crate::{app, output};
+
+pub async fn binges(b: app::Binges, config: Config) -> Result<()> {
+    let username = match b.username {
+        Some(username) => username,
+        None => config.default_username.clone(),
+    };
+
+    let mut saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let excluded = app::exclude_scrobbles(&mut saved_scrobbles, b.include_excluded)?;
+
+    let thresholds = Thresholds {
+        day_plays: b.day_plays,
+        week_plays: b.week_plays,
+        artist_share: b.share,
+        min_week_scrobbles: b.min_week_scrobbles,
+        decay_weeks: b.decay_weeks,
+    };
+
+    eprintln!("Looking for binges in the history of {}...\n", &username);
+    let mut binges = Binges::new(saved_scrobbles.as_slice(), thresholds);
+    binges.excluded_scrobbles = excluded;
+    output::print_report(&binges, b.output)
+}
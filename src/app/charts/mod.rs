@@ -0,0 +1,167 @@
+use anyhow::Result;
+use chrono::{Local, NaiveDate, TimeZone};
+use clap::Parser;
+use num_format::ToFormattedString;
+
+use crate::config::Config;
+use crate::data::db;
+use crate::models::saved_weekly_charts::{ChartKind, SavedWeeklyCharts};
+use crate::output::{self, OutputFormat};
+use crate::reports::charts::ChartDiff;
+use crate::{app, data, lastfm, utils};
+
+/// Charts are saved after every batch of weeks, so an interrupted sync does not lose its progress
+const BATCH_SIZE: usize = 50;
+
+#[derive(Parser)]
+pub enum ChartsSubCommand {
+    Diff(Diff),
+    Sync(Sync),
+}
+
+/// Compares the saved Last.fm charts with weekly charts generated from saved scrobbles
+#[derive(Parser)]
+pub struct Diff {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// The chart to compare
+    #[clap(short, long, arg_enum, default_value = "track")]
+    pub kind: ChartKind,
+    /// Only compares weeks starting on or after this date, e.g. 2021-01-01
+    #[clap(short, long)]
+    pub since: Option<NaiveDate>,
+    /// The format to write the differences in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// Fetches the weekly artist, album and track charts of every week that has not been saved yet
+#[derive(Parser)]
+pub struct Sync {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Only fetches weeks starting on or after this date, e.g. 2021-01-01
+    #[clap(short, long)]
+    pub since: Option<NaiveDate>,
+}
+
+pub async fn charts(c: app::Charts, config: Config) -> Result<()> {
+    match c.subcmd {
+        ChartsSubCommand::Diff(d) => diff(d, config).await,
+        ChartsSubCommand::Sync(s) => sync(s, config).await,
+    }
+}
+
+async fn diff(d: Diff, config: Config) -> Result<()> {
+    let username = d
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+
+    let charts = load_charts(&username, &config).await?;
+    if charts.is_empty() {
+        eprintln!(
+            "No weekly charts for `{}` have been saved. Please run `charts sync` first.",
+            username
+        );
+        return Ok(());
+    }
+
+    let saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+
+    let since = start_timestamp(d.since);
+    let charts =
+        SavedWeeklyCharts::new(charts.iter().filter(|c| c.from >= since).cloned().collect());
+
+    let diff = ChartDiff::new(&charts, saved_scrobbles.as_slice(), d.kind);
+    output::print_report(&diff, d.output)
+}
+
+async fn sync(s: Sync, config: Config) -> Result<()> {
+    let username = s
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+    let locale = utils::get_locale();
+
+    let user = lastfm::profile::fetch_profile(&username, &config.api_key).await?;
+    let mut charts = load_charts(&username, &config).await?;
+
+    eprintln!("Fetching the weekly chart list for `{}`...", username);
+    let now = utils::get_current_unix_timestamp();
+    let since = start_timestamp(s.since).max(user.registered.timestamp());
+    let weeks = lastfm::weekly_charts::fetch_chart_list(&username, &config.api_key)
+        .await?
+        .into_iter()
+        // Only complete weeks are saved, since the current week's charts are still changing
+        .filter(|(from, to)| *to > since && *to <= now && !charts.contains(*from))
+        .collect::<Vec<(i64, i64)>>();
+
+    if weeks.is_empty() {
+        eprintln!("Every weekly chart has already been saved.");
+        return Ok(());
+    }
+
+    eprintln!(
+        "Fetching the charts of {} weeks...",
+        weeks.len().to_formatted_string(&locale)
+    );
+
+    let pool = if app::uses_sqlite_database(&username, &config) {
+        Some(db::get_sqlite_pool().await?)
+    } else {
+        None
+    };
+
+    for batch in weeks.chunks(BATCH_SIZE) {
+        let fetched =
+            lastfm::weekly_charts::fetch_charts(&username, batch.to_vec(), &config.api_key).await;
+
+        for chart in fetched {
+            if let Some(pool) = &pool {
+                db::insert_weekly_chart(pool, &chart).await?;
+            }
+            charts.insert(chart);
+        }
+
+        if pool.is_none() {
+            data::save_weekly_charts(&charts, &username)?;
+        }
+    }
+
+    let missing = weeks
+        .iter()
+        .filter(|(from, _)| !charts.contains(*from))
+        .count();
+    if missing > 0 {
+        eprintln!(
+            "{} weeks could not be fetched. Run the command again to retry them.",
+            missing.to_formatted_string(&locale)
+        );
+    }
+
+    Ok(())
+}
+
+/// Loads the saved weekly charts from the Sqlite database or a file
+async fn load_charts(username: &str, config: &Config) -> Result<SavedWeeklyCharts> {
+    if app::uses_sqlite_database(username, config) && db::check_if_sqlite_database_exists()? {
+        let pool = db::get_sqlite_pool().await?;
+        return db::get_weekly_charts(&pool).await;
+    }
+
+    data::load_weekly_charts(username)
+}
+
+/// The unix timestamp of the start of a local date, or 0 without a date
+fn start_timestamp(date: Option<NaiveDate>) -> i64 {
+    date.map_or(0, |date| {
+        Local
+            .from_local_datetime(&date.and_hms(0, 0, 0))
+            .earliest()
+            .map_or(0, |dt| dt.timestamp())
+    })
+}
@@ -0,0 +1,31 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::reports::compare::Compatibility;
+use crate::{app, output, utils};
+
+pub async fn compare(c: app::Compare, config: Config) -> Result<()> {
+    let mut scrobbles_a = match app::load_saved_scrobbles(&c.user_a, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let excluded_a = app::exclude_scrobbles(&mut scrobbles_a, c.include_excluded)?;
+
+    let mut scrobbles_b = match app::load_saved_scrobbles(&c.user_b, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let excluded_b = app::exclude_scrobbles(&mut scrobbles_b, c.include_excluded)?;
+
+    eprintln!("Comparing {} and {}...\n", &c.user_a, &c.user_b);
+    let mut compatibility = Compatibility::new(
+        (&c.user_a, scrobbles_a.as_slice()),
+        (&c.user_b, scrobbles_b.as_slice()),
+        c.period,
+        utils::get_current_unix_timestamp(),
+    );
+    compatibility.excluded_scrobbles = excluded_a + excluded_b;
+    output::print_report(&compatibility, c.output)?;
+
+    Ok(())
+}
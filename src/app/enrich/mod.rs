@@ -0,0 +1,155 @@
+use anyhow::Result;
+use clap::Parser;
+use num_format::ToFormattedString;
+
+use crate::config::Config;
+use crate::{app, data, lastfm, utils};
+
+/// Durations are saved after every batch, so an interrupted enrichment does not lose its progress
+const BATCH_SIZE: usize = 500;
+
+#[derive(Parser)]
+pub enum EnrichSubCommand {
+    Durations(Durations),
+    Tags(Tags),
+}
+
+/// Looks up the duration of every distinct scrobbled track with `track.getInfo`
+#[derive(Parser)]
+pub struct Durations {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Looks up tracks again whose duration was unknown the last time
+    #[clap(long, takes_value = false)]
+    pub retry_unknown: bool,
+}
+
+/// Looks up the top tags of every distinct scrobbled artist with `artist.getTopTags`
+#[derive(Parser)]
+pub struct Tags {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Looks up artists again whose tags are older than this many days
+    #[clap(long)]
+    pub refresh_days: Option<i64>,
+}
+
+pub async fn enrich(e: app::Enrich, config: Config) -> Result<()> {
+    match e.subcmd {
+        EnrichSubCommand::Durations(d) => durations(d, config).await,
+        EnrichSubCommand::Tags(t) => tags(t, config).await,
+    }
+}
+
+async fn durations(d: Durations, config: Config) -> Result<()> {
+    let username = d
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+    let locale = utils::get_locale();
+
+    let saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let scrobbles = saved_scrobbles.as_slice();
+
+    let mut durations = data::load_track_durations()?;
+
+    let mut tracks = durations.missing(scrobbles);
+    if d.retry_unknown {
+        tracks.append(&mut durations.unknown(scrobbles));
+    }
+
+    if tracks.is_empty() {
+        eprintln!("The duration of every track has already been looked up.");
+        return Ok(());
+    }
+
+    eprintln!(
+        "Looking up the duration of {} tracks...",
+        tracks.len().to_formatted_string(&locale)
+    );
+
+    for batch in tracks.chunks(BATCH_SIZE) {
+        let fetched = lastfm::track_info::fetch_durations(batch.to_vec(), &config.api_key).await;
+        fetched
+            .into_iter()
+            .for_each(|duration| durations.insert(duration));
+        data::save_track_durations(&durations)?;
+    }
+
+    let known = scrobbles
+        .iter()
+        .filter(|s| durations.duration_ms(s).is_some())
+        .count();
+    eprintln!(
+        "Durations are known for {} of {} scrobbles ({:.1}%).",
+        known.to_formatted_string(&locale),
+        scrobbles.len().to_formatted_string(&locale),
+        known as f64 / scrobbles.len().max(1) as f64 * 100.0
+    );
+
+    let missing = durations.missing(scrobbles).len();
+    if missing > 0 {
+        eprintln!(
+            "{} tracks could not be looked up. Run the command again to retry them.",
+            missing.to_formatted_string(&locale)
+        );
+    }
+
+    Ok(())
+}
+
+async fn tags(t: Tags, config: Config) -> Result<()> {
+    let username = t
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+    let locale = utils::get_locale();
+
+    let saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let scrobbles = saved_scrobbles.as_slice();
+
+    let mut tags = data::load_artist_tags()?;
+
+    let mut artists = tags.missing(scrobbles);
+    if let Some(days) = t.refresh_days {
+        let refresh_before = utils::get_current_unix_timestamp() - days * 86_400;
+        artists.append(&mut tags.fetched_before(scrobbles, refresh_before));
+    }
+
+    if artists.is_empty() {
+        eprintln!("The tags of every artist have already been looked up.");
+    } else {
+        eprintln!(
+            "Looking up the tags of {} artists...",
+            artists.len().to_formatted_string(&locale)
+        );
+
+        for batch in artists.chunks(BATCH_SIZE) {
+            let fetched = lastfm::artist_tags::fetch_tags(batch.to_vec(), &config.api_key).await;
+            fetched.into_iter().for_each(|t| tags.insert(t));
+            data::save_artist_tags(&tags)?;
+        }
+
+        let missing = tags.missing(scrobbles).len();
+        if missing > 0 {
+            eprintln!(
+                "{} artists could not be looked up. Run the command again to retry them.",
+                missing.to_formatted_string(&locale)
+            );
+        }
+    }
+
+    let rules = data::create_genre_rules()?;
+    eprintln!(
+        "Tags are mapped onto genres with the rules in `{}`.",
+        rules.display()
+    );
+
+    Ok(())
+}
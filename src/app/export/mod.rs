@@ -0,0 +1,87 @@
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+
+use crate::app;
+use crate::config::Config;
+use crate::models::saved_weekly_charts::ChartKind;
+use crate::reports::chart_race::{self, Interval, Layout, Mode};
+
+#[derive(Parser)]
+pub enum ExportSubCommand {
+    ChartRace(ChartRace),
+}
+
+/// Exports the top ranked artists, albums or tracks at each interval as CSV for bar chart race
+/// tools
+#[derive(Parser)]
+pub struct ChartRace {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// What to rank
+    #[clap(short, long, arg_enum, default_value = "artist")]
+    pub kind: ChartKind,
+    /// The length of each step of the race
+    #[clap(short, long, arg_enum, default_value = "week")]
+    pub interval: Interval,
+    /// Whether play counts are totals since the first scrobble, or only cover the last `window` intervals
+    #[clap(short, long, arg_enum, default_value = "cumulative")]
+    pub mode: Mode,
+    /// The number of intervals counted in rolling mode
+    #[clap(short, long, default_value = "4")]
+    pub window: usize,
+    /// The number of ranked names at each interval
+    #[clap(short, long, default_value = "10")]
+    pub top: usize,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The CSV layout to write
+    #[clap(short, long, arg_enum, default_value = "wide")]
+    pub layout: Layout,
+    /// Writes the CSV to a file instead of stdout
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+pub async fn export(e: app::Export, config: Config) -> Result<()> {
+    match e.subcmd {
+        ExportSubCommand::ChartRace(c) => chart_race(c, config).await,
+    }
+}
+
+async fn chart_race(c: ChartRace, config: Config) -> Result<()> {
+    let username = c
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+
+    let mut saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    app::exclude_scrobbles(&mut saved_scrobbles, c.include_excluded)?;
+    saved_scrobbles.clean_titles(&app::load_title_cleaner()?);
+
+    let race = chart_race::ChartRace::new(
+        saved_scrobbles.as_slice(),
+        c.kind,
+        c.interval,
+        c.mode,
+        c.window,
+        c.top,
+    );
+
+    match c.output {
+        Some(path) => {
+            race.write_csv(File::create(&path)?, c.layout)?;
+            eprintln!("Chart race saved to `{}`", path.display());
+        }
+        None => race.write_csv(io::stdout(), c.layout)?,
+    }
+
+    Ok(())
+}
@@ -5,9 +5,11 @@ use crate::app::Fetch;
 use crate::config::{Config, StorageFormat};
 use crate::data::db;
 use crate::models::saved_scrobbles::SavedScrobbles;
-use crate::{data, lastfm, utils};
+use crate::{app, data, lastfm, utils};
 
 pub async fn fetch(f: Fetch, config: Config) -> Result<()> {
+    let local_times = data::load_local_times(&config)?;
+
     let username = match f.username {
         Some(username) => username,
         None => config.default_username,
@@ -36,18 +38,9 @@ pub async fn fetch(f: Fetch, config: Config) -> Result<()> {
     if !f.new_file {
         if file_format == "db" {
             let pool = db::get_sqlite_pool().await?;
-            // most_recent_timestamp = db::get_most_recent_scrobble(&pool).await?;
-
-            most_recent_timestamp = match db::get_most_recent_scrobble(&pool).await {
-                Ok(timestamp) => timestamp,
-                Err(_) => {
-                    println!("Existing Sqlite database not found. Creating one now...");
-                    db::build_sqlite_database()?;
-                    0
-                }
-            }
+            most_recent_timestamp = db::get_most_recent_scrobble(&pool).await?;
         } else {
-            saved_tracks = match data::load_from_file(&user.name, &file_format) {
+            saved_tracks = match data::load_from_file(&user.name, file_format) {
                 Ok(saved_scrobbles) => saved_scrobbles,
                 Err(_) => {
                     println!(
@@ -61,7 +54,7 @@ pub async fn fetch(f: Fetch, config: Config) -> Result<()> {
     }
 
     /*let mut saved_tracks = if !f.new_file {
-        match data::load_from_file(&user.name, &file_format) {
+        match data::load_from_file(&user.name, file_format) {
             Ok(saved_scrobbles) => saved_scrobbles,
             Err(_) => {
                 println!(
@@ -139,7 +132,33 @@ pub async fn fetch(f: Fetch, config: Config) -> Result<()> {
         return Ok(());
     }
 
-    let new_tracks_len = &new_tracks.len();
+    let mut new_scrobbles = SavedScrobbles::from_scrobbles(&new_tracks);
+    new_scrobbles.retime(&local_times);
+    if let Some(rules) = data::load_name_rules()? {
+        let renamed = new_scrobbles.normalize(&rules.canonicalizer()?);
+        if renamed > 0 {
+            println!(
+                "Renamed {} new scrobbles with the name rules",
+                renamed.to_formatted_string(&utils::get_locale())
+            );
+        }
+    }
+
+    let exclude_when_fetching = match data::load_exclusion_rules()? {
+        Some(rules) if rules.exclude_when_fetching => {
+            let excluded = new_scrobbles.exclude(&app::compile_exclusions(&rules)?);
+            if excluded > 0 {
+                println!(
+                    "Excluded {} new scrobbles with the exclusion rules",
+                    excluded.to_formatted_string(&utils::get_locale())
+                );
+            }
+            true
+        }
+        _ => false,
+    };
+
+    let new_tracks_len = new_scrobbles.total_saved_scrobbles();
     /*let new_total = if !saved_tracks.is_empty() {
         match new_tracks_len {
             1 => println!("Saving one new track to existing file..."),
@@ -148,13 +167,13 @@ pub async fn fetch(f: Fetch, config: Config) -> Result<()> {
                 new_tracks_len.to_formatted_string(&utils::get_locale())
             ),
         }
-        data::append_to_file(&new_tracks, &mut saved_tracks, &user.name, &file_format)?
+        data::append_to_file(&new_tracks, &mut saved_tracks, &user.name, file_format)?
     } else {
         println!(
             "Saving {} tracks to file...",
             &new_tracks.len().to_formatted_string(&utils::get_locale())
         );
-        data::save_to_file(&new_tracks, &user.name, &file_format)?
+        data::save_to_file(&new_tracks, &user.name, file_format)?
     };*/
 
     let new_total;
@@ -167,13 +186,13 @@ pub async fn fetch(f: Fetch, config: Config) -> Result<()> {
                     new_tracks_len.to_formatted_string(&utils::get_locale())
                 ),
             }
-            data::append_to_file(&new_tracks, &mut saved_tracks, &user.name, &file_format)?
+            data::append_to_file(new_scrobbles, &mut saved_tracks, &user.name, file_format)?
         } else {
             println!(
                 "Saving {} tracks to file...",
-                &new_tracks.len().to_formatted_string(&utils::get_locale())
+                new_tracks_len.to_formatted_string(&utils::get_locale())
             );
-            data::save_to_file(&new_tracks, &user.name, &file_format)?
+            data::save_to_file(&new_scrobbles, &user.name, file_format)?
         };
     } else {
         if most_recent_timestamp != 0 {
@@ -187,15 +206,15 @@ pub async fn fetch(f: Fetch, config: Config) -> Result<()> {
         } else {
             println!(
                 "Saving {} tracks to file...",
-                &new_tracks.len().to_formatted_string(&utils::get_locale())
+                new_tracks_len.to_formatted_string(&utils::get_locale())
             );
         }
-        let new_scrobbles = SavedScrobbles::from_scrobbles(&new_tracks);
         let pool = db::get_sqlite_pool().await?;
         new_total = db::insert_scrobbles(new_scrobbles, &pool).await?;
     }
 
-    if new_total != user.play_count() && !f.current_day {
+    // Excluded scrobbles are part of Last.fm's play count, but are never saved
+    if new_total != user.play_count() && !f.current_day && !exclude_when_fetching {
         println!(
             "{} scrobbles were saved to the file, when {} scrobbles were expected.",
             new_total.to_formatted_string(&utils::get_locale()),
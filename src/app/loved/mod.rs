@@ -0,0 +1,120 @@
+use anyhow::Result;
+use clap::Parser;
+use num_format::ToFormattedString;
+
+use crate::config::Config;
+use crate::data::db;
+use crate::models::saved_loved_tracks::SavedLovedTracks;
+use crate::output::{self, OutputFormat};
+use crate::{app, data, lastfm, utils};
+
+#[derive(Parser)]
+pub enum LovedSubCommand {
+    List(List),
+    Sync(Sync),
+}
+
+/// Lists a user's loved tracks, from the most recently loved to the least
+#[derive(Parser)]
+pub struct List {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// The maximum number of loved tracks to list
+    #[clap(short, long)]
+    pub limit: Option<usize>,
+    /// The format to write the loved tracks in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// Fetches a user's loved tracks with `user.getLovedTracks` and updates the `loved` flag of every
+/// saved scrobble to match
+#[derive(Parser)]
+pub struct Sync {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+}
+
+pub async fn loved(l: app::Loved, config: Config) -> Result<()> {
+    match l.subcmd {
+        LovedSubCommand::List(l) => list(l, config).await,
+        LovedSubCommand::Sync(s) => sync(s, config).await,
+    }
+}
+
+async fn list(l: List, config: Config) -> Result<()> {
+    let username = l
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+
+    let loved_tracks = if app::uses_sqlite_database(&username, &config)
+        && db::check_if_sqlite_database_exists()?
+    {
+        let pool = db::get_sqlite_pool().await?;
+        Some(db::get_loved_tracks(&pool).await?)
+    } else {
+        data::load_loved_tracks(&username)?
+    };
+
+    let mut loved_tracks = match loved_tracks {
+        Some(loved_tracks) => loved_tracks,
+        None => {
+            eprintln!(
+                "No loved tracks for `{}` have been saved. Please run `loved sync` first.",
+                username
+            );
+            return Ok(());
+        }
+    };
+
+    if let Some(limit) = l.limit {
+        loved_tracks.truncate(limit);
+    }
+
+    output::print_report(&loved_tracks, l.output)
+}
+
+async fn sync(s: Sync, config: Config) -> Result<()> {
+    let username = s
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+    let locale = utils::get_locale();
+
+    eprintln!("Fetching loved tracks for `{}`...", username);
+    let loved_tracks = lastfm::loved_tracks::fetch_loved_tracks(&username, &config.api_key).await?;
+    let loved_tracks = SavedLovedTracks::from_loved_tracks(&loved_tracks);
+    eprintln!(
+        "{} loved tracks retrieved",
+        loved_tracks.len().to_formatted_string(&locale)
+    );
+
+    if app::uses_sqlite_database(&username, &config) {
+        let pool = db::get_sqlite_pool().await?;
+        let changed = db::replace_loved_tracks(&pool, &loved_tracks).await?;
+        eprintln!(
+            "Updated {} scrobbles in the database",
+            changed.to_formatted_string(&locale)
+        );
+    } else {
+        data::save_loved_tracks(&loved_tracks, &username)?;
+    }
+
+    if let Some(file_format) = data::find_which_file_exists(&username)? {
+        let mut saved_scrobbles = data::load_from_file(&username, file_format)?;
+        let changed = saved_scrobbles.update_loved(&loved_tracks);
+
+        if changed > 0 {
+            data::overwrite_file(&saved_scrobbles, &username, file_format)?;
+        }
+        eprintln!(
+            "Updated {} scrobbles in `{}.{}`",
+            changed.to_formatted_string(&locale),
+            username,
+            file_format
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::reports::loyalty::{ArtistLoyalty, ForgottenThresholds};
+use crate::{app, output};
+
+pub async fn loyalty(l: app::Loyalty, config: Config) -> Result<()> {
+    let username = match l.username {
+        Some(username) => username,
+        None => config.default_username.clone(),
+    };
+
+    let mut saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let excluded = app::exclude_scrobbles(&mut saved_scrobbles, l.include_excluded)?;
+
+    let forgotten = ForgottenThresholds {
+        min_plays: l.favourite_plays,
+        months: l.forgotten_months,
+    };
+
+    let mut loyalty = ArtistLoyalty::new(saved_scrobbles.as_slice(), forgotten, l.limit);
+    loyalty.excluded_scrobbles = excluded;
+    output::print_report(&loyalty, l.output)
+}
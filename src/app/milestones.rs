@@ -0,0 +1,61 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::data::db;
+use crate::reports::milestones::{self, Milestones, ScrobbleNumber};
+use crate::{app, data, output};
+
+pub async fn milestones(m: app::Milestones, config: Config) -> Result<()> {
+    let username = match m.username {
+        Some(username) => username,
+        None => config.default_username.clone(),
+    };
+
+    // Looking up a single scrobble in the database does not require loading the entire history,
+    // unless scrobbles are excluded, since the exclusion rules cannot be applied in the query
+    if let Some(number) = m.number {
+        if (m.include_excluded || data::load_exclusion_rules()?.is_none())
+            && data::find_which_file_exists(&username)?.is_none()
+            && app::uses_sqlite_database(&username, &config)
+            && db::check_if_sqlite_database_exists()?
+        {
+            let pool = db::get_sqlite_pool().await?;
+            let scrobble_number = ScrobbleNumber {
+                number,
+                total_scrobbles: db::count_scrobbles(&pool).await? as i32,
+                scrobble: if number > 0 {
+                    db::get_scrobble_by_number(&pool, number as i64).await?
+                } else {
+                    None
+                },
+                excluded_scrobbles: 0,
+            };
+
+            return output::print_report(&scrobble_number, m.output);
+        }
+    }
+
+    let mut saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let excluded = app::exclude_scrobbles(&mut saved_scrobbles, m.include_excluded)?;
+    let scrobbles = saved_scrobbles.as_slice();
+
+    match m.number {
+        Some(number) => {
+            let scrobble_number = ScrobbleNumber {
+                number,
+                total_scrobbles: scrobbles.len() as i32,
+                scrobble: milestones::find_by_number(scrobbles, number).cloned(),
+                excluded_scrobbles: excluded,
+            };
+            output::print_report(&scrobble_number, m.output)
+        }
+        None => {
+            let mut report = Milestones::new(scrobbles, m.every);
+            report.excluded_scrobbles = excluded;
+            output::print_report(&report, m.output)
+        }
+    }
+}
@@ -1,9 +1,47 @@
+use anyhow::Result;
 use clap::Parser;
+use num_format::ToFormattedString;
 
+use crate::app::charts::ChartsSubCommand;
 use crate::app::config::ConfigSubCommand;
+use crate::app::enrich::EnrichSubCommand;
+use crate::app::export::ExportSubCommand;
+use crate::app::loved::LovedSubCommand;
+use crate::app::report::ReportSubCommand;
+use crate::app::stats::StatsSubCommand;
+use crate::config::StorageFormat;
+use crate::data;
+use crate::data::db;
+use crate::models::artist_tags::ArtistTagsCache;
+use crate::models::exclusion_rules::{ExclusionRules, Exclusions};
+use crate::models::name_rules::TitleCleaner;
+use crate::models::saved_scrobbles::SavedScrobbles;
+use crate::output::OutputFormat;
+use crate::query::{HumanDate, ScrobbleFilter, TextFilter};
+use crate::reports::anomalies::AnomalyKind;
+use crate::reports::on_this_day::MonthDay;
+use crate::reports::query_results::{GroupBy, SortBy};
+use crate::stats::Period;
+use crate::utils;
 
+pub mod anomalies;
+pub mod binges;
+pub mod charts;
+pub mod compare;
 pub mod config;
+pub mod enrich;
+pub mod export;
 pub mod fetch;
+pub mod loved;
+pub mod loyalty;
+pub mod milestones;
+pub mod normalize;
+pub mod on_this_day;
+pub mod query;
+pub mod report;
+pub mod retime;
+pub mod search;
+pub mod sessions;
 pub mod stats;
 
 /// Provides commands to download your listening history from Last.fm and export it to several formats
@@ -17,11 +55,108 @@ pub struct Opts {
 /// Application subcommands, which include configuring the application, fetching data from Last.fm, and crunching statistics
 #[derive(Parser)]
 pub enum SubCommand {
+    Anomalies(Anomalies),
+    Binges(Binges),
+    Charts(Charts),
+    Compare(Compare),
     Config(Config),
+    Enrich(Enrich),
+    Export(Export),
     Fetch(Fetch),
+    Loved(Loved),
+    Loyalty(Loyalty),
+    Milestones(Milestones),
+    Normalize(Normalize),
+    #[clap(name = "onthisday")]
+    OnThisDay(OnThisDay),
+    Query(Query),
+    Report(Report),
+    Retime(Retime),
+    Search(Search),
+    Sessions(Sessions),
     Stats(Stats),
 }
 
+/// A subcommand for finding double scrobbles, impossible bursts of scrobbles, and scrobbles dated in the future or before Last.fm existed
+#[derive(Parser)]
+pub struct Anomalies {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Only this kind of anomaly. May be repeated.
+    #[clap(short, long, arg_enum)]
+    pub kind: Vec<AnomalyKind>,
+    /// A repeat of the previous track within this many seconds is a near duplicate
+    #[clap(short, long, default_value = "60")]
+    pub duplicate_seconds: i64,
+    /// Scrobbles closer together than this many seconds cannot both have been played
+    #[clap(short, long, default_value = "30")]
+    pub min_gap_seconds: i64,
+    /// The maximum number of suspicious scrobbles to list
+    #[clap(short, long, default_value = "50")]
+    pub limit: usize,
+    /// Moves the suspicious scrobbles out of the saved scrobbles and into a quarantine file
+    #[clap(long, takes_value = false)]
+    pub quarantine: bool,
+    /// The format to write the anomalies in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// A subcommand for finding binged tracks, weeks dominated by one artist, and whether those phases have faded
+#[derive(Parser)]
+pub struct Binges {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Plays of one track within a day that make a binge
+    #[clap(short, long, default_value = "5")]
+    pub day_plays: i32,
+    /// Plays of one track within a week that make a binge
+    #[clap(short, long, default_value = "15")]
+    pub week_plays: i32,
+    /// The percentage of a week's scrobbles one artist needs to dominate the week
+    #[clap(short, long, default_value = "50")]
+    pub share: f64,
+    /// Weeks with fewer scrobbles are never dominated by one artist
+    #[clap(short, long, default_value = "20")]
+    pub min_week_scrobbles: i32,
+    /// Weeks without a play, up to the newest scrobble, after which a binged track has decayed
+    #[clap(long, default_value = "8")]
+    pub decay_weeks: i64,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The format to write the binges in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// Provides commands for mirroring Last.fm's weekly charts and comparing them with saved scrobbles
+#[derive(Parser)]
+pub struct Charts {
+    #[clap(subcommand)]
+    pub subcmd: ChartsSubCommand,
+}
+
+/// A subcommand for comparing the listening taste of two users with saved files
+#[derive(Parser)]
+pub struct Compare {
+    /// The first Last.fm username
+    pub user_a: String,
+    /// The second Last.fm username
+    pub user_b: String,
+    /// The period to compare, ending now
+    #[clap(short, long, arg_enum, default_value = "overall")]
+    pub period: Period,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The format to write the comparison in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
 /// Provides commands for interacting with the application's configuration file
 #[derive(Parser)]
 pub struct Config {
@@ -29,6 +164,20 @@ pub struct Config {
     pub subcmd: ConfigSubCommand,
 }
 
+/// Provides commands for looking up additional data about saved scrobbles from Last.fm
+#[derive(Parser)]
+pub struct Enrich {
+    #[clap(subcommand)]
+    pub subcmd: EnrichSubCommand,
+}
+
+/// Provides commands for exporting saved scrobbles for other tools
+#[derive(Parser)]
+pub struct Export {
+    #[clap(subcommand)]
+    pub subcmd: ExportSubCommand,
+}
+
 /// A subcommand for fetching your listening history from Last.fm
 #[derive(Parser)]
 pub struct Fetch {
@@ -55,10 +204,342 @@ pub struct Fetch {
     pub current_day: bool,
 }
 
+/// Provides commands for syncing and listing loved tracks
+#[derive(Parser)]
+pub struct Loved {
+    #[clap(subcommand)]
+    pub subcmd: LovedSubCommand,
+}
+
+/// A subcommand for classifying artists as core, seasonal, occasional, one-off or forgotten favourites
+#[derive(Parser)]
+pub struct Loyalty {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// The fewest plays of an artist that can become a forgotten favourite
+    #[clap(short, long, default_value = "50")]
+    pub favourite_plays: i32,
+    /// Months without a play, up to the newest scrobble, after which a favourite is forgotten
+    #[clap(short = 'm', long, default_value = "12")]
+    pub forgotten_months: i32,
+    /// The number of most played artists to list
+    #[clap(short, long, default_value = "25")]
+    pub limit: usize,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The format to write the artist lifecycles in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// A subcommand for finding milestone scrobbles, such as the 1st, 1,000th, and 10,000th scrobble
+#[derive(Parser)]
+pub struct Milestones {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Lists the 1st and every Nth scrobble, rather than the 1st, 1,000th, and every 10,000th
+    #[clap(short, long)]
+    pub every: Option<i32>,
+    /// Looks up a single scrobble by its number, e.g. 123456 for the 123,456th scrobble
+    #[clap(short, long)]
+    pub number: Option<i32>,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The format to write the milestones in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// A subcommand for renaming saved scrobbles with the artist, album and track name rules in `names.json`
+#[derive(Parser)]
+pub struct Normalize {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+}
+
+/// A subcommand for looking back at what was scrobbled on the same day in previous years
+#[derive(Parser)]
+pub struct OnThisDay {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// The day to look back on, in MM-DD format. Defaults to today.
+    #[clap(short, long)]
+    pub date: Option<MonthDay>,
+    /// The number of top tracks and artists to list for each year
+    #[clap(short, long, default_value = "5")]
+    pub top: usize,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The format to write the retrospective in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// A subcommand for listing, counting or grouping the scrobbles that match a set of filters
+#[derive(Parser)]
+pub struct Query {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    #[clap(flatten)]
+    pub filter: FilterArgs,
+    /// How to sort the matching scrobbles
+    #[clap(short, long, arg_enum, default_value = "date")]
+    pub sort: SortBy,
+    /// Reverses the order of the scrobbles or groups, e.g. oldest first
+    #[clap(short, long, takes_value = false)]
+    pub reverse: bool,
+    /// The maximum number of scrobbles or groups to list
+    #[clap(short, long)]
+    pub limit: Option<usize>,
+    /// Prints only the number of matching scrobbles
+    #[clap(short, long, takes_value = false, conflicts_with = "group-by")]
+    pub count: bool,
+    /// Counts the matching scrobbles in each group instead of listing them
+    #[clap(short, long, arg_enum)]
+    pub group_by: Option<GroupBy>,
+    /// The format to write the results in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// Provides commands for generating standalone reports from a saved file
+#[derive(Parser)]
+pub struct Report {
+    #[clap(subcommand)]
+    pub subcmd: ReportSubCommand,
+}
+
+/// A subcommand for recomputing the local times of saved scrobbles with the home time zone and the overrides in `timezones.json`
+#[derive(Parser)]
+pub struct Retime {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+}
+
+/// A subcommand for searching track, artist and album names, ranking the best matches first
+#[derive(Parser)]
+pub struct Search {
+    /// The words to search for. Each word also matches longer words it begins, e.g. `radio` matches `Radiohead`.
+    #[clap(required = true)]
+    pub search: Vec<String>,
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// The maximum number of tracks to list
+    #[clap(short, long, default_value = "25")]
+    pub limit: usize,
+    /// The format to write the results in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
+/// A subcommand for splitting the listening history into sessions of continuous listening
+#[derive(Parser)]
+pub struct Sessions {
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Minutes without a scrobble that end a listening session
+    #[clap(short, long, default_value = "30")]
+    pub gap: i64,
+    /// Different tracks from one album that must be played back-to-back to count as a full album
+    #[clap(short, long, default_value = "5")]
+    pub min_album_tracks: usize,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The format to write the sessions in
+    #[clap(short, long, arg_enum, default_value = "table")]
+    pub output: OutputFormat,
+}
+
 /// A subcommand for calculating stats from a saved file
 #[derive(Parser)]
 pub struct Stats {
+    #[clap(subcommand)]
+    pub subcmd: Option<StatsSubCommand>,
     /// A Last.fm username
-    #[clap(short)]
+    #[clap(short, global = true)]
     pub username: Option<String>,
+    /// The format to write the stats in
+    #[clap(short, long, arg_enum, default_value = "table", global = true)]
+    pub output: OutputFormat,
+    #[clap(flatten)]
+    pub filter: FilterArgs,
+    /// Draws charts of top artists and scrobbles over time after the stats
+    #[clap(long, takes_value = false)]
+    pub charts: bool,
+    /// Draws the charts with plain ASCII characters, rather than detecting Unicode support
+    #[clap(long, takes_value = false, requires = "charts")]
+    pub ascii: bool,
+}
+
+/// Restricts the scrobbles a command works on
+#[derive(Parser)]
+pub struct FilterArgs {
+    /// Only scrobbles from this day on, e.g. 2022, 2022-03, "March 2022", 2022-03-21, "last month" or "3 weeks ago"
+    #[clap(long, global = true)]
+    pub from: Option<HumanDate>,
+    /// Only scrobbles up to and including this day, in the same forms as --from
+    #[clap(long, global = true)]
+    pub to: Option<HumanDate>,
+    /// Only scrobbles by this artist
+    #[clap(long, global = true)]
+    pub artist: Option<String>,
+    /// Only scrobbles from this album
+    #[clap(long, global = true)]
+    pub album: Option<String>,
+    /// Only scrobbles of this track
+    #[clap(long, global = true)]
+    pub track: Option<String>,
+    /// Only scrobbles whose artist, album or track contains this text
+    #[clap(long, global = true)]
+    pub search: Option<String>,
+    /// Matches --artist, --album, --track and --search as regular expressions instead
+    #[clap(long, takes_value = false, global = true)]
+    pub regex: bool,
+    /// Only scrobbles of loved tracks
+    #[clap(long, takes_value = false, global = true)]
+    pub loved_only: bool,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false, global = true)]
+    pub include_excluded: bool,
+}
+
+impl FilterArgs {
+    pub fn to_filter(&self) -> Result<ScrobbleFilter> {
+        let text = |text: &Option<String>| -> Result<Option<TextFilter>> {
+            match text {
+                Some(text) if self.regex => Ok(Some(TextFilter::regex(text)?)),
+                Some(text) => Ok(Some(TextFilter::exact(text))),
+                None => Ok(None),
+            }
+        };
+
+        Ok(ScrobbleFilter {
+            // A month or year as --from starts on its first day, and as --to ends on its last
+            from: self.from.map(|from| from.start),
+            to: self.to.map(|to| to.end),
+            artist: text(&self.artist)?,
+            album: text(&self.album)?,
+            track: text(&self.track)?,
+            search: match &self.search {
+                Some(search) if self.regex => Some(TextFilter::regex(search)?),
+                Some(search) => Some(TextFilter::contains(search)),
+                None => None,
+            },
+            loved_only: self.loved_only,
+        })
+    }
+}
+
+/// Loads the saved scrobbles for a user from a file or the Sqlite database, or explains why no
+/// scrobbles could be loaded
+async fn load_saved_scrobbles(
+    username: &str,
+    config: &crate::config::Config,
+) -> Result<Option<SavedScrobbles>> {
+    if data::find_which_file_exists(username)?.is_some() {
+        return Ok(Some(data::load_from_any_file(username)?));
+    }
+
+    if uses_sqlite_database(username, config) && db::check_if_sqlite_database_exists()? {
+        eprintln!("Loading saved scrobbles from database...");
+        let pool = db::get_sqlite_pool().await?;
+        return Ok(Some(db::get_scrobbles(&pool).await?));
+    }
+
+    eprintln!(
+        "No saved scrobbles for `{}` exist. Please fetch scrobbles first.",
+        username
+    );
+
+    Ok(None)
+}
+
+/// Loads the saved scrobbles for a user that match a filter
+///
+/// With the Sqlite database the filter is applied in the query, so only matching scrobbles are
+/// loaded.
+async fn load_filtered_scrobbles(
+    username: &str,
+    config: &crate::config::Config,
+    filter: &ScrobbleFilter,
+) -> Result<Option<SavedScrobbles>> {
+    if filter.is_empty() || data::find_which_file_exists(username)?.is_some() {
+        let saved_scrobbles = load_saved_scrobbles(username, config).await?;
+        return Ok(saved_scrobbles.map(|s| filter.apply(s)));
+    }
+
+    if uses_sqlite_database(username, config) && db::check_if_sqlite_database_exists()? {
+        eprintln!("Loading matching scrobbles from database...");
+        let pool = db::get_sqlite_pool().await?;
+        let saved_scrobbles = db::get_matching_scrobbles(&pool, filter).await?;
+        return Ok(Some(filter.apply(saved_scrobbles)));
+    }
+
+    // Explains why nothing could be loaded
+    load_saved_scrobbles(username, config).await
+}
+
+/// The cleanup from the name rules, or the built-in cleanup when there are no name rules
+fn load_title_cleaner() -> Result<TitleCleaner> {
+    data::load_name_rules()?.unwrap_or_default().title_cleaner()
+}
+
+/// Removes the scrobbles matched by the user's exclusion rules, unless they are included, and
+/// returns the number of scrobbles removed
+fn exclude_scrobbles(saved_scrobbles: &mut SavedScrobbles, include_excluded: bool) -> Result<i32> {
+    let excluded = remove_excluded_scrobbles(saved_scrobbles, include_excluded)?;
+    print_excluded(excluded);
+
+    Ok(excluded)
+}
+
+/// Removes the scrobbles matched by the user's exclusion rules like
+/// [exclude_scrobbles](fn.exclude_scrobbles.html), without saying how many were removed
+fn remove_excluded_scrobbles(
+    saved_scrobbles: &mut SavedScrobbles,
+    include_excluded: bool,
+) -> Result<i32> {
+    let rules = match data::load_exclusion_rules()? {
+        Some(rules) if !include_excluded => rules,
+        _ => return Ok(0),
+    };
+
+    Ok(saved_scrobbles.exclude(&compile_exclusions(&rules)?))
+}
+
+fn print_excluded(excluded: i32) {
+    if excluded > 0 {
+        eprintln!(
+            "Excluded {} scrobbles with the exclusion rules. Use --include-excluded to include them.",
+            excluded.to_formatted_string(&utils::get_locale())
+        );
+    }
+}
+
+/// Compiles exclusion rules, with the cached artist tags if the rules exclude any tags
+fn compile_exclusions(rules: &ExclusionRules) -> Result<Exclusions> {
+    let tags = if rules.tags.is_empty() {
+        ArtistTagsCache::default()
+    } else {
+        data::load_artist_tags()?
+    };
+
+    rules.exclusions(&tags)
+}
+
+/// The Sqlite database is named after, and only holds the scrobbles of, the default user
+fn uses_sqlite_database(username: &str, config: &crate::config::Config) -> bool {
+    matches!(config.storage_format, StorageFormat::Sqlite) && username == config.default_username
 }
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use num_format::ToFormattedString;
+
+use crate::config::Config;
+use crate::data::db;
+use crate::{app, data, utils};
+
+pub async fn normalize(n: app::Normalize, config: Config) -> Result<()> {
+    let username = n
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+    let locale = utils::get_locale();
+
+    let rules = match data::load_name_rules()? {
+        Some(rules) => rules.canonicalizer()?,
+        None => {
+            let file = data::create_name_rules()?;
+            eprintln!(
+                "Created empty name rules in `{}`. Add aliases and rewrites to it, then run `normalize` again.",
+                file.display()
+            );
+            return Ok(());
+        }
+    };
+
+    if app::uses_sqlite_database(&username, &config) && db::check_if_sqlite_database_exists()? {
+        let pool = db::get_sqlite_pool().await?;
+        let changed = db::normalize_names(&pool, &rules).await?;
+        eprintln!(
+            "Renamed {} scrobbles in the database",
+            changed.to_formatted_string(&locale)
+        );
+    }
+
+    if let Some(file_format) = data::find_which_file_exists(&username)? {
+        let mut saved_scrobbles = data::load_from_file(&username, file_format)?;
+        let changed = saved_scrobbles.normalize(&rules);
+
+        if changed > 0 {
+            data::overwrite_file(&saved_scrobbles, &username, file_format)?;
+        }
+        eprintln!(
+            "Renamed {} scrobbles in `{}.{}`",
+            changed.to_formatted_string(&locale),
+            username,
+            file_format
+        );
+    }
+
+    Ok(())
+}
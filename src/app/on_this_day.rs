@@ -0,0 +1,31 @@
+use anyhow::Result;
+use chrono::{Datelike, Local};
+
+use crate::config::Config;
+use crate::reports::on_this_day::{MonthDay, OnThisDay};
+use crate::{app, output};
+
+pub async fn on_this_day(o: app::OnThisDay, config: Config) -> Result<()> {
+    let username = match o.username {
+        Some(username) => username,
+        None => config.default_username.clone(),
+    };
+
+    let mut saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let excluded = app::exclude_scrobbles(&mut saved_scrobbles, o.include_excluded)?;
+    saved_scrobbles.clean_titles(&app::load_title_cleaner()?);
+
+    let today = Local::today().naive_local();
+    let date = o.date.unwrap_or_else(|| MonthDay::from_date(today));
+
+    let mut on_this_day = OnThisDay::new(saved_scrobbles.as_slice(), date, today.year(), o.top);
+    on_this_day.excluded_scrobbles = excluded;
+    if on_this_day.years.is_empty() {
+        eprintln!("Nothing was scrobbled on {} in previous years.", date);
+    }
+
+    output::print_report(&on_this_day, o.output)
+}
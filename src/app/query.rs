@@ -0,0 +1,52 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::output::OutputFormat;
+use crate::reports::query_results::{GroupCounts, ScrobbleCount, ScrobbleList};
+use crate::{app, output};
+
+pub async fn query(q: app::Query, config: Config) -> Result<()> {
+    let username = match q.username {
+        Some(username) => username,
+        None => config.default_username.clone(),
+    };
+
+    let filter = q.filter.to_filter()?;
+    let mut saved_scrobbles =
+        match app::load_filtered_scrobbles(&username, &config, &filter).await? {
+            Some(saved_scrobbles) => saved_scrobbles,
+            None => return Ok(()),
+        };
+
+    let excluded = app::exclude_scrobbles(&mut saved_scrobbles, q.filter.include_excluded)?;
+
+    if q.count {
+        let count = ScrobbleCount {
+            scrobbles: saved_scrobbles.total_saved_scrobbles(),
+            excluded_scrobbles: excluded,
+        };
+        return output::print_report(&count, q.output);
+    }
+
+    // Other formats are likely read by scripts, which get the empty report instead
+    if saved_scrobbles.is_empty() && q.output == OutputFormat::Table {
+        eprintln!("No saved scrobbles match the filters.");
+        return Ok(());
+    }
+
+    match q.group_by {
+        Some(group_by) => {
+            saved_scrobbles.clean_titles(&app::load_title_cleaner()?);
+            let mut groups =
+                GroupCounts::new(saved_scrobbles.as_slice(), group_by, q.reverse, q.limit);
+            groups.excluded_scrobbles = excluded;
+            output::print_report(&groups, q.output)
+        }
+        None => {
+            let mut list =
+                ScrobbleList::new(saved_scrobbles.as_slice(), q.sort, q.reverse, q.limit);
+            list.excluded_scrobbles = excluded;
+            output::print_report(&list, q.output)
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::Datelike;
+use clap::Parser;
+
+use crate::config::Config;
+use crate::models::saved_scrobbles::SavedScrobbles;
+use crate::reports::year_review::YearReview;
+use crate::reports::DocumentFormat;
+use crate::{app, data};
+
+#[derive(Parser)]
+pub enum ReportSubCommand {
+    Year(Year),
+}
+
+/// Builds a "year in review" document for a single calendar year
+#[derive(Parser)]
+pub struct Year {
+    /// The calendar year to review, e.g. 2021
+    pub year: i32,
+    /// A Last.fm username
+    #[clap(short)]
+    pub username: Option<String>,
+    /// Includes the scrobbles matched by the exclusion rules in `exclusions.json`
+    #[clap(long, takes_value = false)]
+    pub include_excluded: bool,
+    /// The document format to generate
+    #[clap(short, long, arg_enum, default_value = "html")]
+    pub output: DocumentFormat,
+    /// Writes the report to a file instead of stdout
+    #[clap(short = 'w', long)]
+    pub file: Option<PathBuf>,
+}
+
+pub async fn report(r: app::Report, config: Config) -> Result<()> {
+    match r.subcmd {
+        ReportSubCommand::Year(y) => year(y, config).await,
+    }
+}
+
+async fn year(y: Year, config: Config) -> Result<()> {
+    let username = y
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+
+    let mut saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    // Only the scrobbles of the year are counted as excluded from its review
+    let year = y.year;
+    let in_year = |saved_scrobbles: &SavedScrobbles| {
+        saved_scrobbles
+            .as_slice()
+            .iter()
+            .filter(|s| s.date().year() == year)
+            .count() as i32
+    };
+    let before = in_year(&saved_scrobbles);
+    app::remove_excluded_scrobbles(&mut saved_scrobbles, y.include_excluded)?;
+    let excluded = before - in_year(&saved_scrobbles);
+    app::print_excluded(excluded);
+    saved_scrobbles.clean_titles(&app::load_title_cleaner()?);
+
+    let durations = data::load_track_durations()?;
+    let mut review = YearReview::new(saved_scrobbles.as_slice(), y.year, &durations);
+    review.excluded_scrobbles = excluded;
+    if review.total_scrobbles == 0 {
+        eprintln!("No scrobbles were found for {}.", y.year);
+        return Ok(());
+    }
+
+    let document = review.render(y.output);
+    match y.file {
+        Some(path) => {
+            fs::write(&path, document)?;
+            eprintln!("Report saved to `{}`", path.display());
+        }
+        None => println!("{}", document),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::Result;
+use num_format::ToFormattedString;
+
+use crate::config::Config;
+use crate::data::db;
+use crate::{app, data, utils};
+
+pub async fn retime(r: app::Retime, config: Config) -> Result<()> {
+    let username = r
+        .username
+        .unwrap_or_else(|| config.default_username.clone());
+    let locale = utils::get_locale();
+
+    if data::load_time_zone_overrides()?.is_none() {
+        let file = data::create_time_zone_overrides()?;
+        eprintln!(
+            "Created empty time zone overrides in `{}`. Add the dates spent in other time zones to it, then run `retime` again.",
+            file.display()
+        );
+    }
+    let local_times = data::load_local_times(&config)?;
+
+    if app::uses_sqlite_database(&username, &config) && db::check_if_sqlite_database_exists()? {
+        let pool = db::get_sqlite_pool().await?;
+        let changed = db::retime_scrobbles(&pool, &local_times).await?;
+        eprintln!(
+            "Changed the local time of {} scrobbles in the database",
+            changed.to_formatted_string(&locale)
+        );
+    }
+
+    if let Some(file_format) = data::find_which_file_exists(&username)? {
+        let mut saved_scrobbles = data::load_from_file(&username, file_format)?;
+        let changed = saved_scrobbles.retime(&local_times);
+
+        if changed > 0 {
+            data::overwrite_file(&saved_scrobbles, &username, file_format)?;
+        }
+        eprintln!(
+            "Changed the local time of {} scrobbles in `{}.{}`",
+            changed.to_formatted_string(&locale),
+            username,
+            file_format
+        );
+    }
+
+    Ok(())
+}
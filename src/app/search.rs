@@ -0,0 +1,71 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::data::db;
+use crate::output::OutputFormat;
+use crate::reports::search::{self, SearchResults, SearchTerms};
+use crate::{app, data, output};
+
+pub async fn search(s: app::Search, config: Config) -> Result<()> {
+    let username = match s.username {
+        Some(username) => username,
+        None => config.default_username.clone(),
+    };
+
+    let text = s.search.join(" ");
+    let terms = SearchTerms::parse(&text);
+    if terms.is_empty() {
+        eprintln!("Search for at least one word.");
+        return Ok(());
+    }
+
+    let mut corrected = None;
+
+    // The database's full-text index answers searches without loading the entire history
+    let results = if data::find_which_file_exists(&username)?.is_none()
+        && app::uses_sqlite_database(&username, &config)
+        && db::check_if_sqlite_database_exists()?
+    {
+        let pool = db::get_sqlite_pool().await?;
+        let mut results = db::search_scrobbles(&pool, &terms.fts_query(), s.limit as i64).await?;
+        if results.is_empty() {
+            let vocabulary = db::get_search_vocabulary(&pool).await?;
+            if let Some(terms) = terms.corrected(&vocabulary) {
+                results = db::search_scrobbles(&pool, &terms.fts_query(), s.limit as i64).await?;
+                corrected = Some(terms);
+            }
+        }
+        results
+    } else {
+        let saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+            Some(saved_scrobbles) => saved_scrobbles,
+            None => return Ok(()),
+        };
+        let scrobbles = saved_scrobbles.as_slice();
+
+        let mut results = search::search_scrobbles(scrobbles, &terms, s.limit);
+        if results.is_empty() {
+            if let Some(terms) = terms.corrected(&search::vocabulary(scrobbles)) {
+                results = search::search_scrobbles(scrobbles, &terms, s.limit);
+                corrected = Some(terms);
+            }
+        }
+        results
+    };
+
+    // Other formats are likely read by scripts, which get the empty report instead
+    if results.is_empty() && s.output == OutputFormat::Table {
+        eprintln!("No saved scrobbles match `{}`.", text);
+        return Ok(());
+    }
+
+    let results = SearchResults {
+        search: text,
+        // A correction that matches nothing either is not worth showing
+        corrected: corrected
+            .filter(|_| !results.is_empty())
+            .map(|terms| terms.words.join(" ")),
+        results,
+    };
+    output::print_report(&results, s.output)
+}
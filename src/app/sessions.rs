@@ -0,0 +1,26 @@
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::reports::sessions::SessionStats;
+use crate::{app, output};
+
+pub async fn sessions(s: app::Sessions, config: Config) -> Result<()> {
+    let username = match s.username {
+        Some(username) => username,
+        None => config.default_username.clone(),
+    };
+
+    let mut saved_scrobbles = match app::load_saved_scrobbles(&username, &config).await? {
+        Some(saved_scrobbles) => saved_scrobbles,
+        None => return Ok(()),
+    };
+    let excluded = app::exclude_scrobbles(&mut saved_scrobbles, s.include_excluded)?;
+
+    eprintln!("Detecting listening sessions for {}...\n", &username);
+    let mut session_stats =
+        SessionStats::new(saved_scrobbles.as_slice(), s.gap, s.min_album_tracks);
+    session_stats.excluded_scrobbles = excluded;
+    output::print_report(&session_stats, s.output)?;
+
+    Ok(())
+}
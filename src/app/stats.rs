@@ -1,30 +1,260 @@
 use anyhow::Result;
+use chrono::Local;
+use clap::Parser;
 
 use crate::config::Config;
-use crate::{app, data};
+use crate::models::name_rules::TitleCleaner;
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::OutputFormat;
+use crate::reports::cleanup::CleanupPreview;
+use crate::reports::diversity;
+use crate::reports::forecast;
+use crate::reports::genres::{GenreShares, Interval};
+use crate::reports::listening_time::{ListeningTime, TimeGrouping};
+use crate::stats::Period;
+use crate::terminal_charts::{self, Charset};
+use crate::{app, data, lastfm, output, utils};
 
-pub fn stats(s: app::Stats, config: Config) -> Result<()> {
+#[derive(Parser)]
+pub enum StatsSubCommand {
+    Cleanup(Cleanup),
+    Diversity(Diversity),
+    Forecast(Forecast),
+    Genres(Genres),
+    Time(Time),
+}
+
+/// Previews the tracks and albums that the title cleanup merges, e.g. a track and its remaster
+#[derive(Parser)]
+pub struct Cleanup {
+    /// The maximum number of tracks and of albums to list
+    #[clap(short, long, default_value = "25")]
+    pub limit: usize,
+}
+
+/// Reports how evenly plays are spread across artists, overall and month over month
+#[derive(Parser)]
+pub struct Diversity {
+    /// The period to measure, ending now
+    #[clap(short, long, arg_enum, default_value = "overall")]
+    pub period: Period,
+}
+
+/// Projects when the next scrobble milestone, or a number of plays of an artist, will be reached
+#[derive(Parser)]
+pub struct Forecast {
+    /// The number of recent days the listening rate is measured over
+    #[clap(short, long, default_value = "90")]
+    pub window: i64,
+    /// The scrobble count to project. Defaults to the next milestone, e.g. 100,000 or 250,000.
+    #[clap(short, long)]
+    pub target: Option<i32>,
+    /// An artist to project plays for
+    #[clap(short = 'a', long)]
+    pub plays_of: Option<String>,
+    /// The plays of the artist to project. Defaults to the artist's next milestone.
+    #[clap(short, long, requires = "plays-of")]
+    pub plays: Option<i32>,
+}
+
+/// Reports the share of each genre over time, using the artist tags looked up with `enrich tags`
+#[derive(Parser)]
+pub struct Genres {
+    /// The length of the periods to compare
+    #[clap(short, long, arg_enum, default_value = "year")]
+    pub by: Interval,
+    /// The number of genres to show for each period
+    #[clap(short, long, default_value = "8")]
+    pub top: usize,
+}
+
+/// Reports hours listened, using the track durations looked up with `enrich durations`
+#[derive(Parser)]
+pub struct Time {
+    /// How to group the hours listened
+    #[clap(short, long, arg_enum, default_value = "month")]
+    pub by: TimeGrouping,
+    /// The maximum number of groups to list
+    #[clap(short, long, default_value = "25")]
+    pub limit: usize,
+}
+
+pub async fn stats(s: app::Stats, config: Config) -> Result<()> {
     let username = match s.username {
         Some(username) => username,
-        None => config.default_username,
+        None => config.default_username.clone(),
     };
 
-    match data::find_which_file_exists(&username)? {
-        Some(_) => true,
-        None => {
-            println!(
-                "No file for `{}` exists. Stats cannot be calculated.",
-                &username
+    let filter = s.filter.to_filter()?;
+    let mut saved_scrobbles =
+        match app::load_filtered_scrobbles(&username, &config, &filter).await? {
+            Some(saved_scrobbles) => saved_scrobbles,
+            None => return Ok(()),
+        };
+    if saved_scrobbles.is_empty() && !filter.is_empty() {
+        eprintln!("No saved scrobbles match the filters.");
+        return Ok(());
+    }
+
+    let excluded = app::exclude_scrobbles(&mut saved_scrobbles, s.filter.include_excluded)?;
+
+    let cleaner = app::load_title_cleaner()?;
+    // The preview compares the names as they were saved, and every other report counts the
+    // names that the cleanup merges as one
+    if let Some(StatsSubCommand::Cleanup(c)) = &s.subcmd {
+        return cleanup(c, saved_scrobbles.as_slice(), &cleaner, excluded, s.output);
+    }
+    saved_scrobbles.clean_titles(&cleaner);
+
+    match s.subcmd {
+        Some(StatsSubCommand::Cleanup(_)) => unreachable!("The cleanup preview is handled above"),
+        Some(StatsSubCommand::Diversity(d)) => {
+            let mut diversity = diversity::Diversity::new(
+                saved_scrobbles.as_slice(),
+                d.period,
+                utils::get_current_unix_timestamp(),
             );
-            return Ok(());
+            diversity.excluded_scrobbles = excluded;
+            output::print_report(&diversity, s.output)
+        }
+        Some(StatsSubCommand::Forecast(f)) => {
+            let filtered = !filter.is_empty();
+            forecast(
+                f,
+                saved_scrobbles.as_slice(),
+                filtered,
+                excluded,
+                &username,
+                &config,
+                s.output,
+            )
+            .await
+        }
+        Some(StatsSubCommand::Genres(g)) => {
+            genres(g, saved_scrobbles.as_slice(), excluded, s.output)
+        }
+        Some(StatsSubCommand::Time(t)) => time(t, saved_scrobbles.as_slice(), excluded, s.output),
+        None => {
+            eprintln!("Crunching stats for {}...\n", &username);
+            let mut stats = saved_scrobbles.generate_stats();
+            stats.excluded_scrobbles = excluded;
+            output::print_report(&stats, s.output)?;
+
+            // Charts would make other formats unreadable by the programs they are piped into
+            if s.charts && s.output != OutputFormat::Table {
+                eprintln!("Charts are only drawn with the table output format.");
+            } else if s.charts {
+                let charset = if s.ascii {
+                    Charset::Ascii
+                } else {
+                    Charset::detect()
+                };
+                let width = terminal_charts::terminal_width();
+                println!(
+                    "\n{}",
+                    terminal_charts::render_stats_charts(
+                        saved_scrobbles.as_slice(),
+                        width,
+                        charset
+                    )
+                );
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn cleanup(
+    c: &Cleanup,
+    scrobbles: &[SavedScrobble],
+    cleaner: &TitleCleaner,
+    excluded: i32,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut preview = CleanupPreview::new(scrobbles, cleaner, c.limit);
+    preview.excluded_scrobbles = excluded;
+    if preview.is_empty() {
+        eprintln!("The title cleanup does not merge any tracks or albums.");
+        return Ok(());
+    }
+
+    output::print_report(&preview, format)
+}
+
+async fn forecast(
+    f: Forecast,
+    scrobbles: &[SavedScrobble],
+    filtered: bool,
+    excluded: i32,
+    username: &str,
+    config: &Config,
+    format: OutputFormat,
+) -> Result<()> {
+    if scrobbles.is_empty() {
+        eprintln!("No scrobbles have been saved yet.");
+        return Ok(());
+    }
+
+    // Last.fm's play count includes scrobbles made since the last fetch, but only makes sense
+    // when projecting the whole history
+    let play_count = if filtered {
+        scrobbles.len() as i32
+    } else {
+        match lastfm::profile::fetch_profile(username, &config.api_key).await {
+            Ok(user) => user.play_count(),
+            Err(e) => {
+                eprintln!(
+                    "Could not fetch the play count from Last.fm, using saved scrobbles: {}",
+                    e
+                );
+                scrobbles.len() as i32
+            }
         }
     };
 
-    let saved_scrobbles = data::load_from_any_file(&username)?;
+    let today = Local::today().naive_local();
+    let mut forecast = forecast::Forecast::new(scrobbles, play_count, today, f.window, f.target);
+    if let Some(artist) = &f.plays_of {
+        forecast = match forecast.with_artist(scrobbles, artist, f.plays) {
+            Some(forecast) => forecast,
+            None => {
+                eprintln!("`{}` has never been scrobbled.", artist);
+                return Ok(());
+            }
+        };
+    }
+    forecast.excluded_scrobbles = excluded;
+
+    output::print_report(&forecast, format)
+}
+
+fn time(t: Time, scrobbles: &[SavedScrobble], excluded: i32, format: OutputFormat) -> Result<()> {
+    let durations = data::load_track_durations()?;
+    if durations.is_empty() {
+        eprintln!("No track durations have been looked up yet. Run `enrich durations` first.");
+        return Ok(());
+    }
+
+    let mut listening_time = ListeningTime::new(scrobbles, &durations, t.by, t.limit);
+    listening_time.excluded_scrobbles = excluded;
+    output::print_report(&listening_time, format)
+}
 
-    println!("Crunching stats for {}...\n", &username);
-    let stats = saved_scrobbles.generate_stats();
-    stats.print();
+fn genres(
+    g: Genres,
+    scrobbles: &[SavedScrobble],
+    excluded: i32,
+    format: OutputFormat,
+) -> Result<()> {
+    let tags = data::load_artist_tags()?;
+    if tags.is_empty() {
+        eprintln!("No artist tags have been looked up yet. Run `enrich tags` first.");
+        return Ok(());
+    }
 
-    Ok(())
+    let rules = data::load_genre_rules()?;
+    let mut genres = GenreShares::new(scrobbles, &tags, &rules, g.by, g.top);
+    genres.excluded_scrobbles = excluded;
+    output::print_report(&genres, format)
 }
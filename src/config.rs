@@ -4,10 +4,9 @@ use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use anyhow::Result;
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
-use crate::data::db;
-
 static CRATE_NAME: &str = env!("CARGO_CRATE_NAME");
 
 #[derive(Deserialize, Serialize)]
@@ -23,8 +22,9 @@ pub fn initialize_config() -> Result<()> {
     let api_key = set_api_key();
     let username = set_username();
     let storage_format = set_storage_format()?;
+    let timezone = set_timezone();
 
-    let config = Config::new(api_key, username, storage_format);
+    let config = Config::new(api_key, username, storage_format, timezone);
 
     config.save_config()
 }
@@ -36,9 +36,11 @@ pub fn update_config() -> Result<()> {
     let mut api_key = config.api_key;
     let mut username = config.default_username;
     let mut storage_format = config.storage_format;
+    let mut timezone = config.timezone;
 
     let mut choice = String::new();
 
+    choice.clear();
     println!("Update API key? (y/n)");
     io::stdin()
         .read_line(&mut choice)
@@ -48,6 +50,7 @@ pub fn update_config() -> Result<()> {
         api_key = set_api_key();
     }
 
+    choice.clear();
     println!("Update default username? (y/n)");
     io::stdin()
         .read_line(&mut choice)
@@ -57,6 +60,7 @@ pub fn update_config() -> Result<()> {
         username = set_username();
     }
 
+    choice.clear();
     println!("Update storage format? (y/n)");
     io::stdin()
         .read_line(&mut choice)
@@ -66,7 +70,17 @@ pub fn update_config() -> Result<()> {
         storage_format = set_storage_format()?;
     }
 
-    config = Config::new(api_key, username, storage_format);
+    choice.clear();
+    println!("Update home time zone? (y/n)");
+    io::stdin()
+        .read_line(&mut choice)
+        .expect("Failed to read user selection");
+
+    if choice.trim() == "y" {
+        timezone = set_timezone();
+    }
+
+    config = Config::new(api_key, username, storage_format, timezone);
     config.save_config()
 }
 
@@ -93,6 +107,29 @@ fn set_username() -> String {
     username.trim().to_string()
 }
 
+/// Asks for an IANA time zone name until a valid one is entered, or nothing for the time zone of
+/// this machine
+fn set_timezone() -> Option<String> {
+    loop {
+        println!("Enter your home time zone, e.g. `Europe/London`:");
+        println!("(Leave it empty to use the time zone of this machine.)");
+        let mut timezone = String::new();
+        io::stdin()
+            .read_line(&mut timezone)
+            .expect("Failed to read time zone");
+
+        let timezone = timezone.trim();
+        if timezone.is_empty() {
+            return None;
+        }
+        if timezone.parse::<Tz>().is_ok() {
+            return Some(timezone.to_string());
+        }
+
+        println!("`{}` is not a time zone. Please try again.", timezone);
+    }
+}
+
 fn set_storage_format() -> Result<StorageFormat> {
     let mut valid_selection = false;
     let mut selection = String::new();
@@ -104,6 +141,7 @@ fn set_storage_format() -> Result<StorageFormat> {
         println!("2. JSON file");
         println!("3. Sqlite database");
 
+        selection.clear();
         io::stdin()
             .read_line(&mut selection)
             .expect("Failed to read selection");
@@ -125,15 +163,9 @@ fn set_storage_format() -> Result<StorageFormat> {
         };
     }
 
-    // We can safely unwrap here because `storage_format` should have a value
-    let storage_format = storage_format.unwrap();
-
-    if let StorageFormat::Sqlite = storage_format {
-        println!("Sqlite storage format was selected. Building database now...");
-        db::build_sqlite_database()?;
-    }
-
-    Ok(storage_format)
+    // We can safely unwrap here because `storage_format` should have a value.
+    // A Sqlite database is created, and migrated, the first time it is opened.
+    Ok(storage_format.unwrap())
 }
 
 pub fn check_if_config_exists() -> bool {
@@ -145,14 +177,24 @@ pub struct Config {
     pub api_key: String,
     pub default_username: String,
     pub storage_format: StorageFormat,
+    /// The IANA name of the time zone scrobbles are listened in, away from the dates in
+    /// `timezones.json`. Without one, the time zone of this machine is used.
+    #[serde(default)]
+    pub timezone: Option<String>,
 }
 
 impl Config {
-    pub fn new(api_key: String, default_username: String, storage_format: StorageFormat) -> Self {
+    pub fn new(
+        api_key: String,
+        default_username: String,
+        storage_format: StorageFormat,
+        timezone: Option<String>,
+    ) -> Self {
         Self {
             api_key,
             default_username,
             storage_format,
+            timezone,
         }
     }
 
@@ -198,6 +240,12 @@ impl Config {
         println!("Current Configuration:");
         println!("Default Last.fm username: {}", self.default_username);
         println!("Default storage format: {}", storage_format);
+        println!(
+            "Home time zone: {}",
+            self.timezone
+                .as_deref()
+                .unwrap_or("the time zone of this machine")
+        );
 
         if full_config {
             println!("Current Last.fm API key: {}", self.api_key);
@@ -206,6 +254,12 @@ impl Config {
 }
 
 fn build_config_path() -> PathBuf {
+    build_config_file_path("config.json")
+}
+
+/// Builds the path of a file in the application's configuration directory, creating the
+/// directory if necessary
+pub fn build_config_file_path(file_name: &str) -> PathBuf {
     let config_dir = dirs::config_dir().unwrap();
 
     let path = Path::new(config_dir.as_path());
@@ -213,5 +267,5 @@ fn build_config_path() -> PathBuf {
 
     fs::create_dir_all(&path).expect("Path could not be created");
 
-    path.join("config.json")
+    path.join(file_name)
 }
@@ -1,14 +1,11 @@
 use anyhow::{Context, Result};
 
 use crate::data;
-use crate::models::recent_tracks::Track;
 use crate::models::saved_scrobbles::SavedScrobbles;
 
-pub fn save_to_csv(scrobbles: &[Track], username: &str) -> Result<i32> {
+pub fn save_to_csv(scrobbles: &SavedScrobbles, username: &str) -> Result<i32> {
     let file = data::build_file_path(username, "csv")?;
 
-    let scrobbles = SavedScrobbles::from_scrobbles(scrobbles);
-
     let mut wtr = csv::Writer::from_path(file).unwrap();
     scrobbles.to_csv_writer(&mut wtr);
     wtr.flush().unwrap();
@@ -17,7 +14,7 @@ pub fn save_to_csv(scrobbles: &[Track], username: &str) -> Result<i32> {
 }
 
 pub fn append_to_csv(
-    scrobbles: &[Track],
+    scrobbles: SavedScrobbles,
     saved_scrobbles: &mut SavedScrobbles,
     username: &str,
 ) -> Result<i32> {
@@ -32,15 +29,26 @@ pub fn append_to_csv(
     Ok(saved_scrobbles.total_saved_scrobbles())
 }
 
+/// Replaces the saved csv file with `saved_scrobbles`
+pub fn overwrite_csv(saved_scrobbles: &SavedScrobbles, username: &str) -> Result<()> {
+    let file = data::build_file_path(username, "csv")?;
+
+    let mut wtr = csv::Writer::from_path(file).context("Error creating csv writer")?;
+    saved_scrobbles.to_csv_writer(&mut wtr);
+    wtr.flush().context("Error flushing csv writer")?;
+
+    Ok(())
+}
+
 pub fn load_from_csv(username: &str) -> Result<SavedScrobbles> {
-    println!("Loading saved scrobbles from `{}.csv`...", username);
+    eprintln!("Loading saved scrobbles from `{}.csv`...", username);
 
     let file = data::build_file_path(username, "csv")?;
 
     let mut rdr = csv::Reader::from_path(file).context("Error creating csv reader")?;
     let saved_scrobbles = SavedScrobbles::from_csv_reader(&mut rdr);
 
-    println!(
+    eprintln!(
         "{} saved scrobbles retrieved from file\n",
         &saved_scrobbles.total_saved_scrobbles_formatted()
     );
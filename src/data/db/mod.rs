@@ -1,26 +1,37 @@
+use std::collections::HashMap;
 use std::env;
 use std::path::Path;
-use std::process::Command;
 use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::Result;
+use chrono::NaiveDateTime;
+use sqlx::migrate::Migrator;
 use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous};
 use sqlx::SqlitePool;
 
 use crate::config::Config;
+use crate::data;
+use crate::models::name_rules::NameCanonicalizer;
+use crate::models::saved_loved_tracks::{SavedLovedTrack, SavedLovedTracks};
 use crate::models::saved_scrobbles::{SavedScrobble, SavedScrobbles};
+use crate::models::saved_weekly_charts::{
+    ChartKind, SavedWeeklyCharts, WeeklyChart, WeeklyChartEntry,
+};
+use crate::models::time_zones::LocalTimes;
+use crate::query::{ScrobbleFilter, SqlValue};
+use crate::reports::search::SearchResult;
 
 fn build_database_name() -> Result<String> {
     let config = Config::load_config()?;
     let db = format!("{}.db", config.default_username);
 
-    return Ok(db);
+    Ok(db)
 }
 
-fn check_if_sqlite_database_exists() -> Result<bool> {
+pub fn check_if_sqlite_database_exists() -> Result<bool> {
     let db_name = build_database_name()?;
-    return Ok(Path::new(db_name.as_str()).exists())
+    Ok(Path::new(db_name.as_str()).exists())
 }
 
 fn build_connection_string() -> Result<String> {
@@ -32,83 +43,141 @@ fn build_connection_string() -> Result<String> {
     Ok(connection_string)
 }
 
-pub fn build_sqlite_database() -> Result<()> {
-    // Check if sqlite database already exists
-    if check_if_sqlite_database_exists()? {
-        // No need to re-create, return early
-        return Ok(())
-    }
-
-    // We just want to set the environment variable
-    let _ = build_connection_string();
-
-    // Create the database
-    let _create = Command::new("sqlx")
-        .arg("database")
-        .arg("create")
-        .output()
-        .expect("Failed to create database");
-
-    let _migrations = Command::new("sqlx")
-        .arg("migrate")
-        .arg("run")
-        .output()
-        .expect("Failed to run database migrations");
-
-    Ok(())
-}
-
 pub async fn get_sqlite_pool() -> Result<SqlitePool> {
-    if !check_if_sqlite_database_exists()? {
-        build_sqlite_database()?
-    }
-
     let connection_string = build_connection_string()?;
 
     let options = SqliteConnectOptions::from_str(&connection_string)?
+        .create_if_missing(true)
         .busy_timeout(Duration::from_secs(30))
         .journal_mode(SqliteJournalMode::Wal)
         .synchronous(SqliteSynchronous::Normal);
 
     let pool = SqlitePool::connect_with(options).await?;
 
+    let merged_before = is_migration_applied(&pool, SCROBBLES_IDENTITY_MIGRATION).await?;
+    MIGRATOR.run(&pool).await?;
+    if !merged_before {
+        report_merged_scrobbles(&pool).await?;
+    }
+    fill_missing_utc_offsets(&pool).await?;
+
     Ok(pool)
 }
 
+/// Applies the migrations in `migrations` that have not been applied yet
+///
+/// Sqlx records the applied migrations in the `_sqlx_migrations` table, the same way the `sqlx`
+/// command line tool does, so a database created with either is brought up to date.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// The migration that merges copies of the same scrobble, keeping the removed copies in the
+/// `merged_scrobbles` table
+const SCROBBLES_IDENTITY_MIGRATION: i64 = 20261019140000;
+
+async fn is_migration_applied(pool: &SqlitePool, version: i64) -> Result<bool> {
+    let tracked: (i64,) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*)
+        FROM sqlite_master
+        WHERE type = 'table' AND name = '_sqlx_migrations'
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if tracked.0 == 0 {
+        return Ok(false);
+    }
+
+    let applied: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM _sqlx_migrations WHERE version = ? AND success")
+            .bind(version)
+            .fetch_one(pool)
+            .await?;
+
+    Ok(applied.0 > 0)
+}
+
+async fn report_merged_scrobbles(pool: &SqlitePool) -> Result<()> {
+    let merged: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM merged_scrobbles")
+        .fetch_one(pool)
+        .await?;
+
+    if merged.0 > 0 {
+        eprintln!(
+            "Merged {} duplicate scrobbles in the database. The removed copies are kept in the `merged_scrobbles` table.",
+            merged.0
+        );
+    }
+
+    Ok(())
+}
+
+/// Saves scrobbles, merging any that were already saved the same way
+/// [SavedScrobble::merge](../../models/saved_scrobbles/struct.SavedScrobble.html#method.merge)
+/// does. Returns the number of scrobbles that were new.
 pub async fn insert_scrobbles(scrobbles: SavedScrobbles, pool: &SqlitePool) -> Result<i32> {
     let mut count = 0;
 
     for scrobble in scrobbles.get_saved_scrobbles() {
-        insert_scrobble(scrobble, pool).await?;
-        count = count + 1;
+        if insert_scrobble(scrobble, pool).await? {
+            count += 1;
+        }
     }
 
     Ok(count)
 }
 
-async fn insert_scrobble(scrobble: SavedScrobble, pool: &SqlitePool) -> Result<i64> {
+/// Returns whether the scrobble was new
+async fn insert_scrobble(scrobble: SavedScrobble, pool: &SqlitePool) -> Result<bool> {
     let mut conn = pool.acquire().await?;
 
-    let id = sqlx::query!(
+    let utc_offset = scrobble.utc_offset();
+    let (timestamp_utc, artist, title) = scrobble.identity();
+    let exists: (bool,) = sqlx::query_as(
         r#"
-        INSERT INTO scrobbles (track, artist, album, loved, timestamp_utc)
-        VALUES (?1, ?2, ?3, ?4, ?5)
+        SELECT EXISTS(SELECT 1
+                      FROM scrobbles
+                      WHERE timestamp_utc = ?1
+                        AND coalesce(original_artist, artist) = ?2
+                        AND coalesce(original_track, track) = ?3)
         "#,
-        scrobble.title,
-        scrobble.artist,
-        scrobble.album,
-        scrobble.loved,
-        scrobble.timestamp_utc,
     )
-        .execute(&mut conn)
-        .await?
-        .last_insert_rowid();
+    .bind(timestamp_utc)
+    .bind(artist)
+    .bind(title)
+    .fetch_one(&mut conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO scrobbles (track, artist, album, loved, timestamp_utc,
+                               original_track, original_artist, original_album, utc_offset)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        ON CONFLICT (timestamp_utc, coalesce(original_artist, artist), coalesce(original_track, track))
+            DO UPDATE SET loved          = max(loved, excluded.loved),
+                          album          = coalesce(nullif(album, ''), excluded.album),
+                          original_album = coalesce(original_album, excluded.original_album)
+        "#,
+    )
+    .bind(scrobble.title)
+    .bind(scrobble.artist)
+    .bind(scrobble.album)
+    .bind(scrobble.loved)
+    .bind(scrobble.timestamp_utc)
+    .bind(scrobble.original_title)
+    .bind(scrobble.original_artist)
+    .bind(scrobble.original_album)
+    .bind(utc_offset)
+    .execute(&mut conn)
+    .await?;
 
-    Ok(id)
+    Ok(!exists.0)
 }
 
+/// Timestamp to fetch newer scrobbles from, or 0 if the database has no scrobbles yet
 pub async fn get_most_recent_scrobble(pool: &SqlitePool) -> Result<i64> {
-    let most_recent_scrobble: (i64, ) = sqlx::query_as(
+    let most_recent_scrobble: Option<(i64, )> = sqlx::query_as(
         r#"
         SELECT timestamp_utc
         FROM scrobbles
@@ -116,38 +185,552 @@ pub async fn get_most_recent_scrobble(pool: &SqlitePool) -> Result<i64> {
         DESC LIMIT 1
         "#,
     )
-        .fetch_one(pool)
+        .fetch_optional(pool)
         .await?;
 
-    let most_recent_scrobble = most_recent_scrobble.0 + 10;
+    let most_recent_scrobble = most_recent_scrobble.map_or(0, |(timestamp, )| timestamp + 10);
 
     Ok(most_recent_scrobble)
 }
 
-/*pub async fn get_scrobbles(pool: &SqlitePool) -> Result<Vec<SavedScrobble>> {
-    let mut recs = sqlx::query!(
+type ScrobbleRow = (
+    String,
+    String,
+    Option<String>,
+    bool,
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+);
+
+/// A [ScrobbleRow](type.ScrobbleRow.html) preceded by the scrobble's id
+type NumberedScrobbleRow = (
+    i64,
+    String,
+    String,
+    Option<String>,
+    bool,
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+);
+
+fn scrobble_from_row(row: ScrobbleRow) -> SavedScrobble {
+    let (track, artist, album, loved, timestamp_utc, original_track, original_artist, original_album, utc_offset) =
+        row;
+    let mut scrobble =
+        SavedScrobble::new(track, artist, album.unwrap_or_default(), loved, timestamp_utc);
+    scrobble.original_title = original_track;
+    scrobble.original_artist = original_artist;
+    scrobble.original_album = original_album;
+    if let Some(utc_offset) = utc_offset {
+        scrobble.set_utc_offset(utc_offset);
+    }
+    scrobble
+}
+
+/// Retrieves every saved scrobble from the database
+pub async fn get_scrobbles(pool: &SqlitePool) -> Result<SavedScrobbles> {
+    let rows: Vec<ScrobbleRow> = sqlx::query_as(
+        r#"
+        SELECT track, artist, album, loved, timestamp_utc,
+               original_track, original_artist, original_album, utc_offset
+        FROM scrobbles
+        ORDER BY timestamp_utc DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let scrobbles = rows.into_iter().map(scrobble_from_row).collect();
+
+    Ok(SavedScrobbles::new(scrobbles))
+}
+
+/// Retrieves every saved scrobble from the database with its id, newest first
+pub async fn get_numbered_scrobbles(pool: &SqlitePool) -> Result<Vec<(i64, SavedScrobble)>> {
+    let rows: Vec<NumberedScrobbleRow> = sqlx::query_as(
         r#"
-        SELECT track, artist, album, loved, timestamp_utc, timestamp_local
-        FROM scrobbles_local
-        ORDER BY timestamp_local DESC
-        "#
+        SELECT id, track, artist, album, loved, timestamp_utc,
+               original_track, original_artist, original_album, utc_offset
+        FROM scrobbles
+        ORDER BY timestamp_utc DESC, id DESC
+        "#,
     )
+    .fetch_all(pool)
+    .await?;
+
+    let scrobbles = rows
+        .into_iter()
+        .map(
+            |(id, track, artist, album, loved, timestamp_utc, original_track, original_artist, original_album, utc_offset)| {
+                let scrobble = scrobble_from_row((
+                    track,
+                    artist,
+                    album,
+                    loved,
+                    timestamp_utc,
+                    original_track,
+                    original_artist,
+                    original_album,
+                    utc_offset,
+                ));
+                (id, scrobble)
+            },
+        )
+        .collect();
+
+    Ok(scrobbles)
+}
+
+/// Deletes the scrobbles with the given ids, returning the number deleted
+pub async fn delete_scrobbles(pool: &SqlitePool, ids: &[i64]) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let mut deleted = 0;
+    for id in ids {
+        deleted += sqlx::query("DELETE FROM scrobbles WHERE id = ?1")
+            .bind(id)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+    }
+
+    tx.commit().await?;
+
+    Ok(deleted)
+}
+
+/// Retrieves the saved scrobbles that match the conditions of a filter that Sqlite can evaluate
+///
+/// Regex filters are not evaluated, so the results must still be narrowed down with
+/// [ScrobbleFilter::apply](../../query/struct.ScrobbleFilter.html#method.apply).
+pub async fn get_matching_scrobbles(
+    pool: &SqlitePool,
+    filter: &ScrobbleFilter,
+) -> Result<SavedScrobbles> {
+    let (conditions, values) = filter.sql_conditions();
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let sql = format!(
+        r#"
+        SELECT track, artist, album, loved, timestamp_utc,
+               original_track, original_artist, original_album, utc_offset
+        FROM scrobbles
+        {}
+        ORDER BY timestamp_utc DESC
+        "#,
+        where_clause
+    );
+
+    let mut query = sqlx::query_as::<_, ScrobbleRow>(&sql);
+    for value in values {
+        query = match value {
+            SqlValue::Integer(value) => query.bind(value),
+            SqlValue::Text(value) => query.bind(value),
+        };
+    }
+
+    let scrobbles = query
         .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(scrobble_from_row)
+        .collect();
+
+    Ok(SavedScrobbles::new(scrobbles))
+}
+
+/// Counts the scrobbles saved in the database
+pub async fn count_scrobbles(pool: &SqlitePool) -> Result<i64> {
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM scrobbles")
+        .fetch_one(pool)
         .await?;
 
+    Ok(count.0)
+}
+
+/// Retrieves the `number`th scrobble in chronological order, where the first scrobble is number 1
+///
+/// The scrobble is found by stepping over the earlier entries of the `scrobbles_chronological`
+/// index, which only holds timestamps and ids, so the lookup still takes longer the larger the
+/// number. Only the one scrobble's row is read, rather than the entire history.
+pub async fn get_scrobble_by_number(
+    pool: &SqlitePool,
+    number: i64,
+) -> Result<Option<SavedScrobble>> {
+    let row: Option<ScrobbleRow> = sqlx::query_as(
+        r#"
+        SELECT track, artist, album, loved, timestamp_utc,
+               original_track, original_artist, original_album, utc_offset
+        FROM scrobbles
+        WHERE id = (SELECT id
+                    FROM scrobbles
+                    ORDER BY timestamp_utc, id
+                    LIMIT 1 OFFSET ?1)
+        "#,
+    )
+    .bind(number - 1)
+    .fetch_optional(pool)
+    .await?;
 
-    let mut scrobbles = Vec::new();
-    for rec in recs {
-        let scrobble = SavedScrobble {
-            title: rec.track,
-            artist: rec.artist,
-            album: rec.album,
-            loved: rec.loved,
-            timestamp_utc: rec.timestamp_utc,
-            datetime_local: rec.timestamp_local,
+    Ok(row.map(scrobble_from_row))
+}
+
+/// Renames every scrobble to the canonical spelling of its original names, keeping the original
+/// names that changed
+///
+/// Only the scrobbles that changed are updated. Returns the number of scrobbles that changed.
+pub async fn normalize_names(pool: &SqlitePool, rules: &NameCanonicalizer) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let rows: Vec<NumberedScrobbleRow> = sqlx::query_as(
+        r#"
+        SELECT id, track, artist, album, loved, timestamp_utc,
+               original_track, original_artist, original_album, utc_offset
+        FROM scrobbles
+        "#,
+    )
+    .fetch_all(&mut tx)
+    .await?;
+
+    let mut changed = 0;
+    for (id, track, artist, album, loved, timestamp_utc, original_track, original_artist, original_album, utc_offset) in
+        rows
+    {
+        let mut scrobble = scrobble_from_row((
+            track,
+            artist,
+            album,
+            loved,
+            timestamp_utc,
+            original_track,
+            original_artist,
+            original_album,
+            utc_offset,
+        ));
+        if !scrobble.normalize(rules) {
+            continue;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE scrobbles
+            SET track           = ?2,
+                artist          = ?3,
+                album           = ?4,
+                original_track  = ?5,
+                original_artist = ?6,
+                original_album  = ?7
+            WHERE id = ?1
+            "#,
+        )
+        .bind(id)
+        .bind(scrobble.title)
+        .bind(scrobble.artist)
+        .bind(scrobble.album)
+        .bind(scrobble.original_title)
+        .bind(scrobble.original_artist)
+        .bind(scrobble.original_album)
+        .execute(&mut tx)
+        .await?;
+        changed += 1;
+    }
+
+    tx.commit().await?;
+
+    Ok(changed)
+}
+
+/// Recomputes the local time of every scrobble with `local_times`
+///
+/// Only the scrobbles whose offset from UTC changed are updated. Returns the number of scrobbles
+/// that changed.
+pub async fn retime_scrobbles(pool: &SqlitePool, local_times: &LocalTimes) -> Result<u64> {
+    set_utc_offsets(pool, local_times, "SELECT id, timestamp_utc, utc_offset FROM scrobbles").await
+}
+
+/// Gives scrobbles saved before local times were stored the offset of the configured time zones
+async fn fill_missing_utc_offsets(pool: &SqlitePool) -> Result<()> {
+    let local_times = data::load_local_times(&Config::load_config()?)?;
+    let filled = set_utc_offsets(
+        pool,
+        &local_times,
+        "SELECT id, timestamp_utc, utc_offset FROM scrobbles WHERE utc_offset IS NULL",
+    )
+    .await?;
+
+    if filled > 0 {
+        eprintln!("Set the local time of {} scrobbles saved without one", filled);
+    }
+
+    Ok(())
+}
+
+/// Sets the offset of each scrobble selected by `select`, which selects the id, timestamp and
+/// offset of scrobbles. Returns how many offsets changed.
+async fn set_utc_offsets(pool: &SqlitePool, local_times: &LocalTimes, select: &str) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let rows: Vec<(i64, i64, Option<i32>)> = sqlx::query_as(select).fetch_all(&mut tx).await?;
+
+    let mut changed = 0;
+    for (id, timestamp_utc, utc_offset) in rows {
+        let new_offset = local_times
+            .datetime_local(timestamp_utc)
+            .offset()
+            .local_minus_utc();
+        if utc_offset == Some(new_offset) {
+            continue;
+        }
+
+        sqlx::query("UPDATE scrobbles SET utc_offset = ?2 WHERE id = ?1")
+            .bind(id)
+            .bind(new_offset)
+            .execute(&mut tx)
+            .await?;
+        changed += 1;
+    }
+
+    tx.commit().await?;
+
+    Ok(changed)
+}
+
+/// Searches the full-text index of track, artist and album names, returning the best matching
+/// tracks first
+///
+/// `fts_query` is an FTS5 query such as
+/// [SearchTerms::fts_query](../../reports/search/struct.SearchTerms.html#method.fts_query) builds.
+/// Tracks that rank equally are ordered by how often they were scrobbled.
+pub async fn search_scrobbles(
+    pool: &SqlitePool,
+    fts_query: &str,
+    limit: i64,
+) -> Result<Vec<SearchResult>> {
+    let rows: Vec<(String, String, Option<String>, i64, i64)> = sqlx::query_as(
+        r#"
+        WITH matches AS MATERIALIZED (
+            SELECT rowid, rank
+            FROM scrobbles_search
+            WHERE scrobbles_search MATCH ?1
+        )
+        SELECT s.track, s.artist, s.album, COUNT(*) AS scrobbles, MAX(s.timestamp_utc + s.utc_offset)
+        FROM matches m
+        JOIN scrobbles s ON s.id = m.rowid
+        GROUP BY s.track, s.artist, s.album
+        ORDER BY MIN(m.rank), scrobbles DESC, s.track
+        LIMIT ?2
+        "#,
+    )
+    .bind(fts_query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let results = rows
+        .into_iter()
+        .map(|(track, artist, album, scrobbles, local_seconds)| SearchResult {
+            track,
+            artist,
+            album: album.unwrap_or_default(),
+            scrobbles: scrobbles as i32,
+            last_scrobbled: NaiveDateTime::from_timestamp(local_seconds, 0).date(),
+        })
+        .collect();
+
+    Ok(results)
+}
+
+/// Every word in the full-text index, and the number of scrobbles it appears in
+pub async fn get_search_vocabulary(pool: &SqlitePool) -> Result<HashMap<String, i64>> {
+    let rows: Vec<(String, i64)> = sqlx::query_as("SELECT term, doc FROM scrobbles_search_terms")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// Retrieves the user's loved tracks, from the most recently loved to the least
+pub async fn get_loved_tracks(pool: &SqlitePool) -> Result<SavedLovedTracks> {
+    let rows: Vec<(String, String, i64)> = sqlx::query_as(
+        r#"
+        SELECT track, artist, timestamp_utc
+        FROM loved_tracks
+        ORDER BY timestamp_utc DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let loved_tracks = rows
+        .into_iter()
+        .map(|(track, artist, timestamp_utc)| SavedLovedTrack::new(track, artist, timestamp_utc))
+        .collect();
+
+    Ok(SavedLovedTracks::new(loved_tracks))
+}
+
+/// Replaces the saved loved tracks and updates the `loved` column of every scrobble to match
+///
+/// Returns the number of scrobbles whose `loved` column changed.
+pub async fn replace_loved_tracks(
+    pool: &SqlitePool,
+    loved_tracks: &SavedLovedTracks,
+) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM loved_tracks")
+        .execute(&mut tx)
+        .await?;
+
+    for loved_track in loved_tracks.as_slice() {
+        sqlx::query(
+            r#"
+            INSERT INTO loved_tracks (track, artist, timestamp_utc)
+            VALUES (?1, ?2, ?3)
+            "#,
+        )
+        .bind(&loved_track.title)
+        .bind(&loved_track.artist)
+        .bind(loved_track.timestamp_utc)
+        .execute(&mut tx)
+        .await?;
+    }
+
+    let changed = sqlx::query(
+        r#"
+        UPDATE scrobbles
+        SET loved = 1 - loved
+        WHERE loved != EXISTS(SELECT 1
+                              FROM loved_tracks lt
+                              WHERE lower(lt.artist) = lower(coalesce(scrobbles.original_artist, scrobbles.artist))
+                                AND lower(lt.track) = lower(coalesce(scrobbles.original_track, scrobbles.track)))
+        "#,
+    )
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(changed)
+}
+
+/// Retrieves every saved weekly chart
+pub async fn get_weekly_charts(pool: &SqlitePool) -> Result<SavedWeeklyCharts> {
+    let weeks: Vec<(i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT id, timestamp_from, timestamp_to
+        FROM weekly_charts
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let entries: Vec<(i64, String, i32, String, Option<String>, i32)> = sqlx::query_as(
+        r#"
+        SELECT chart_id, kind, rank, artist, name, scrobbles
+        FROM weekly_chart_entries
+        ORDER BY chart_id, kind, rank
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut charts = weeks
+        .into_iter()
+        .map(|(id, from, to)| {
+            let chart = WeeklyChart {
+                from,
+                to,
+                artists: Vec::new(),
+                albums: Vec::new(),
+                tracks: Vec::new(),
+            };
+            (id, chart)
+        })
+        .collect::<HashMap<i64, WeeklyChart>>();
+
+    for (chart_id, kind, rank, artist, name, scrobbles) in entries {
+        let kind = match kind.as_str() {
+            "artist" => ChartKind::Artist,
+            "album" => ChartKind::Album,
+            _ => ChartKind::Track,
         };
-        scrobbles.push(scrobble)
+
+        if let Some(chart) = charts.get_mut(&chart_id) {
+            chart.entries_mut(kind).push(WeeklyChartEntry {
+                rank,
+                artist,
+                name,
+                scrobbles,
+            });
+        }
     }
 
-    Ok(scrobbles)
-}*/
+    Ok(SavedWeeklyCharts::new(charts.into_iter().map(|c| c.1).collect()))
+}
+
+/// Saves a weekly chart, replacing any chart already saved for the same week
+pub async fn insert_weekly_chart(pool: &SqlitePool, chart: &WeeklyChart) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM weekly_chart_entries
+        WHERE chart_id IN (SELECT id FROM weekly_charts WHERE timestamp_from = ?1)
+        "#,
+    )
+    .bind(chart.from)
+    .execute(&mut tx)
+    .await?;
+
+    sqlx::query("DELETE FROM weekly_charts WHERE timestamp_from = ?1")
+        .bind(chart.from)
+        .execute(&mut tx)
+        .await?;
+
+    let chart_id = sqlx::query(
+        r#"
+        INSERT INTO weekly_charts (timestamp_from, timestamp_to)
+        VALUES (?1, ?2)
+        "#,
+    )
+    .bind(chart.from)
+    .bind(chart.to)
+    .execute(&mut tx)
+    .await?
+    .last_insert_rowid();
+
+    for kind in [ChartKind::Artist, ChartKind::Album, ChartKind::Track] {
+        for entry in chart.entries(kind) {
+            sqlx::query(
+                r#"
+                INSERT INTO weekly_chart_entries (chart_id, kind, rank, artist, name, scrobbles)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )
+            .bind(chart_id)
+            .bind(kind.as_str())
+            .bind(entry.rank)
+            .bind(&entry.artist)
+            .bind(&entry.name)
+            .bind(entry.scrobbles)
+            .execute(&mut tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -1,18 +1,16 @@
 use anyhow::Result;
 
 use crate::data;
-use crate::models::recent_tracks::Track;
 use crate::models::saved_scrobbles::SavedScrobbles;
 
-pub fn save_to_json(scrobbles: &[Track], username: &str) -> Result<i32> {
+pub fn save_to_json(scrobbles: &SavedScrobbles, username: &str) -> Result<i32> {
     let file = data::build_file_path(username, "json")?;
-    let scrobbles = SavedScrobbles::from_scrobbles(scrobbles);
     scrobbles.save_as_json(&file)?;
     Ok(scrobbles.total_saved_scrobbles())
 }
 
 pub fn append_to_json(
-    scrobbles: &[Track],
+    scrobbles: SavedScrobbles,
     saved_scrobbles: &mut SavedScrobbles,
     username: &str,
 ) -> Result<i32> {
@@ -22,6 +20,12 @@ pub fn append_to_json(
     Ok(saved_scrobbles.total_saved_scrobbles())
 }
 
+/// Replaces the saved json file with `saved_scrobbles`
+pub fn overwrite_json(saved_scrobbles: &SavedScrobbles, username: &str) -> Result<()> {
+    let file = data::build_file_path(username, "json")?;
+    saved_scrobbles.save_as_json(&file)
+}
+
 pub fn load_from_json(username: &str) -> Result<SavedScrobbles> {
     let file = data::build_file_path(username, "json")?;
     SavedScrobbles::load_from_json(&file)
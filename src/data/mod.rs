@@ -1,17 +1,41 @@
 use std::env;
+use std::fs;
+use std::io::{BufReader, BufWriter};
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 
-use crate::models::recent_tracks::Track;
+use crate::config;
+use crate::models::artist_tags::ArtistTagsCache;
+use crate::models::exclusion_rules::ExclusionRules;
+use crate::models::genre_rules::GenreRules;
+use crate::models::name_rules::NameRules;
+use crate::models::saved_loved_tracks::SavedLovedTracks;
 use crate::models::saved_scrobbles::SavedScrobbles;
+use crate::models::saved_weekly_charts::SavedWeeklyCharts;
+use crate::models::time_zones::{LocalTimes, TimeZoneOverrides};
+use crate::models::track_durations::TrackDurations;
 
 mod csv;
 pub mod db;
 mod json;
 
+/// Track durations are shared by every user, so they are cached in a single file
+const TRACK_DURATIONS_FILE: &str = "track_durations.json";
+const ARTIST_TAGS_FILE: &str = "artist_tags.json";
+
+/// Suspicious scrobbles are moved next to the user's saved file, e.g. `username_quarantine.json`
+const QUARANTINE_FILE_SUFFIX: &str = "_quarantine.json";
+
+/// Rules are edited by the user, so they live next to the configuration file
+const GENRE_RULES_FILE: &str = "genres.json";
+const NAME_RULES_FILE: &str = "names.json";
+const EXCLUSION_RULES_FILE: &str = "exclusions.json";
+const TIME_ZONE_OVERRIDES_FILE: &str = "timezones.json";
+
 fn validate_extension(extension: &str) {
-    let valid_extensions = vec!["csv", "json"];
+    let valid_extensions = ["csv", "json"];
 
     if !valid_extensions.contains(&extension) {
         panic!("A valid extension was not provided.")
@@ -26,6 +50,12 @@ fn build_file_path(username: &str, extension: &str) -> Result<PathBuf> {
     Ok(current_dir.join(format!("{}.{}", username, extension)))
 }
 
+fn build_cache_path(file_name: &str) -> Result<PathBuf> {
+    let current_dir =
+        env::current_dir().context("Error fetching current directory from environment")?;
+    Ok(current_dir.join(file_name))
+}
+
 pub fn check_if_file_exists(username: &str, extension: &str) -> Result<bool> {
     validate_extension(extension);
 
@@ -46,7 +76,7 @@ pub fn find_which_file_exists(username: &str) -> Result<Option<&str>> {
     Ok(exists)
 }
 
-pub fn save_to_file(scrobbles: &[Track], username: &str, file_format: &str) -> Result<i32> {
+pub fn save_to_file(scrobbles: &SavedScrobbles, username: &str, file_format: &str) -> Result<i32> {
     if file_format == "csv" {
         csv::save_to_csv(scrobbles, username)
     } else {
@@ -55,7 +85,7 @@ pub fn save_to_file(scrobbles: &[Track], username: &str, file_format: &str) -> R
 }
 
 pub fn append_to_file(
-    scrobbles: &[Track],
+    scrobbles: SavedScrobbles,
     saved_scrobbles: &mut SavedScrobbles,
     username: &str,
     file_format: &str,
@@ -67,13 +97,47 @@ pub fn append_to_file(
     }
 }
 
+/// Replaces a saved file with `saved_scrobbles`, e.g. after the scrobbles were modified
+pub fn overwrite_file(
+    saved_scrobbles: &SavedScrobbles,
+    username: &str,
+    file_format: &str,
+) -> Result<()> {
+    if file_format == "csv" {
+        csv::overwrite_csv(saved_scrobbles, username)
+    } else {
+        json::overwrite_json(saved_scrobbles, username)
+    }
+}
+
+/// Adds scrobbles taken out of a user's history to the user's quarantine file, where they can be
+/// reviewed or restored by hand. Returns the path of the quarantine file.
+pub fn append_to_quarantine<T: Serialize>(scrobbles: &[T], username: &str) -> Result<PathBuf> {
+    let file = build_cache_path(&format!("{}{}", username, QUARANTINE_FILE_SUFFIX))?;
+
+    let mut quarantined: Vec<serde_json::Value> = if file.exists() {
+        serde_json::from_reader(BufReader::new(fs::File::open(&file)?))
+            .with_context(|| format!("Error reading quarantined scrobbles from `{}`", file.display()))?
+    } else {
+        Vec::new()
+    };
+    for scrobble in scrobbles {
+        quarantined.push(serde_json::to_value(scrobble)?);
+    }
+
+    let bw = BufWriter::new(fs::File::create(&file)?);
+    serde_json::to_writer_pretty(bw, &quarantined)?;
+
+    Ok(file)
+}
+
 pub fn load_from_any_file(username: &str) -> Result<SavedScrobbles> {
     let file_format = find_which_file_exists(username)?.context("No valid file was found")?;
     load_from_file(username, file_format)
 }
 
 pub fn load_from_file(username: &str, file_format: &str) -> Result<SavedScrobbles> {
-    println!(
+    eprintln!(
         "Loading saved scrobbles from `{}.{}`...",
         username, file_format
     );
@@ -84,3 +148,172 @@ pub fn load_from_file(username: &str, file_format: &str) -> Result<SavedScrobble
         json::load_from_json(username)
     }
 }
+
+/// Loads the cached track durations, or an empty cache if no durations have been fetched yet
+pub fn load_track_durations() -> Result<TrackDurations> {
+    let file = build_cache_path(TRACK_DURATIONS_FILE)?;
+
+    if !file.exists() {
+        return Ok(TrackDurations::default());
+    }
+
+    TrackDurations::load_from_json(&file)
+}
+
+pub fn save_track_durations(durations: &TrackDurations) -> Result<()> {
+    let file = build_cache_path(TRACK_DURATIONS_FILE)?;
+    durations.save_as_json(&file)
+}
+
+/// Loved tracks are saved next to the user's scrobbles, e.g. `username_loved.json`
+fn build_loved_tracks_path(username: &str) -> Result<PathBuf> {
+    build_cache_path(&format!("{}_loved.json", username))
+}
+
+/// Loads a user's saved loved tracks, or `None` if they have never been synced
+pub fn load_loved_tracks(username: &str) -> Result<Option<SavedLovedTracks>> {
+    let file = build_loved_tracks_path(username)?;
+
+    if !file.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(SavedLovedTracks::load_from_json(&file)?))
+}
+
+pub fn save_loved_tracks(loved_tracks: &SavedLovedTracks, username: &str) -> Result<()> {
+    let file = build_loved_tracks_path(username)?;
+    loved_tracks.save_as_json(&file)
+}
+
+fn build_weekly_charts_path(username: &str) -> Result<PathBuf> {
+    build_cache_path(&format!("{}_charts.json", username))
+}
+
+/// Loads a user's saved weekly charts, or no charts if they have never been synced
+pub fn load_weekly_charts(username: &str) -> Result<SavedWeeklyCharts> {
+    let file = build_weekly_charts_path(username)?;
+
+    if !file.exists() {
+        return Ok(SavedWeeklyCharts::default());
+    }
+
+    SavedWeeklyCharts::load_from_json(&file)
+}
+
+pub fn save_weekly_charts(charts: &SavedWeeklyCharts, username: &str) -> Result<()> {
+    let file = build_weekly_charts_path(username)?;
+    charts.save_as_json(&file)
+}
+
+/// Loads the cached artist tags, or an empty cache if no tags have been fetched yet
+pub fn load_artist_tags() -> Result<ArtistTagsCache> {
+    let file = build_cache_path(ARTIST_TAGS_FILE)?;
+
+    if !file.exists() {
+        return Ok(ArtistTagsCache::default());
+    }
+
+    ArtistTagsCache::load_from_json(&file)
+}
+
+pub fn save_artist_tags(tags: &ArtistTagsCache) -> Result<()> {
+    let file = build_cache_path(ARTIST_TAGS_FILE)?;
+    tags.save_as_json(&file)
+}
+
+/// Loads the user's genre rules, or the default rules if the user has not written any
+pub fn load_genre_rules() -> Result<GenreRules> {
+    let file = config::build_config_file_path(GENRE_RULES_FILE);
+
+    if !file.exists() {
+        return Ok(GenreRules::default());
+    }
+
+    GenreRules::load_from_json(&file)
+        .with_context(|| format!("Error reading genre rules from `{}`", file.display()))
+}
+
+/// Writes the default genre rules for the user to edit, unless the rules file already exists.
+/// Returns the path of the rules file.
+pub fn create_genre_rules() -> Result<PathBuf> {
+    let file = config::build_config_file_path(GENRE_RULES_FILE);
+
+    if !file.exists() {
+        GenreRules::default().save_as_json(&file)?;
+    }
+
+    Ok(file)
+}
+
+/// Loads the user's name rules, or `None` if the user has not written any
+pub fn load_name_rules() -> Result<Option<NameRules>> {
+    let file = config::build_config_file_path(NAME_RULES_FILE);
+
+    if !file.exists() {
+        return Ok(None);
+    }
+
+    let rules = NameRules::load_from_json(&file)
+        .with_context(|| format!("Error reading name rules from `{}`", file.display()))?;
+
+    Ok(Some(rules))
+}
+
+/// Writes empty name rules for the user to edit, unless the rules file already exists. Returns
+/// the path of the rules file.
+pub fn create_name_rules() -> Result<PathBuf> {
+    let file = config::build_config_file_path(NAME_RULES_FILE);
+
+    if !file.exists() {
+        NameRules::default().save_as_json(&file)?;
+    }
+
+    Ok(file)
+}
+
+/// Loads the user's exclusion rules, or `None` if the user has not written any
+pub fn load_exclusion_rules() -> Result<Option<ExclusionRules>> {
+    let file = config::build_config_file_path(EXCLUSION_RULES_FILE);
+
+    if !file.exists() {
+        return Ok(None);
+    }
+
+    let rules = ExclusionRules::load_from_json(&file)
+        .with_context(|| format!("Error reading exclusion rules from `{}`", file.display()))?;
+
+    Ok(Some(rules))
+}
+
+/// Loads the user's time zone overrides, or `None` if the user has not written any
+pub fn load_time_zone_overrides() -> Result<Option<TimeZoneOverrides>> {
+    let file = config::build_config_file_path(TIME_ZONE_OVERRIDES_FILE);
+
+    if !file.exists() {
+        return Ok(None);
+    }
+
+    let overrides = TimeZoneOverrides::load_from_json(&file)
+        .with_context(|| format!("Error reading time zone overrides from `{}`", file.display()))?;
+
+    Ok(Some(overrides))
+}
+
+/// Writes empty time zone overrides for the user to edit, unless the overrides file already
+/// exists. Returns the path of the overrides file.
+pub fn create_time_zone_overrides() -> Result<PathBuf> {
+    let file = config::build_config_file_path(TIME_ZONE_OVERRIDES_FILE);
+
+    if !file.exists() {
+        TimeZoneOverrides::default().save_as_json(&file)?;
+    }
+
+    Ok(file)
+}
+
+/// The home time zone from the configuration file, with the overrides in `timezones.json`
+pub fn load_local_times(config: &config::Config) -> Result<LocalTimes> {
+    let overrides = load_time_zone_overrides()?.unwrap_or_default();
+    LocalTimes::new(config.timezone.as_deref(), &overrides)
+}
@@ -0,0 +1,79 @@
+use std::time;
+
+use futures::prelude::*;
+use indicatif::ProgressBar;
+
+use crate::lastfm;
+use crate::models::artist_tags::{ArtistTag, ArtistTags};
+use crate::models::top_tags::TopTagsResponse;
+use crate::models::ApiResponse;
+use crate::utils;
+
+/// Only the strongest tags of each artist are kept
+const MAX_TAGS: usize = 10;
+
+/// Looks up the top tags of each artist with `artist.getTopTags`
+///
+/// Artists Last.fm does not know are returned without tags, so they are not looked up again.
+/// Artists that could not be looked up at all (e.g. after repeated network errors) are left out,
+/// so they are retried the next time.
+pub async fn fetch_tags(artists: Vec<String>, api_key: &str) -> Vec<ArtistTags> {
+    let bar = ProgressBar::new(artists.len() as u64);
+
+    let client = reqwest::Client::builder()
+        .timeout(time::Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    let tags = stream::iter(artists)
+        .map(|artist| {
+            let client = client.clone();
+            let api_key = api_key.to_string();
+            let bar = bar.clone();
+
+            tokio::spawn(async move {
+                let tags = fetch_artist_tags(&client, &api_key, artist).await;
+                bar.inc(1);
+                tags
+            })
+        })
+        .buffer_unordered(lastfm::ENRICHMENT_PARALLEL_REQUESTS)
+        .filter_map(|t| async move { t.unwrap() })
+        .collect::<Vec<ArtistTags>>()
+        .await;
+
+    bar.finish();
+
+    tags
+}
+
+async fn fetch_artist_tags(
+    client: &reqwest::Client,
+    api_key: &str,
+    artist: String,
+) -> Option<ArtistTags> {
+    let url = lastfm::build_method_url(
+        "artist.getTopTags",
+        api_key,
+        &[("artist", &artist), ("autocorrect", "1")],
+    );
+
+    let tags = match lastfm::get_with_retries(client, &url).await? {
+        ApiResponse::Success(TopTagsResponse { top_tags }) => top_tags
+            .tags()
+            .iter()
+            .take(MAX_TAGS)
+            .map(|tag| ArtistTag {
+                name: tag.name.to_string(),
+                weight: tag.count,
+            })
+            .collect(),
+        ApiResponse::Failure(_) => Vec::new(),
+    };
+
+    Some(ArtistTags {
+        artist,
+        tags,
+        fetched_at: utils::get_current_unix_timestamp(),
+    })
+}
@@ -0,0 +1,57 @@
+use std::time;
+
+use anyhow::{anyhow, Result};
+use indicatif::ProgressBar;
+
+use crate::lastfm;
+use crate::models::loved_tracks::{LovedTrack, LovedTracksResponse};
+use crate::models::ApiResponse;
+
+/// The largest page size `user.getLovedTracks` accepts
+const LIMIT: i32 = 1000;
+
+/// Fetches every track a user has loved with `user.getLovedTracks`
+///
+/// Users rarely have more than a few pages of loved tracks, so the pages are fetched one at a
+/// time.
+pub async fn fetch_loved_tracks(username: &str, api_key: &str) -> Result<Vec<LovedTrack>> {
+    let client = reqwest::Client::builder()
+        .timeout(time::Duration::from_secs(60))
+        .build()?;
+
+    let mut loved_tracks = Vec::new();
+    let mut page = 1;
+    let mut bar: Option<ProgressBar> = None;
+
+    loop {
+        let url = lastfm::build_method_url(
+            "user.getLovedTracks",
+            api_key,
+            &[
+                ("user", username),
+                ("page", &page.to_string()),
+                ("limit", &LIMIT.to_string()),
+            ],
+        );
+
+        let response = match lastfm::get_with_retries(&client, &url).await {
+            Some(ApiResponse::Success(LovedTracksResponse { loved_tracks })) => loved_tracks,
+            Some(ApiResponse::Failure(error)) => return Err(anyhow!("{}", error.message)),
+            None => return Err(anyhow!("Error fetching page {} of loved tracks", page)),
+        };
+
+        let total_pages = response.attr.total_pages();
+        let bar = bar.get_or_insert_with(|| ProgressBar::new(total_pages as u64));
+        bar.inc(1);
+
+        loved_tracks.append(&mut response.into_tracks());
+
+        if page >= total_pages {
+            bar.finish();
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(loved_tracks)
+}
@@ -1,10 +1,68 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+
 use crate::models::user::User;
+use crate::models::ApiResponse;
 
+pub mod artist_tags;
+pub mod loved_tracks;
 pub mod profile;
 pub mod recently_played;
+pub mod track_info;
+pub mod weekly_charts;
+
+const API_ROOT: &str = "http://ws.audioscrobbler.com/2.0/";
 
 const PARALLEL_REQUESTS: usize = 50;
 
+/// Enrichment makes one request per artist or track, so it runs fewer requests at once to stay
+/// within Last.fm's rate limits
+const ENRICHMENT_PARALLEL_REQUESTS: usize = 5;
+
+const RETRIES: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Error code returned by Last.fm when an artist or track does not exist
+const ERROR_NOT_FOUND: i32 = 6;
+
+/// Builds a request url for any API method, encoding each parameter
+fn build_method_url(method: &str, api_key: &str, params: &[(&str, &str)]) -> Url {
+    let mut url = Url::parse_with_params(
+        API_ROOT,
+        &[("method", method), ("api_key", api_key), ("format", "json")],
+    )
+    .expect("Error building request url");
+
+    url.query_pairs_mut().extend_pairs(params);
+
+    url
+}
+
+/// Sends a request to the API, retrying after network errors, unreadable responses, and API
+/// errors other than [ERROR_NOT_FOUND](constant.ERROR_NOT_FOUND.html)
+///
+/// Returns `None` if every attempt failed.
+async fn get_with_retries<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &Url,
+) -> Option<ApiResponse<T>> {
+    for _ in 0..RETRIES {
+        if let Ok(response) = client.get(url.clone()).send().await {
+            match response.json::<ApiResponse<T>>().await {
+                Ok(ApiResponse::Failure(error)) if error.error != ERROR_NOT_FOUND => {}
+                Ok(response) => return Some(response),
+                Err(_) => {}
+            }
+        }
+
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+
+    None
+}
+
 fn build_request_url(
     user: &User,
     api_key: &str,
@@ -0,0 +1,70 @@
+use std::time;
+
+use futures::prelude::*;
+use indicatif::ProgressBar;
+
+use crate::lastfm;
+use crate::models::track_durations::TrackDuration;
+use crate::models::track_info::TrackInfoResponse;
+use crate::models::ApiResponse;
+use crate::utils;
+
+/// Looks up the duration of each `(artist, title)` pair with `track.getInfo`
+///
+/// Tracks Last.fm does not know are returned with an unknown duration, so they are not looked up
+/// again. Tracks that could not be looked up at all (e.g. after repeated network errors) are left
+/// out, so they are retried the next time.
+pub async fn fetch_durations(tracks: Vec<(String, String)>, api_key: &str) -> Vec<TrackDuration> {
+    let bar = ProgressBar::new(tracks.len() as u64);
+
+    let client = reqwest::Client::builder()
+        .timeout(time::Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    let durations = stream::iter(tracks)
+        .map(|(artist, title)| {
+            let client = client.clone();
+            let api_key = api_key.to_string();
+            let bar = bar.clone();
+
+            tokio::spawn(async move {
+                let duration = fetch_duration(&client, &api_key, artist, title).await;
+                bar.inc(1);
+                duration
+            })
+        })
+        .buffer_unordered(lastfm::ENRICHMENT_PARALLEL_REQUESTS)
+        .filter_map(|t| async move { t.unwrap() })
+        .collect::<Vec<TrackDuration>>()
+        .await;
+
+    bar.finish();
+
+    durations
+}
+
+async fn fetch_duration(
+    client: &reqwest::Client,
+    api_key: &str,
+    artist: String,
+    title: String,
+) -> Option<TrackDuration> {
+    let url = lastfm::build_method_url(
+        "track.getInfo",
+        api_key,
+        &[("artist", &artist), ("track", &title)],
+    );
+
+    let duration_ms = match lastfm::get_with_retries(client, &url).await? {
+        ApiResponse::Success(TrackInfoResponse { track }) => track.duration_ms(),
+        ApiResponse::Failure(_) => None,
+    };
+
+    Some(TrackDuration {
+        artist,
+        title,
+        duration_ms,
+        fetched_at: utils::get_current_unix_timestamp(),
+    })
+}
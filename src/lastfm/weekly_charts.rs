@@ -0,0 +1,128 @@
+use std::time;
+
+use anyhow::{anyhow, Result};
+use futures::prelude::*;
+use indicatif::ProgressBar;
+
+use crate::lastfm;
+use crate::models::saved_weekly_charts::{WeeklyChart, WeeklyChartEntry};
+use crate::models::weekly_charts::{
+    WeeklyAlbumChartResponse, WeeklyArtistChartResponse, WeeklyChartListResponse,
+    WeeklyTrackChartResponse,
+};
+use crate::models::ApiResponse;
+
+/// Fetches the range of every week Last.fm has charts for with `user.getWeeklyChartList`, as
+/// `(from, to)` unix timestamps from oldest to newest
+pub async fn fetch_chart_list(username: &str, api_key: &str) -> Result<Vec<(i64, i64)>> {
+    let client = reqwest::Client::new();
+    let url = lastfm::build_method_url("user.getWeeklyChartList", api_key, &[("user", username)]);
+
+    let chart_list = match lastfm::get_with_retries(&client, &url).await {
+        Some(ApiResponse::Success(WeeklyChartListResponse { weekly_chart_list })) => {
+            weekly_chart_list
+        }
+        Some(ApiResponse::Failure(error)) => return Err(anyhow!("{}", error.message)),
+        None => return Err(anyhow!("Error fetching the weekly chart list")),
+    };
+
+    Ok(chart_list
+        .weeks()
+        .iter()
+        .map(|week| (week.from(), week.to()))
+        .collect())
+}
+
+/// Fetches the weekly artist, album and track charts of each `(from, to)` week
+///
+/// Weeks where any of the three charts could not be fetched are left out, so they are retried the
+/// next time.
+pub async fn fetch_charts(
+    username: &str,
+    weeks: Vec<(i64, i64)>,
+    api_key: &str,
+) -> Vec<WeeklyChart> {
+    let bar = ProgressBar::new(weeks.len() as u64);
+
+    let client = reqwest::Client::builder()
+        .timeout(time::Duration::from_secs(60))
+        .build()
+        .unwrap();
+
+    let charts = stream::iter(weeks)
+        .map(|(from, to)| {
+            let client = client.clone();
+            let username = username.to_string();
+            let api_key = api_key.to_string();
+            let bar = bar.clone();
+
+            tokio::spawn(async move {
+                let chart = fetch_chart(&client, &username, &api_key, from, to).await;
+                bar.inc(1);
+                chart
+            })
+        })
+        .buffer_unordered(lastfm::ENRICHMENT_PARALLEL_REQUESTS)
+        .filter_map(|c| async move { c.unwrap() })
+        .collect::<Vec<WeeklyChart>>()
+        .await;
+
+    bar.finish();
+
+    charts
+}
+
+async fn fetch_chart(
+    client: &reqwest::Client,
+    username: &str,
+    api_key: &str,
+    from: i64,
+    to: i64,
+) -> Option<WeeklyChart> {
+    let (from_param, to_param) = (from.to_string(), to.to_string());
+    let url = |method: &str| {
+        lastfm::build_method_url(
+            method,
+            api_key,
+            &[("user", username), ("from", &from_param), ("to", &to_param)],
+        )
+    };
+
+    let artists = match lastfm::get_with_retries(client, &url("user.getWeeklyArtistChart")).await? {
+        ApiResponse::Success(WeeklyArtistChartResponse { chart }) => chart
+            .artist
+            .as_slice()
+            .iter()
+            .map(WeeklyChartEntry::from_artist_entry)
+            .collect(),
+        ApiResponse::Failure(_) => return None,
+    };
+
+    let albums = match lastfm::get_with_retries(client, &url("user.getWeeklyAlbumChart")).await? {
+        ApiResponse::Success(WeeklyAlbumChartResponse { chart }) => chart
+            .album
+            .as_slice()
+            .iter()
+            .map(WeeklyChartEntry::from_entry)
+            .collect(),
+        ApiResponse::Failure(_) => return None,
+    };
+
+    let tracks = match lastfm::get_with_retries(client, &url("user.getWeeklyTrackChart")).await? {
+        ApiResponse::Success(WeeklyTrackChartResponse { chart }) => chart
+            .track
+            .as_slice()
+            .iter()
+            .map(WeeklyChartEntry::from_entry)
+            .collect(),
+        ApiResponse::Failure(_) => return None,
+    };
+
+    Some(WeeklyChart {
+        from,
+        to,
+        artists,
+        albums,
+        tracks,
+    })
+}
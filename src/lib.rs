@@ -23,5 +23,9 @@ pub mod config;
 pub mod data;
 pub mod lastfm;
 pub mod models;
+pub mod output;
+pub mod query;
+pub mod reports;
 pub mod stats;
+pub mod terminal_charts;
 pub mod utils;
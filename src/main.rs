@@ -22,16 +22,32 @@ async fn main() -> Result<()> {
     let config = Config::load_config()?;
 
     match opts.subcmd {
+        SubCommand::Anomalies(a) => app::anomalies::anomalies(a, config).await?,
+        SubCommand::Binges(b) => app::binges::binges(b, config).await?,
+        SubCommand::Charts(c) => app::charts::charts(c, config).await?,
+        SubCommand::Compare(c) => app::compare::compare(c, config).await?,
         SubCommand::Config(c) => match c.subcmd {
             ConfigSubCommand::Delete(_) => config.delete_config()?,
             ConfigSubCommand::Print(p) => config.print_config(p.full_config),
             ConfigSubCommand::Update(_) => config::update_config()?,
         },
+        SubCommand::Enrich(e) => app::enrich::enrich(e, config).await?,
+        SubCommand::Export(e) => app::export::export(e, config).await?,
         SubCommand::Fetch(f) => app::fetch::fetch(f, config).await?,
-        SubCommand::Stats(s) => app::stats::stats(s, config)?,
+        SubCommand::Loved(l) => app::loved::loved(l, config).await?,
+        SubCommand::Loyalty(l) => app::loyalty::loyalty(l, config).await?,
+        SubCommand::Milestones(m) => app::milestones::milestones(m, config).await?,
+        SubCommand::Normalize(n) => app::normalize::normalize(n, config).await?,
+        SubCommand::OnThisDay(o) => app::on_this_day::on_this_day(o, config).await?,
+        SubCommand::Query(q) => app::query::query(q, config).await?,
+        SubCommand::Report(r) => app::report::report(r, config).await?,
+        SubCommand::Retime(r) => app::retime::retime(r, config).await?,
+        SubCommand::Search(s) => app::search::search(s, config).await?,
+        SubCommand::Sessions(s) => app::sessions::sessions(s, config).await?,
+        SubCommand::Stats(s) => app::stats::stats(s, config).await?,
     }
 
-    println!("\nDone!");
+    eprintln!("\nDone!");
 
     Ok(())
 }
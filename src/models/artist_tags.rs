@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::models::saved_scrobbles::SavedScrobble;
+
+/// A tag applied to an artist by Last.fm users
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArtistTag {
+    pub name: String,
+    /// How strongly the tag applies to the artist, from 0 to 100
+    pub weight: i32,
+}
+
+/// The top tags of an artist, as reported by `artist.getTopTags`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArtistTags {
+    pub artist: String,
+    /// The artist's tags, from the highest weight to the lowest. Empty if the artist has no tags
+    /// or Last.fm does not know the artist.
+    pub tags: Vec<ArtistTag>,
+    /// When the tags were looked up, as a unix timestamp
+    pub fetched_at: i64,
+}
+
+/// A local cache of artist tags, shared by every user
+#[derive(Default)]
+pub struct ArtistTagsCache {
+    tags: HashMap<String, ArtistTags>,
+}
+
+impl ArtistTagsCache {
+    pub fn new(tags: Vec<ArtistTags>) -> Self {
+        let mut cache = Self::default();
+        tags.into_iter().for_each(|t| cache.insert(t));
+        cache
+    }
+
+    pub fn save_as_json(&self, file: &Path) -> Result<()> {
+        let mut tags = self.tags.values().collect::<Vec<&ArtistTags>>();
+        tags.sort_by(|a, b| a.artist.cmp(&b.artist));
+
+        let f = fs::File::create(file)?;
+        let bw = BufWriter::new(f);
+        serde_json::to_writer_pretty(bw, &tags)?;
+
+        Ok(())
+    }
+
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let tags: Vec<ArtistTags> = serde_json::from_reader(br)?;
+
+        Ok(ArtistTagsCache::new(tags))
+    }
+
+    pub fn insert(&mut self, tags: ArtistTags) {
+        self.tags.insert(Self::key(&tags.artist), tags);
+    }
+
+    pub fn get(&self, artist: &str) -> Option<&ArtistTags> {
+        self.tags.get(&Self::key(artist))
+    }
+
+    /// The artists with a tag for which `predicate` returns `true`
+    pub fn artists_tagged<'a, F>(&'a self, predicate: F) -> impl Iterator<Item = &'a str>
+    where
+        F: Fn(&ArtistTag) -> bool + 'a,
+    {
+        self.tags
+            .values()
+            .filter(move |tags| tags.tags.iter().any(&predicate))
+            .map(|tags| tags.artist.as_str())
+    }
+
+    /// The distinct artists in `scrobbles` whose tags have not been looked up yet
+    pub fn missing(&self, scrobbles: &[SavedScrobble]) -> Vec<String> {
+        Self::distinct_artists(scrobbles, |artist| self.get(artist).is_none())
+    }
+
+    /// The distinct artists in `scrobbles` whose tags were looked up before `timestamp`
+    pub fn fetched_before(&self, scrobbles: &[SavedScrobble], timestamp: i64) -> Vec<String> {
+        Self::distinct_artists(scrobbles, |artist| match self.get(artist) {
+            Some(tags) => tags.fetched_at < timestamp,
+            None => false,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    fn distinct_artists<F>(scrobbles: &[SavedScrobble], predicate: F) -> Vec<String>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut artists = scrobbles
+            .iter()
+            .filter(|s| predicate(&s.artist))
+            .map(|s| s.artist.to_string())
+            .collect::<Vec<String>>();
+
+        artists.sort_by_key(|artist| Self::key(artist));
+        artists.dedup_by_key(|artist| Self::key(artist));
+
+        artists
+    }
+
+    /// Artists are matched case-insensitively, the same way Last.fm matches them
+    fn key(artist: &str) -> String {
+        artist.to_lowercase()
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::models::artist_tags::ArtistTagsCache;
+use crate::models::saved_scrobbles::SavedScrobble;
+
+/// Tags with a lower weight do not exclude an artist unless the rules set their own minimum
+const DEFAULT_MIN_TAG_WEIGHT: i32 = 50;
+
+/// Rules for leaving scrobbles that are not music, such as white noise, podcasts or audiobooks,
+/// out of stats and reports
+///
+/// A scrobble is excluded when any rule matches it.
+#[derive(Serialize, Deserialize)]
+pub struct ExclusionRules {
+    /// Artists to exclude, matched ignoring case
+    #[serde(default)]
+    pub artists: Vec<String>,
+    /// Albums to exclude, matched ignoring case
+    #[serde(default)]
+    pub albums: Vec<String>,
+    /// Regular expressions matched against track names, ignoring case
+    #[serde(default)]
+    pub titles: Vec<String>,
+    /// Excludes artists with any of these tags, using the tags looked up with `enrich tags`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Tags with a weight below this are ignored, from 0 to 100
+    #[serde(default = "default_min_tag_weight")]
+    pub min_tag_weight: i32,
+    /// Drops excluded scrobbles as they are fetched, so they are never saved
+    #[serde(default)]
+    pub exclude_when_fetching: bool,
+}
+
+fn default_min_tag_weight() -> i32 {
+    DEFAULT_MIN_TAG_WEIGHT
+}
+
+impl Default for ExclusionRules {
+    fn default() -> Self {
+        Self {
+            artists: Vec::new(),
+            albums: Vec::new(),
+            titles: Vec::new(),
+            tags: Vec::new(),
+            min_tag_weight: DEFAULT_MIN_TAG_WEIGHT,
+            exclude_when_fetching: false,
+        }
+    }
+}
+
+impl ExclusionRules {
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let rules = serde_json::from_reader(br)?;
+
+        Ok(rules)
+    }
+
+    /// Compiles the rules, failing if a title pattern is not a valid regular expression. Tags are
+    /// resolved to the artists in `tags` that have them.
+    pub fn exclusions(&self, tags: &ArtistTagsCache) -> Result<Exclusions> {
+        let lowercase = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| name.trim().to_lowercase())
+                .collect::<HashSet<String>>()
+        };
+
+        let titles = self
+            .titles
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("Invalid title pattern `{}`", pattern))
+            })
+            .collect::<Result<Vec<Regex>>>()?;
+
+        let mut artists = lowercase(&self.artists);
+        let excluded_tags = lowercase(&self.tags);
+        if !excluded_tags.is_empty() {
+            artists.extend(
+                tags.artists_tagged(|tag| {
+                    tag.weight >= self.min_tag_weight
+                        && excluded_tags.contains(&tag.name.to_lowercase())
+                })
+                .map(|artist| artist.to_lowercase()),
+            );
+        }
+
+        Ok(Exclusions {
+            artists,
+            albums: lowercase(&self.albums),
+            titles,
+        })
+    }
+}
+
+/// Compiled [ExclusionRules](struct.ExclusionRules.html)
+#[derive(Default)]
+pub struct Exclusions {
+    artists: HashSet<String>,
+    albums: HashSet<String>,
+    titles: Vec<Regex>,
+}
+
+impl Exclusions {
+    pub fn is_empty(&self) -> bool {
+        self.artists.is_empty() && self.albums.is_empty() && self.titles.is_empty()
+    }
+
+    pub fn excludes(&self, scrobble: &SavedScrobble) -> bool {
+        self.artists.contains(&scrobble.artist.to_lowercase())
+            || (!scrobble.album.is_empty() && self.albums.contains(&scrobble.album.to_lowercase()))
+            || self
+                .titles
+                .iter()
+                .any(|title| title.is_match(&scrobble.title))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::artist_tags::{ArtistTag, ArtistTags};
+    use crate::models::saved_scrobbles::SavedScrobbles;
+
+    #[test]
+    fn test_exclusion_rules() {
+        let rules = ExclusionRules {
+            artists: vec!["rain sounds".to_string()],
+            albums: vec!["Sleep Sounds".to_string()],
+            titles: vec![r"^chapter \d+".to_string()],
+            tags: vec!["white noise".to_string()],
+            ..ExclusionRules::default()
+        };
+        let tags = ArtistTagsCache::new(vec![
+            ArtistTags {
+                artist: "Sleepy Noise".to_string(),
+                tags: vec![ArtistTag {
+                    name: "White Noise".to_string(),
+                    weight: 100,
+                }],
+                fetched_at: 0,
+            },
+            ArtistTags {
+                artist: "Brian Eno".to_string(),
+                tags: vec![ArtistTag {
+                    name: "white noise".to_string(),
+                    weight: 5,
+                }],
+                fetched_at: 0,
+            },
+        ]);
+
+        let jan_1_2021 = 1_609_502_400;
+        let mut saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Thunderstorm", "Rain Sounds", "Storms", jan_1_2021 + 300),
+            SavedScrobble::played("Ocean", "Various Artists", "Sleep Sounds", jan_1_2021 + 240),
+            SavedScrobble::played(
+                "Chapter 12",
+                "Some Narrator",
+                "An Audiobook",
+                jan_1_2021 + 180,
+            ),
+            SavedScrobble::played("Pink Noise", "Sleepy Noise", "", jan_1_2021 + 120),
+            SavedScrobble::played("An Ending", "Brian Eno", "Apollo", jan_1_2021 + 60),
+            SavedScrobble::played("Reckoner", "Radiohead", "In Rainbows", jan_1_2021),
+        ]);
+        assert_eq!(
+            saved_scrobbles.exclude(&rules.exclusions(&tags).unwrap()),
+            4
+        );
+
+        let titles = saved_scrobbles
+            .get_saved_scrobbles()
+            .into_iter()
+            .map(|s| s.title)
+            .collect::<Vec<String>>();
+        assert_eq!(titles, vec!["An Ending", "Reckoner"]);
+
+        let invalid = ExclusionRules {
+            titles: vec!["(".to_string()],
+            ..ExclusionRules::default()
+        };
+        assert!(invalid.exclusions(&tags).is_err());
+    }
+}
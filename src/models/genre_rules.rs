@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::models::artist_tags::ArtistTags;
+
+/// Tags with a lower weight are ignored unless the rules set their own minimum
+const DEFAULT_MIN_WEIGHT: i32 = 10;
+
+/// Rules for mapping Last.fm tags onto a small set of genres
+///
+/// Each artist is given the genre whose tags have the highest combined weight among the artist's
+/// tags. Tags are matched case-insensitively.
+#[derive(Serialize, Deserialize)]
+pub struct GenreRules {
+    /// Tags with a weight below this are ignored, from 0 to 100
+    #[serde(default = "default_min_weight")]
+    pub min_weight: i32,
+    /// Checked in order, so the first genre wins a tie
+    pub genres: Vec<GenreRule>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GenreRule {
+    pub genre: String,
+    pub tags: Vec<String>,
+}
+
+fn default_min_weight() -> i32 {
+    DEFAULT_MIN_WEIGHT
+}
+
+impl GenreRules {
+    pub fn save_as_json(&self, file: &Path) -> Result<()> {
+        let f = fs::File::create(file)?;
+        let bw = BufWriter::new(f);
+        serde_json::to_writer_pretty(bw, &self)?;
+
+        Ok(())
+    }
+
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let rules = serde_json::from_reader(br)?;
+
+        Ok(rules)
+    }
+
+    /// The genre of an artist, or `None` if none of the artist's tags match a rule
+    pub fn genre_of(&self, artist_tags: &ArtistTags) -> Option<&str> {
+        let mut weights: HashMap<usize, i32> = HashMap::new();
+
+        for tag in artist_tags
+            .tags
+            .iter()
+            .filter(|t| t.weight >= self.min_weight)
+        {
+            let name = tag.name.to_lowercase();
+            if let Some(rule) = self
+                .genres
+                .iter()
+                .position(|rule| rule.tags.iter().any(|t| t.to_lowercase() == name))
+            {
+                *weights.entry(rule).or_insert(0) += tag.weight;
+            }
+        }
+
+        weights
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map(|(rule, _)| self.genres[rule].genre.as_str())
+    }
+}
+
+impl Default for GenreRules {
+    /// A starting point that covers the most common Last.fm tags, meant to be edited
+    fn default() -> Self {
+        let rule = |genre: &str, tags: &[&str]| GenreRule {
+            genre: genre.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        };
+
+        Self {
+            min_weight: DEFAULT_MIN_WEIGHT,
+            genres: vec![
+                rule(
+                    "Electronic",
+                    &[
+                        "electronic",
+                        "electronica",
+                        "idm",
+                        "ambient",
+                        "techno",
+                        "house",
+                        "trance",
+                        "edm",
+                        "dubstep",
+                        "drum and bass",
+                        "downtempo",
+                        "synthpop",
+                        "trip-hop",
+                    ],
+                ),
+                rule(
+                    "Metal",
+                    &[
+                        "metal",
+                        "heavy metal",
+                        "black metal",
+                        "death metal",
+                        "doom metal",
+                        "metalcore",
+                        "progressive metal",
+                    ],
+                ),
+                rule(
+                    "Rock",
+                    &[
+                        "rock",
+                        "alternative",
+                        "alternative rock",
+                        "indie",
+                        "indie rock",
+                        "classic rock",
+                        "punk",
+                        "post-punk",
+                        "post-rock",
+                        "shoegaze",
+                        "grunge",
+                        "hard rock",
+                        "progressive rock",
+                        "psychedelic",
+                    ],
+                ),
+                rule("Pop", &["pop", "indie pop", "k-pop", "dance", "electropop"]),
+                rule("Hip-Hop", &["hip-hop", "hip hop", "rap", "trap"]),
+                rule("R&B", &["rnb", "r&b", "soul", "funk", "neo-soul"]),
+                rule("Jazz", &["jazz", "fusion", "bebop", "smooth jazz"]),
+                rule(
+                    "Classical",
+                    &["classical", "contemporary classical", "baroque", "opera"],
+                ),
+                rule(
+                    "Folk",
+                    &[
+                        "folk",
+                        "singer-songwriter",
+                        "acoustic",
+                        "americana",
+                        "country",
+                    ],
+                ),
+                rule("Blues", &["blues"]),
+                rule("Reggae", &["reggae", "ska", "dub"]),
+                rule("Soundtrack", &["soundtrack", "video game music", "score"]),
+            ],
+        }
+    }
+}
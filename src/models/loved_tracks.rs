@@ -0,0 +1,43 @@
+use serde::Deserialize;
+
+use crate::models::recent_tracks::{Attr, Date};
+use crate::models::OneOrMany;
+
+#[derive(Debug, Deserialize)]
+pub struct LovedTracksResponse {
+    #[serde(rename = "lovedtracks")]
+    pub loved_tracks: LovedTracks,
+}
+
+/// A page of the response to a `user.getLovedTracks` request
+#[derive(Debug, Deserialize)]
+pub struct LovedTracks {
+    #[serde(rename = "@attr")]
+    pub attr: Attr,
+    /// Missing when the user has not loved any tracks
+    #[serde(default)]
+    track: OneOrMany<LovedTrack>,
+}
+
+impl LovedTracks {
+    pub fn tracks(&self) -> &[LovedTrack] {
+        self.track.as_slice()
+    }
+
+    pub fn into_tracks(self) -> Vec<LovedTrack> {
+        self.track.into_vec()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LovedTrack {
+    pub name: String,
+    pub artist: LovedTrackArtist,
+    /// When the track was loved
+    pub date: Date,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LovedTrackArtist {
+    pub name: String,
+}
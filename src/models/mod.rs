@@ -2,9 +2,21 @@
 
 use serde::Deserialize;
 
+pub mod artist_tags;
+pub mod exclusion_rules;
+pub mod genre_rules;
+pub mod loved_tracks;
+pub mod name_rules;
 pub mod recent_tracks;
+pub mod saved_loved_tracks;
 pub mod saved_scrobbles;
+pub mod saved_weekly_charts;
+pub mod time_zones;
+pub mod top_tags;
+pub mod track_durations;
+pub mod track_info;
 pub mod user;
+pub mod weekly_charts;
 
 #[derive(Deserialize)]
 #[serde(untagged)]
@@ -18,3 +30,34 @@ pub struct ErrorResponse {
     pub error: i32,
     pub message: String,
 }
+
+/// A list in an API response, which Last.fm returns as a single object instead of an array when
+/// the list has only one item
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn as_slice(&self) -> &[T] {
+        match self {
+            OneOrMany::Many(items) => items,
+            OneOrMany::One(item) => std::slice::from_ref(item),
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::Many(items) => items,
+            OneOrMany::One(item) => vec![item],
+        }
+    }
+}
+
+impl<T> Default for OneOrMany<T> {
+    fn default() -> Self {
+        OneOrMany::Many(Vec::new())
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Matches a featured artist credit at the end of a name, e.g. ` feat. Someone` or ` (ft. Someone)`
+const FEATURED_PATTERN: &str = r"\s+[(\[]?(?:feat\.?|ft\.|featuring)\s+([^)\]]+?)[)\]]?$";
+
+/// Matches a suffix at the end of a name, after ` - ` or in brackets, e.g. ` - Live` or ` (Live)`
+const SUFFIX_PATTERN: &str = r"\s*(?:[-–]\s*(?:{suffix})|[(\[]\s*(?:{suffix})\s*[)\]])\s*$";
+
+/// Rules for giving every artist, album and track a single canonical spelling
+///
+/// Featured artists are handled first, then the rewrites are applied in order, and finally the
+/// aliases are looked up. The cleanup is separate, and only applies when scrobbles are grouped.
+#[derive(Serialize, Deserialize)]
+pub struct NameRules {
+    /// Artist names mapped to their canonical spelling, matched ignoring case
+    #[serde(default)]
+    pub artists: BTreeMap<String, String>,
+    /// Album names mapped to their canonical spelling, matched ignoring case
+    #[serde(default)]
+    pub albums: BTreeMap<String, String>,
+    /// Track names mapped to their canonical spelling, matched ignoring case
+    #[serde(default)]
+    pub tracks: BTreeMap<String, String>,
+    #[serde(default)]
+    pub rewrites: Vec<Rewrite>,
+    #[serde(default)]
+    pub featured_artists: FeaturedArtists,
+    /// Suffixes removed from track and album names when scrobbles are grouped, without changing
+    /// the saved names
+    #[serde(default = "default_cleanup")]
+    pub cleanup: Vec<CleanupPattern>,
+    /// More suffixes to remove when scrobbles are grouped, as regular expressions
+    #[serde(default)]
+    pub cleanup_patterns: Vec<String>,
+}
+
+/// Live recordings and tracks with a year are often different recordings, so they are only
+/// merged when asked for
+fn default_cleanup() -> Vec<CleanupPattern> {
+    vec![
+        CleanupPattern::Remaster,
+        CleanupPattern::Deluxe,
+        CleanupPattern::MonoStereo,
+    ]
+}
+
+impl Default for NameRules {
+    /// No aliases or rewrites, and the default cleanup patterns
+    fn default() -> Self {
+        Self {
+            artists: BTreeMap::new(),
+            albums: BTreeMap::new(),
+            tracks: BTreeMap::new(),
+            rewrites: Vec::new(),
+            featured_artists: FeaturedArtists::default(),
+            cleanup: default_cleanup(),
+            cleanup_patterns: Vec::new(),
+        }
+    }
+}
+
+/// Replaces every match of a regular expression in one kind of name. The replacement can refer
+/// to capture groups, e.g. `$1`.
+#[derive(Serialize, Deserialize)]
+pub struct Rewrite {
+    pub field: NameField,
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameField {
+    Artist,
+    Album,
+    Track,
+}
+
+/// What to do with artists credited as featuring another, e.g. `Someone feat. Someone Else`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FeaturedArtists {
+    /// Leaves names as they are
+    #[default]
+    Keep,
+    /// Credits the main artist only, and adds ` (feat. Someone Else)` to the track name
+    MoveToTitle,
+    /// Removes featured artists from artist and track names
+    Remove,
+}
+
+/// Suffixes that streaming services add to track and album names
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanupPattern {
+    /// e.g. `Remastered 2011`, `2009 Remaster` or `Digital Remaster`
+    Remaster,
+    /// e.g. `Deluxe Edition`, `Expanded Edition` or `25th Anniversary Edition`
+    Deluxe,
+    /// e.g. `Live`, `Live Version` or `Live at Wembley`
+    Live,
+    /// e.g. `Mono`, `Stereo Mix` or `2009 Mono Remaster`
+    MonoStereo,
+    /// e.g. `1999` or `2011 Version`
+    Year,
+}
+
+impl CleanupPattern {
+    /// The suffix, without the ` - ` or brackets that separate it from the name
+    fn suffix(&self) -> &'static str {
+        match self {
+            CleanupPattern::Remaster => {
+                r"(?:\d{4}\s+)?(?:digital(?:ly)?\s+)?re-?master(?:ed)?(?:\s+(?:\d{4}|version|edition))*"
+            }
+            CleanupPattern::Deluxe => {
+                r"(?:super\s+)?deluxe(?:\s+(?:edition|version))?|(?:expanded|special|\d+(?:st|nd|rd|th)\s+anniversary)\s+edition"
+            }
+            CleanupPattern::Live => {
+                r"live(?:\s+(?:version|recording|(?:at|from|in|on)\s+[^)\]]+))?"
+            }
+            CleanupPattern::MonoStereo => {
+                r"(?:\d{4}\s+)?(?:mono|stereo)(?:\s+(?:version|mix|re-?master(?:ed)?))?(?:\s+\d{4})?"
+            }
+            CleanupPattern::Year => r"\d{4}(?:\s+(?:version|mix|edit))?",
+        }
+    }
+}
+
+impl NameRules {
+    pub fn save_as_json(&self, file: &Path) -> Result<()> {
+        let f = fs::File::create(file)?;
+        let bw = BufWriter::new(f);
+        serde_json::to_writer_pretty(bw, &self)?;
+
+        Ok(())
+    }
+
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let rules = serde_json::from_reader(br)?;
+
+        Ok(rules)
+    }
+
+    /// Compiles the rules, failing if a rewrite's pattern is not a valid regular expression
+    pub fn canonicalizer(&self) -> Result<NameCanonicalizer> {
+        let aliases = |aliases: &BTreeMap<String, String>| {
+            aliases
+                .iter()
+                .map(|(alias, name)| (alias.to_lowercase(), name.to_string()))
+                .collect::<HashMap<String, String>>()
+        };
+
+        let rewrites = self
+            .rewrites
+            .iter()
+            .map(|rewrite| {
+                let regex = RegexBuilder::new(&rewrite.pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("Invalid rewrite pattern `{}`", rewrite.pattern))?;
+                Ok((rewrite.field, regex, rewrite.replacement.to_string()))
+            })
+            .collect::<Result<Vec<(NameField, Regex, String)>>>()?;
+
+        Ok(NameCanonicalizer {
+            artists: aliases(&self.artists),
+            albums: aliases(&self.albums),
+            tracks: aliases(&self.tracks),
+            rewrites,
+            featured_artists: self.featured_artists,
+            featured: RegexBuilder::new(FEATURED_PATTERN)
+                .case_insensitive(true)
+                .build()?,
+        })
+    }
+
+    /// Compiles the cleanup, failing if a cleanup pattern is not a valid regular expression
+    pub fn title_cleaner(&self) -> Result<TitleCleaner> {
+        let suffixes = self
+            .cleanup
+            .iter()
+            .map(|pattern| pattern.suffix())
+            .chain(self.cleanup_patterns.iter().map(|pattern| pattern.as_str()))
+            .map(|suffix| {
+                RegexBuilder::new(&SUFFIX_PATTERN.replace("{suffix}", suffix))
+                    .case_insensitive(true)
+                    .build()
+                    .with_context(|| format!("Invalid cleanup pattern `{}`", suffix))
+            })
+            .collect::<Result<Vec<Regex>>>()?;
+
+        Ok(TitleCleaner { suffixes })
+    }
+}
+
+/// Compiled cleanup patterns, which remove suffixes from track and album names
+#[derive(Default)]
+pub struct TitleCleaner {
+    suffixes: Vec<Regex>,
+}
+
+impl TitleCleaner {
+    /// Removes suffixes until none are left, e.g. `Song - 2011 Remaster (Mono)` becomes `Song`.
+    /// A name that is nothing but a suffix, e.g. an album called `Live`, is left alone.
+    pub fn clean(&self, name: &str) -> String {
+        let mut cleaned = name.trim().to_string();
+
+        loop {
+            let shorter = self.suffixes.iter().fold(cleaned.clone(), |name, suffix| {
+                suffix.replace(&name, "").trim_end().to_string()
+            });
+            if shorter.is_empty() {
+                return cleaned;
+            }
+            if shorter == cleaned {
+                return shorter;
+            }
+            cleaned = shorter;
+        }
+    }
+}
+
+/// The names of a scrobbled track
+#[derive(Clone, Debug, PartialEq)]
+pub struct Names {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+}
+
+/// Compiled [NameRules](struct.NameRules.html)
+pub struct NameCanonicalizer {
+    artists: HashMap<String, String>,
+    albums: HashMap<String, String>,
+    tracks: HashMap<String, String>,
+    rewrites: Vec<(NameField, Regex, String)>,
+    featured_artists: FeaturedArtists,
+    featured: Regex,
+}
+
+impl NameCanonicalizer {
+    /// The canonical spelling of a track's names
+    pub fn canonical(&self, names: &Names) -> Names {
+        let mut names = names.clone();
+
+        if self.featured_artists != FeaturedArtists::Keep {
+            if let Some(captures) = self.featured.captures(&names.artist) {
+                let featured = captures[1].trim().to_string();
+                names.artist = names.artist[..captures.get(0).unwrap().start()].to_string();
+
+                if self.featured_artists == FeaturedArtists::MoveToTitle
+                    && !self.featured.is_match(&names.title)
+                {
+                    names.title = format!("{} (feat. {})", names.title, featured);
+                }
+            }
+
+            if self.featured_artists == FeaturedArtists::Remove {
+                names.title = self.featured.replace(&names.title, "").to_string();
+            }
+        }
+
+        for (field, regex, replacement) in &self.rewrites {
+            let name = match field {
+                NameField::Artist => &mut names.artist,
+                NameField::Album => &mut names.album,
+                NameField::Track => &mut names.title,
+            };
+            *name = regex.replace_all(name, replacement.as_str()).to_string();
+        }
+
+        let alias = |aliases: &HashMap<String, String>, name: &str| {
+            let name = name.trim();
+            aliases
+                .get(&name.to_lowercase())
+                .cloned()
+                .unwrap_or_else(|| name.to_string())
+        };
+
+        Names {
+            title: alias(&self.tracks, &names.title),
+            artist: alias(&self.artists, &names.artist),
+            album: alias(&self.albums, &names.album),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::saved_scrobbles::{SavedScrobble, SavedScrobbles};
+
+    #[test]
+    fn test_name_rules() {
+        let mut rules = NameRules {
+            featured_artists: FeaturedArtists::MoveToTitle,
+            ..NameRules::default()
+        };
+        rules
+            .artists
+            .insert("beatles".to_string(), "The Beatles".to_string());
+        rules.rewrites.push(Rewrite {
+            field: NameField::Album,
+            pattern: r"\s+\(remastered\)$".to_string(),
+            replacement: String::new(),
+        });
+
+        let jan_1_2021 = 1_609_502_400;
+        let mut saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played(
+                "Something",
+                "Beatles",
+                "Abbey Road (Remastered)",
+                jan_1_2021 + 60,
+            ),
+            SavedScrobble::played(
+                "Rockstar",
+                "Post Malone feat. 21 Savage",
+                "Beerbongs",
+                jan_1_2021,
+            ),
+        ]);
+        assert_eq!(
+            saved_scrobbles.normalize(&rules.canonicalizer().unwrap()),
+            2
+        );
+
+        let scrobbles = saved_scrobbles.get_saved_scrobbles();
+        assert_eq!(scrobbles[0].artist, "The Beatles");
+        assert_eq!(scrobbles[0].album, "Abbey Road");
+        assert_eq!(scrobbles[0].original_artist.as_deref(), Some("Beatles"));
+        assert_eq!(scrobbles[0].original_title, None);
+        assert_eq!(scrobbles[1].artist, "Post Malone");
+        assert_eq!(scrobbles[1].title, "Rockstar (feat. 21 Savage)");
+
+        // Rules are always applied to the original names, so removing a rule undoes it
+        assert_eq!(
+            saved_scrobbles.normalize(&rules.canonicalizer().unwrap()),
+            0
+        );
+        let names = NameRules::default().canonicalizer().unwrap();
+        assert_eq!(saved_scrobbles.normalize(&names), 2);
+        let scrobbles = saved_scrobbles.get_saved_scrobbles();
+        assert_eq!(scrobbles[0].artist, "Beatles");
+        assert_eq!(scrobbles[0].original_artist, None);
+        assert_eq!(scrobbles[1].artist, "Post Malone feat. 21 Savage");
+    }
+
+    #[test]
+    fn test_title_cleaner() {
+        let cleaner = NameRules::default().title_cleaner().unwrap();
+        assert_eq!(cleaner.clean("Something - Remastered 2009"), "Something");
+        assert_eq!(cleaner.clean("Help! (Deluxe Edition) [Mono]"), "Help!");
+        // Live recordings and years are only removed when asked for
+        assert_eq!(
+            cleaner.clean("Paranoid Android - Live at Glastonbury"),
+            "Paranoid Android - Live at Glastonbury"
+        );
+        assert_eq!(
+            cleaner.clean("Let It Be (2021 Mix)"),
+            "Let It Be (2021 Mix)"
+        );
+
+        let rules = NameRules {
+            cleanup: vec![CleanupPattern::Live, CleanupPattern::Year],
+            ..NameRules::default()
+        };
+        let cleaner = rules.title_cleaner().unwrap();
+        assert_eq!(cleaner.clean("Let It Be (2021 Mix)"), "Let It Be");
+        assert_eq!(
+            cleaner.clean("Paranoid Android - Live at Glastonbury"),
+            "Paranoid Android"
+        );
+        assert_eq!(cleaner.clean("Live Forever"), "Live Forever");
+        // Nothing is left of a name that is only a suffix
+        assert_eq!(cleaner.clean("(Live)"), "(Live)");
+
+        let rules = NameRules {
+            cleanup: vec![CleanupPattern::Remaster],
+            cleanup_patterns: vec!["radio edit".to_string()],
+            ..NameRules::default()
+        };
+        let cleaner = rules.title_cleaner().unwrap();
+        assert_eq!(cleaner.clean("Song - 2011 Remaster"), "Song");
+        assert_eq!(cleaner.clean("Song (Radio Edit)"), "Song");
+        assert_eq!(cleaner.clean("Song (Live)"), "Song (Live)");
+    }
+}
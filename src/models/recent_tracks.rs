@@ -20,7 +20,6 @@ pub struct Attr {
     page: String,
     #[serde(rename = "perPage")]
     per_page: String,
-    user: String,
     total: String,
     #[serde(rename = "totalPages")]
     total_pages: String,
@@ -120,8 +119,6 @@ pub struct Album {
 #[derive(Clone, Debug, Deserialize)]
 pub struct Date {
     uts: String,
-    #[serde(rename = "#text")]
-    text: String,
 }
 
 impl Date {
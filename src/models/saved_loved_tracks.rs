@@ -0,0 +1,132 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::loved_tracks::LovedTrack;
+use crate::output::{Report, Section};
+
+/// A user's loved tracks, from the most recently loved to the least
+#[derive(Default, Serialize)]
+pub struct SavedLovedTracks {
+    loved_tracks: Vec<SavedLovedTrack>,
+}
+
+impl SavedLovedTracks {
+    pub fn new(loved_tracks: Vec<SavedLovedTrack>) -> Self {
+        let mut loved_tracks = Self { loved_tracks };
+        loved_tracks.sort();
+        loved_tracks
+    }
+
+    pub fn from_loved_tracks(loved_tracks: &[LovedTrack]) -> Self {
+        SavedLovedTracks::new(
+            loved_tracks
+                .iter()
+                .map(SavedLovedTrack::from_loved_track)
+                .collect(),
+        )
+    }
+
+    pub fn save_as_json(&self, file: &Path) -> Result<()> {
+        let f = fs::File::create(file)?;
+        let bw = BufWriter::new(f);
+        serde_json::to_writer_pretty(bw, &self.loved_tracks)?;
+
+        Ok(())
+    }
+
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let loved_tracks: Vec<SavedLovedTrack> = serde_json::from_reader(br)?;
+
+        Ok(SavedLovedTracks::new(loved_tracks))
+    }
+
+    /// The `(artist, title)` pairs of every loved track, lowercased for case-insensitive matching
+    pub fn keys(&self) -> HashSet<(String, String)> {
+        self.loved_tracks
+            .iter()
+            .map(|t| Self::key(&t.artist, &t.title))
+            .collect()
+    }
+
+    pub fn as_slice(&self) -> &[SavedLovedTrack] {
+        &self.loved_tracks
+    }
+
+    /// Keeps only the `n` most recently loved tracks
+    pub fn truncate(&mut self, n: usize) {
+        self.loved_tracks.truncate(n)
+    }
+
+    pub fn len(&self) -> usize {
+        self.loved_tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.loved_tracks.is_empty()
+    }
+
+    fn sort(&mut self) {
+        self.loved_tracks.sort_by_key(|t| Reverse(t.timestamp_utc));
+    }
+
+    fn key(artist: &str, title: &str) -> (String, String) {
+        (artist.to_lowercase(), title.to_lowercase())
+    }
+}
+
+impl Report for SavedLovedTracks {
+    fn sections(&self) -> Vec<Section> {
+        vec![Section::table(
+            "Loved Tracks",
+            &["Loved", "Track", "Artist"],
+            self.loved_tracks
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.datetime_local.format("%F %R").to_string().into(),
+                        t.title.as_str().into(),
+                        t.artist.as_str().into(),
+                    ]
+                })
+                .collect(),
+        )]
+    }
+}
+
+/// A loved track and when it was loved
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedLovedTrack {
+    pub title: String,
+    pub artist: String,
+    pub datetime_local: DateTime<Local>,
+    pub timestamp_utc: i64,
+}
+
+impl SavedLovedTrack {
+    /// Creates a loved track, deriving its local date and time from the UTC timestamp
+    pub fn new(title: String, artist: String, timestamp_utc: i64) -> Self {
+        Self {
+            title,
+            artist,
+            datetime_local: Local.timestamp(timestamp_utc, 0),
+            timestamp_utc,
+        }
+    }
+
+    pub fn from_loved_track(loved_track: &LovedTrack) -> Self {
+        SavedLovedTrack::new(
+            loved_track.name.to_string(),
+            loved_track.artist.name.to_string(),
+            loved_track.date.time_stamp(),
+        )
+    }
+}
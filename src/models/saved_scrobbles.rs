@@ -1,4 +1,4 @@
-use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
@@ -10,22 +10,19 @@ use csv::{Reader, Writer};
 use num_format::ToFormattedString;
 use serde::{Deserialize, Serialize};
 
+use crate::models::exclusion_rules::Exclusions;
+use crate::models::name_rules::{NameCanonicalizer, Names, TitleCleaner};
 use crate::models::recent_tracks::Track;
+use crate::models::saved_loved_tracks::SavedLovedTracks;
+use crate::models::time_zones::LocalTimes;
 use crate::stats::Stats;
 use crate::utils;
 
+#[derive(Default)]
 pub struct SavedScrobbles {
     saved_scrobbles: Vec<SavedScrobble>,
 }
 
-impl Default for SavedScrobbles {
-    fn default() -> Self {
-        Self {
-            saved_scrobbles: Vec::default(),
-        }
-    }
-}
-
 impl SavedScrobbles {
     pub fn new(saved_scrobbles: Vec<SavedScrobble>) -> Self {
         let mut saved_scrobbles = Self { saved_scrobbles };
@@ -73,9 +70,9 @@ impl SavedScrobbles {
         Ok(SavedScrobbles::new(saved_scrobbles))
     }
 
-    pub fn append_new_scrobbles(&mut self, new_scrobbles: &[Track]) {
-        let mut new_saved_scrobbles = SavedScrobbles::convert_scrobbles(new_scrobbles);
-        self.saved_scrobbles.append(&mut new_saved_scrobbles);
+    pub fn append_new_scrobbles(&mut self, mut new_scrobbles: SavedScrobbles) {
+        self.saved_scrobbles
+            .append(&mut new_scrobbles.saved_scrobbles);
         self.sort()
     }
 
@@ -96,59 +93,265 @@ impl SavedScrobbles {
             .to_formatted_string(&utils::get_locale())
     }
 
+    /// Borrows the saved scrobbles, sorted from newest to oldest
+    pub fn as_slice(&self) -> &[SavedScrobble] {
+        &self.saved_scrobbles
+    }
+
     pub fn get_saved_scrobbles(&self) -> Vec<SavedScrobble> {
-        return self.saved_scrobbles.clone();
+        self.saved_scrobbles.clone()
     }
 
     pub fn is_empty(&self) -> bool {
         self.saved_scrobbles.is_empty()
     }
 
+    /// Keeps only the scrobbles for which `f` returns `true`, preserving their order
+    pub fn retain<F: FnMut(&SavedScrobble) -> bool>(&mut self, f: F) {
+        self.saved_scrobbles.retain(f)
+    }
+
+    /// Renames every scrobble to the canonical spelling of its original names, and returns the
+    /// number of scrobbles that changed
+    pub fn normalize(&mut self, rules: &NameCanonicalizer) -> i32 {
+        let mut changed = 0;
+        for scrobble in &mut self.saved_scrobbles {
+            if scrobble.normalize(rules) {
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Removes the scrobbles at the given positions, and returns the number removed
+    pub fn remove(&mut self, indices: &[usize]) -> i32 {
+        let indices = indices.iter().collect::<HashSet<&usize>>();
+        let before = self.saved_scrobbles.len();
+
+        let mut index = 0;
+        self.saved_scrobbles.retain(|_| {
+            let keep = !indices.contains(&index);
+            index += 1;
+            keep
+        });
+
+        (before - self.saved_scrobbles.len()) as i32
+    }
+
+    /// Removes the scrobbles matched by `exclusions`, and returns the number removed
+    pub fn exclude(&mut self, exclusions: &Exclusions) -> i32 {
+        let before = self.saved_scrobbles.len();
+        if !exclusions.is_empty() {
+            self.saved_scrobbles.retain(|s| !exclusions.excludes(s));
+        }
+
+        (before - self.saved_scrobbles.len()) as i32
+    }
+
+    /// Recomputes the local time of every scrobble with `local_times`, and returns the number of
+    /// scrobbles whose local time changed
+    pub fn retime(&mut self, local_times: &LocalTimes) -> i32 {
+        let mut changed = 0;
+        for scrobble in &mut self.saved_scrobbles {
+            if scrobble.retime(local_times) {
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Groups the tracks and albums of an artist whose names only differ by the suffixes matched
+    /// by `cleaner`, without changing the names. Each group is shown under the cleaned up name.
+    /// Returns the number of scrobbles shown under another name.
+    pub fn clean_titles(&mut self, cleaner: &TitleCleaner) -> i32 {
+        let titles = group_names(
+            self.saved_scrobbles.iter().map(|s| (&s.artist, &s.title)),
+            cleaner,
+        );
+        let albums = group_names(
+            self.saved_scrobbles.iter().map(|s| (&s.artist, &s.album)),
+            cleaner,
+        );
+
+        let group = |groups: &HashMap<(String, String), String>, artist: &str, name: &str| {
+            groups
+                .get(&(artist.to_string(), name.to_string()))
+                .filter(|group| group.as_str() != name)
+                .cloned()
+        };
+
+        let mut changed = 0;
+        for scrobble in &mut self.saved_scrobbles {
+            scrobble.group_title = group(&titles, &scrobble.artist, &scrobble.title);
+            scrobble.group_album = group(&albums, &scrobble.artist, &scrobble.album);
+
+            if scrobble.group_title.is_some() || scrobble.group_album.is_some() {
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Sets the `loved` flag of every scrobble to match the user's current loved tracks, and
+    /// returns the number of scrobbles that changed
+    ///
+    /// Loved tracks carry the names Last.fm knows, so they are matched against the names the
+    /// scrobbles had before any name rules were applied.
+    pub fn update_loved(&mut self, loved_tracks: &SavedLovedTracks) -> i32 {
+        let loved = loved_tracks.keys();
+        let mut changed = 0;
+
+        for scrobble in &mut self.saved_scrobbles {
+            let names = scrobble.original_names();
+            let is_loved =
+                loved.contains(&(names.artist.to_lowercase(), names.title.to_lowercase()));
+
+            if scrobble.loved != is_loved {
+                scrobble.loved = is_loved;
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    /// Sorts scrobbles from newest to oldest, merging copies of the same scrobble
+    ///
+    /// Scrobbles with the same timestamp are ordered by artist and track, so the order never
+    /// depends on the order scrobbles were fetched in.
     fn sort(&mut self) {
         self.saved_scrobbles
-            .sort_unstable_by_key(|s| s.timestamp_utc);
-        self.saved_scrobbles.dedup_by_key(|s| s.calculate_hash());
+            .sort_by(|a, b| a.identity().cmp(&b.identity()));
+        self.saved_scrobbles.dedup_by(|duplicate, kept| {
+            if duplicate.identity() != kept.identity() {
+                return false;
+            }
+            kept.merge(duplicate);
+            true
+        });
         self.saved_scrobbles.reverse();
     }
 
     fn convert_scrobbles(scrobbles: &[Track]) -> Vec<SavedScrobble> {
         scrobbles
             .iter()
-            .map(|scrobble| SavedScrobble::from_scrobble(scrobble))
+            .map(SavedScrobble::from_scrobble)
             .collect::<Vec<SavedScrobble>>()
     }
 }
 
 /// Represents the data that is saved to a file from a given [Track](struct.Track.html)
-#[derive(Serialize, Deserialize, Clone, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SavedScrobble {
     pub title: String,
     pub artist: String,
     pub album: String,
     pub loved: bool,
-    pub datetime_local: DateTime<Local>,
+    /// The time in the time zone the scrobble was listened in, which keeps its offset when saved
+    pub datetime_local: DateTime<FixedOffset>,
     pub timestamp_utc: i64,
+    /// The track name as scrobbled, when name rules changed it
+    #[serde(default)]
+    pub original_title: Option<String>,
+    /// The artist name as scrobbled, when name rules changed it
+    #[serde(default)]
+    pub original_artist: Option<String>,
+    /// The album name as scrobbled, when name rules changed it
+    #[serde(default)]
+    pub original_album: Option<String>,
+    /// The track name this scrobble is counted under when scrobbles are grouped, when the title
+    /// cleanup merges it with a more scrobbled name
+    #[serde(skip)]
+    pub group_title: Option<String>,
+    /// The album name this scrobble is counted under when scrobbles are grouped, when the title
+    /// cleanup merges it with a more scrobbled name
+    #[serde(skip)]
+    pub group_album: Option<String>,
 }
 
 impl SavedScrobble {
+    /// Creates a scrobble, deriving its local date and time from the UTC timestamp in the time
+    /// zone of this machine
+    pub fn new(
+        title: String,
+        artist: String,
+        album: String,
+        loved: bool,
+        timestamp_utc: i64,
+    ) -> Self {
+        Self {
+            title,
+            artist,
+            album,
+            loved,
+            datetime_local: Local.timestamp(timestamp_utc, 0).into(),
+            timestamp_utc,
+            original_title: None,
+            original_artist: None,
+            original_album: None,
+            group_title: None,
+            group_album: None,
+        }
+    }
+
+    /// Creates a scrobble of a track that is not loved, e.g. to build a history by hand
+    pub fn played(title: &str, artist: &str, album: &str, timestamp_utc: i64) -> Self {
+        Self::new(
+            title.to_string(),
+            artist.to_string(),
+            album.to_string(),
+            false,
+            timestamp_utc,
+        )
+    }
+
     pub fn from_scrobble(scrobble: &Track) -> Self {
         Self {
             title: scrobble.name.to_string(),
             artist: scrobble.artist.name.to_string(),
             album: scrobble.album.text.to_string(),
             loved: scrobble.loved(),
-            datetime_local: scrobble.date().datetime_local(),
+            datetime_local: scrobble.date().datetime_local().into(),
             timestamp_utc: scrobble.date().time_stamp(),
+            original_title: None,
+            original_artist: None,
+            original_album: None,
+            group_title: None,
+            group_album: None,
         }
     }
 
     pub fn from_scrobbles(scrobbles: &[Track]) -> Vec<SavedScrobble> {
         scrobbles
             .iter()
-            .map(|scrobble| SavedScrobble::from_scrobble(scrobble))
+            .map(SavedScrobble::from_scrobble)
             .collect::<Vec<SavedScrobble>>()
     }
 
+    /// The offset of the local time from UTC, in seconds
+    pub fn utc_offset(&self) -> i32 {
+        self.datetime_local.offset().local_minus_utc()
+    }
+
+    /// Moves the local time to another offset from UTC, in seconds, keeping the UTC timestamp
+    pub fn set_utc_offset(&mut self, utc_offset: i32) {
+        self.datetime_local = FixedOffset::east(utc_offset).timestamp(self.timestamp_utc, 0);
+    }
+
+    /// Recomputes the local time with `local_times`, returning whether it changed
+    pub fn retime(&mut self, local_times: &LocalTimes) -> bool {
+        let datetime_local = local_times.datetime_local(self.timestamp_utc);
+        if datetime_local.offset() == self.datetime_local.offset() {
+            return false;
+        }
+
+        self.datetime_local = datetime_local;
+        true
+    }
+
     pub fn date(&self) -> NaiveDate {
         self.datetime_local.naive_local().date()
     }
@@ -169,15 +372,122 @@ impl SavedScrobble {
         format!("{} - {}", self.artist, self.album)
     }
 
+    /// The track and artist that the scrobble is counted under when scrobbles are grouped
+    pub fn group_song_artist(&self) -> String {
+        format!(
+            "{} - {}",
+            self.group_title.as_deref().unwrap_or(&self.title),
+            self.artist
+        )
+    }
+
+    /// The artist and album that the scrobble is counted under when scrobbles are grouped
+    pub fn group_artist_album(&self) -> String {
+        format!(
+            "{} - {}",
+            self.artist,
+            self.group_album.as_deref().unwrap_or(&self.album)
+        )
+    }
+
     pub fn combined_title(&self) -> String {
         format!("{} - {} - {}", self.title, self.artist, self.album)
     }
 
-    pub fn calculate_hash(&self) -> u64 {
-        use std::hash::{Hash, Hasher};
+    /// The names as they were scrobbled, before any name rules were applied
+    pub fn original_names(&self) -> Names {
+        Names {
+            title: self
+                .original_title
+                .as_ref()
+                .unwrap_or(&self.title)
+                .to_string(),
+            artist: self
+                .original_artist
+                .as_ref()
+                .unwrap_or(&self.artist)
+                .to_string(),
+            album: self
+                .original_album
+                .as_ref()
+                .unwrap_or(&self.album)
+                .to_string(),
+        }
+    }
+
+    /// Renames the scrobble to the canonical spelling of its original names, keeping the original
+    /// names that changed. Returns whether the scrobble changed.
+    pub fn normalize(&mut self, rules: &NameCanonicalizer) -> bool {
+        let original = self.original_names();
+        let canonical = rules.canonical(&original);
+        if canonical.title == self.title
+            && canonical.artist == self.artist
+            && canonical.album == self.album
+        {
+            return false;
+        }
+
+        let changed = |original: String, canonical: &str| Some(original).filter(|o| o != canonical);
+        self.original_title = changed(original.title, &canonical.title);
+        self.original_artist = changed(original.artist, &canonical.artist);
+        self.original_album = changed(original.album, &canonical.album);
+        self.title = canonical.title;
+        self.artist = canonical.artist;
+        self.album = canonical.album;
+
+        true
+    }
+
+    /// What makes two scrobbles the same scrobble: the time, artist and track as scrobbled
+    ///
+    /// The loved flag, local time and album are left out, since they can differ between two
+    /// fetches of one scrobble, and so are the names given by name rules.
+    pub fn identity(&self) -> (i64, &str, &str) {
+        (
+            self.timestamp_utc,
+            self.original_artist.as_deref().unwrap_or(&self.artist),
+            self.original_title.as_deref().unwrap_or(&self.title),
+        )
+    }
+
+    /// Fills in another copy of the same scrobble. The scrobble is loved if either copy is, since
+    /// `loved sync` is what unloves scrobbles, and keeps its own album and original names unless
+    /// it has none.
+    pub fn merge(&mut self, other: &SavedScrobble) {
+        self.loved |= other.loved;
+        if self.album.is_empty() {
+            self.album = other.album.to_string();
+        }
+        if self.original_album.is_none() {
+            self.original_album = other.original_album.clone();
+        }
+    }
+}
 
-        let mut s = DefaultHasher::new();
-        self.hash(&mut s);
-        s.finish()
+/// Maps each `(artist, name)` pair to the name its group is shown under, where a group is the
+/// names of one artist that are the same once cleaned up. Groups of more than one name are shown
+/// under the cleaned up name, as in the cleanup preview, and other names are left out.
+fn group_names<'a, I>(names: I, cleaner: &TitleCleaner) -> HashMap<(String, String), String>
+where
+    I: Iterator<Item = (&'a String, &'a String)>,
+{
+    let mut groups: HashMap<(String, String), HashSet<String>> = HashMap::new();
+    for (artist, name) in names {
+        groups
+            .entry((artist.to_string(), cleaner.clean(name)))
+            .or_default()
+            .insert(name.to_string());
     }
+
+    let mut names = HashMap::new();
+    for ((artist, cleaned), variants) in groups {
+        if variants.len() < 2 {
+            continue;
+        }
+        for name in variants {
+            names.insert((artist.to_string(), name), cleaned.to_string());
+        }
+    }
+
+    names
 }
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::Result;
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::models::weekly_charts::{ArtistChartEntry, ChartEntry};
+
+/// The three kinds of weekly charts Last.fm keeps
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    Artist,
+    Album,
+    Track,
+}
+
+impl ChartKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChartKind::Artist => "artist",
+            ChartKind::Album => "album",
+            ChartKind::Track => "track",
+        }
+    }
+}
+
+/// An artist, album or track in a weekly chart
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WeeklyChartEntry {
+    pub rank: i32,
+    pub artist: String,
+    /// The album or track name, or `None` in artist charts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub scrobbles: i32,
+}
+
+impl WeeklyChartEntry {
+    pub fn from_artist_entry(entry: &ArtistChartEntry) -> Self {
+        Self {
+            rank: entry.attr.rank(),
+            artist: entry.name.to_string(),
+            name: None,
+            scrobbles: entry.play_count(),
+        }
+    }
+
+    pub fn from_entry(entry: &ChartEntry) -> Self {
+        Self {
+            rank: entry.attr.rank(),
+            artist: entry.artist.name.to_string(),
+            name: Some(entry.name.to_string()),
+            scrobbles: entry.play_count(),
+        }
+    }
+
+    /// Entries are matched case-insensitively, the same way Last.fm groups scrobbles
+    pub fn key(&self) -> (String, String) {
+        (
+            self.artist.to_lowercase(),
+            self.name.as_deref().unwrap_or_default().to_lowercase(),
+        )
+    }
+
+    /// The artist, or the album or track name followed by the artist
+    pub fn title(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} - {}", name, self.artist),
+            None => self.artist.to_string(),
+        }
+    }
+}
+
+/// The artist, album and track charts of a single week
+///
+/// A week starts at `from` and ends just before `to`, both unix timestamps.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WeeklyChart {
+    pub from: i64,
+    pub to: i64,
+    pub artists: Vec<WeeklyChartEntry>,
+    pub albums: Vec<WeeklyChartEntry>,
+    pub tracks: Vec<WeeklyChartEntry>,
+}
+
+impl WeeklyChart {
+    pub fn entries(&self, kind: ChartKind) -> &[WeeklyChartEntry] {
+        match kind {
+            ChartKind::Artist => &self.artists,
+            ChartKind::Album => &self.albums,
+            ChartKind::Track => &self.tracks,
+        }
+    }
+
+    pub fn entries_mut(&mut self, kind: ChartKind) -> &mut Vec<WeeklyChartEntry> {
+        match kind {
+            ChartKind::Artist => &mut self.artists,
+            ChartKind::Album => &mut self.albums,
+            ChartKind::Track => &mut self.tracks,
+        }
+    }
+
+    /// The number of scrobbles during the week, according to the track chart
+    pub fn total_scrobbles(&self) -> i32 {
+        self.tracks.iter().map(|t| t.scrobbles).sum()
+    }
+}
+
+/// Weekly charts saved locally, ordered by week
+#[derive(Default)]
+pub struct SavedWeeklyCharts {
+    charts: BTreeMap<i64, WeeklyChart>,
+}
+
+impl SavedWeeklyCharts {
+    pub fn new(charts: Vec<WeeklyChart>) -> Self {
+        let mut saved_charts = Self::default();
+        charts.into_iter().for_each(|c| saved_charts.insert(c));
+        saved_charts
+    }
+
+    pub fn save_as_json(&self, file: &Path) -> Result<()> {
+        let f = fs::File::create(file)?;
+        let bw = BufWriter::new(f);
+        serde_json::to_writer_pretty(bw, &self.charts.values().collect::<Vec<_>>())?;
+
+        Ok(())
+    }
+
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let charts: Vec<WeeklyChart> = serde_json::from_reader(br)?;
+
+        Ok(SavedWeeklyCharts::new(charts))
+    }
+
+    pub fn insert(&mut self, chart: WeeklyChart) {
+        self.charts.insert(chart.from, chart);
+    }
+
+    /// Indicates if the charts of the week starting at `from` are saved
+    pub fn contains(&self, from: i64) -> bool {
+        self.charts.contains_key(&from)
+    }
+
+    /// Every saved week, from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = &WeeklyChart> {
+        self.charts.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.charts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.charts.is_empty()
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Offset, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Time zones other than the home time zone that scrobbles were listened in, e.g. while
+/// travelling
+#[derive(Default, Serialize, Deserialize)]
+pub struct TimeZoneOverrides {
+    #[serde(default)]
+    pub overrides: Vec<TimeZoneOverride>,
+}
+
+/// A range of dates spent in another time zone
+#[derive(Serialize, Deserialize)]
+pub struct TimeZoneOverride {
+    /// The first date in the time zone, inclusive
+    pub from: NaiveDate,
+    /// The last date in the time zone, inclusive
+    pub to: NaiveDate,
+    /// An IANA time zone name, e.g. `Asia/Tokyo`
+    pub timezone: String,
+}
+
+impl TimeZoneOverrides {
+    pub fn save_as_json(&self, file: &Path) -> Result<()> {
+        let f = fs::File::create(file)?;
+        let bw = BufWriter::new(f);
+        serde_json::to_writer_pretty(bw, &self)?;
+
+        Ok(())
+    }
+
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let overrides = serde_json::from_reader(br)?;
+
+        Ok(overrides)
+    }
+}
+
+/// Works out the local time of a scrobble from the home time zone and the overrides
+///
+/// Without a home time zone, the time zone of this machine is used.
+#[derive(Default)]
+pub struct LocalTimes {
+    home: Option<Tz>,
+    overrides: Vec<(NaiveDate, NaiveDate, Tz)>,
+}
+
+impl LocalTimes {
+    /// Fails if the home time zone or an override's time zone is not an IANA time zone name
+    pub fn new(home: Option<&str>, overrides: &TimeZoneOverrides) -> Result<Self> {
+        let home = home.map(parse_time_zone).transpose()?;
+        let overrides = overrides
+            .overrides
+            .iter()
+            .map(|o| Ok((o.from, o.to, parse_time_zone(&o.timezone)?)))
+            .collect::<Result<Vec<(NaiveDate, NaiveDate, Tz)>>>()?;
+
+        Ok(Self { home, overrides })
+    }
+
+    /// The local time of a scrobble
+    ///
+    /// A scrobble is in an override's time zone when its date in that time zone is in the
+    /// override's range. When overrides overlap, the first one listed wins.
+    pub fn datetime_local(&self, timestamp_utc: i64) -> DateTime<FixedOffset> {
+        let overridden = self.overrides.iter().find_map(|(from, to, tz)| {
+            let datetime = tz.timestamp(timestamp_utc, 0);
+            let date = datetime.naive_local().date();
+            if *from <= date && date <= *to {
+                Some(fixed(datetime))
+            } else {
+                None
+            }
+        });
+
+        overridden.unwrap_or_else(|| match self.home {
+            Some(tz) => fixed(tz.timestamp(timestamp_utc, 0)),
+            None => Local.timestamp(timestamp_utc, 0).into(),
+        })
+    }
+}
+
+fn parse_time_zone(name: &str) -> Result<Tz> {
+    Tz::from_str(name.trim())
+        .map_err(|e| anyhow!(e))
+        .with_context(|| format!("Invalid time zone `{}`", name))
+}
+
+fn fixed<Z: TimeZone>(datetime: DateTime<Z>) -> DateTime<FixedOffset> {
+    let offset = datetime.offset().fix();
+    datetime.with_timezone(&offset)
+}
@@ -0,0 +1,31 @@
+use serde::Deserialize;
+
+use crate::models::OneOrMany;
+
+#[derive(Debug, Deserialize)]
+pub struct TopTagsResponse {
+    #[serde(rename = "toptags")]
+    pub top_tags: TopTags,
+}
+
+/// Response to an `artist.getTopTags` request
+#[derive(Debug, Deserialize)]
+pub struct TopTags {
+    /// Missing when the artist has no tags
+    #[serde(default)]
+    tag: OneOrMany<Tag>,
+}
+
+impl TopTags {
+    /// The artist's tags, from the highest weight to the lowest
+    pub fn tags(&self) -> &[Tag] {
+        self.tag.as_slice()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    /// How strongly the tag applies to the artist, from 0 to 100
+    pub count: i32,
+}
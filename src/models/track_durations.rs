@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::models::saved_scrobbles::SavedScrobble;
+
+/// The length of a track, as reported by `track.getInfo`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TrackDuration {
+    pub artist: String,
+    pub title: String,
+    /// The length of the track in milliseconds, or `None` if Last.fm does not know it
+    pub duration_ms: Option<u32>,
+    /// When the duration was looked up, as a unix timestamp
+    pub fetched_at: i64,
+}
+
+/// A local cache of track durations, shared by every user
+#[derive(Default)]
+pub struct TrackDurations {
+    durations: HashMap<(String, String), TrackDuration>,
+}
+
+impl TrackDurations {
+    pub fn new(durations: Vec<TrackDuration>) -> Self {
+        let mut track_durations = Self::default();
+        durations
+            .into_iter()
+            .for_each(|d| track_durations.insert(d));
+        track_durations
+    }
+
+    pub fn save_as_json(&self, file: &Path) -> Result<()> {
+        let mut durations = self.durations.values().collect::<Vec<&TrackDuration>>();
+        durations.sort_by(|a, b| a.artist.cmp(&b.artist).then(a.title.cmp(&b.title)));
+
+        let f = fs::File::create(file)?;
+        let bw = BufWriter::new(f);
+        serde_json::to_writer_pretty(bw, &durations)?;
+
+        Ok(())
+    }
+
+    pub fn load_from_json(file: &Path) -> Result<Self> {
+        let f = fs::File::open(file)?;
+        let br = BufReader::new(f);
+        let durations: Vec<TrackDuration> = serde_json::from_reader(br)?;
+
+        Ok(TrackDurations::new(durations))
+    }
+
+    pub fn insert(&mut self, duration: TrackDuration) {
+        self.durations
+            .insert(Self::key(&duration.artist, &duration.title), duration);
+    }
+
+    /// Indicates if the track has been looked up, even if its duration turned out to be unknown
+    pub fn contains(&self, artist: &str, title: &str) -> bool {
+        self.durations.contains_key(&Self::key(artist, title))
+    }
+
+    /// The length of a scrobbled track in milliseconds, if it is known
+    pub fn duration_ms(&self, scrobble: &SavedScrobble) -> Option<u32> {
+        self.durations
+            .get(&Self::key(&scrobble.artist, &scrobble.title))
+            .and_then(|d| d.duration_ms)
+    }
+
+    /// The distinct tracks in `scrobbles` that have not been looked up yet, as `(artist, title)`
+    pub fn missing(&self, scrobbles: &[SavedScrobble]) -> Vec<(String, String)> {
+        Self::distinct_tracks(scrobbles, |s| !self.contains(&s.artist, &s.title))
+    }
+
+    /// The distinct tracks in `scrobbles` that were looked up, but whose duration is unknown
+    pub fn unknown(&self, scrobbles: &[SavedScrobble]) -> Vec<(String, String)> {
+        Self::distinct_tracks(scrobbles, |s| {
+            self.contains(&s.artist, &s.title) && self.duration_ms(s).is_none()
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.durations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.durations.is_empty()
+    }
+
+    fn distinct_tracks<F>(scrobbles: &[SavedScrobble], predicate: F) -> Vec<(String, String)>
+    where
+        F: Fn(&SavedScrobble) -> bool,
+    {
+        let mut tracks = scrobbles
+            .iter()
+            .filter(|s| predicate(s))
+            .map(|s| (s.artist.to_string(), s.title.to_string()))
+            .collect::<Vec<(String, String)>>();
+
+        tracks.sort_by_key(|(artist, title)| Self::key(artist, title));
+        tracks.dedup_by_key(|(artist, title)| Self::key(artist, title));
+
+        tracks
+    }
+
+    /// Tracks are matched case-insensitively, the same way Last.fm matches them
+    fn key(artist: &str, title: &str) -> (String, String) {
+        (artist.to_lowercase(), title.to_lowercase())
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct TrackInfoResponse {
+    pub track: TrackInfo,
+}
+
+/// Response to a `track.getInfo` request
+///
+/// Only the fields used by this application are deserialized.
+#[derive(Debug, Deserialize)]
+pub struct TrackInfo {
+    pub name: String,
+    /// The length of the track in milliseconds, which is `"0"` or missing when unknown
+    duration: Option<String>,
+}
+
+impl TrackInfo {
+    /// The length of the track in milliseconds, if Last.fm knows it
+    pub fn duration_ms(&self) -> Option<u32> {
+        self.duration
+            .as_ref()
+            .and_then(|duration| duration.parse().ok())
+            .filter(|duration| *duration > 0)
+    }
+}
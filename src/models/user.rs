@@ -13,6 +13,12 @@ pub struct Registered {
     pub unixtime: String,
 }
 
+impl Registered {
+    pub fn timestamp(&self) -> i64 {
+        self.unixtime.parse().unwrap_or(0)
+    }
+}
+
 /// Response to a `user.getInfo` request
 ///
 /// Returns information about a user's profile. Certain fields, such as `bootstrap` and `images`,
@@ -0,0 +1,128 @@
+use serde::Deserialize;
+
+use crate::models::OneOrMany;
+
+#[derive(Debug, Deserialize)]
+pub struct WeeklyChartListResponse {
+    #[serde(rename = "weeklychartlist")]
+    pub weekly_chart_list: WeeklyChartList,
+}
+
+/// Response to a `user.getWeeklyChartList` request
+#[derive(Debug, Deserialize)]
+pub struct WeeklyChartList {
+    #[serde(default)]
+    chart: OneOrMany<ChartWeek>,
+}
+
+impl WeeklyChartList {
+    /// Every week Last.fm has charts for, from oldest to newest
+    pub fn weeks(&self) -> &[ChartWeek] {
+        self.chart.as_slice()
+    }
+}
+
+/// The range of a weekly chart, as unix timestamps
+#[derive(Debug, Deserialize)]
+pub struct ChartWeek {
+    from: String,
+    to: String,
+}
+
+impl ChartWeek {
+    pub fn from(&self) -> i64 {
+        self.from.parse().unwrap_or(0)
+    }
+
+    pub fn to(&self) -> i64 {
+        self.to.parse().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WeeklyArtistChartResponse {
+    #[serde(rename = "weeklyartistchart")]
+    pub chart: WeeklyArtistChart,
+}
+
+/// Response to a `user.getWeeklyArtistChart` request
+#[derive(Debug, Deserialize)]
+pub struct WeeklyArtistChart {
+    #[serde(default)]
+    pub artist: OneOrMany<ArtistChartEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArtistChartEntry {
+    pub name: String,
+    #[serde(rename = "@attr")]
+    pub attr: RankAttr,
+    #[serde(rename = "playcount")]
+    play_count: String,
+}
+
+impl ArtistChartEntry {
+    pub fn play_count(&self) -> i32 {
+        self.play_count.parse().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WeeklyAlbumChartResponse {
+    #[serde(rename = "weeklyalbumchart")]
+    pub chart: WeeklyAlbumChart,
+}
+
+/// Response to a `user.getWeeklyAlbumChart` request
+#[derive(Debug, Deserialize)]
+pub struct WeeklyAlbumChart {
+    #[serde(default)]
+    pub album: OneOrMany<ChartEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WeeklyTrackChartResponse {
+    #[serde(rename = "weeklytrackchart")]
+    pub chart: WeeklyTrackChart,
+}
+
+/// Response to a `user.getWeeklyTrackChart` request
+#[derive(Debug, Deserialize)]
+pub struct WeeklyTrackChart {
+    #[serde(default)]
+    pub track: OneOrMany<ChartEntry>,
+}
+
+/// An album or track in a weekly chart
+#[derive(Debug, Deserialize)]
+pub struct ChartEntry {
+    pub name: String,
+    pub artist: ChartEntryArtist,
+    #[serde(rename = "@attr")]
+    pub attr: RankAttr,
+    #[serde(rename = "playcount")]
+    play_count: String,
+}
+
+impl ChartEntry {
+    pub fn play_count(&self) -> i32 {
+        self.play_count.parse().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChartEntryArtist {
+    #[serde(rename = "#text")]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RankAttr {
+    rank: String,
+}
+
+impl RankAttr {
+    pub fn rank(&self) -> i32 {
+        self.rank.parse().unwrap_or(0)
+    }
+}
@@ -0,0 +1,409 @@
+//! Renders statistics and reports in the formats accepted by the `--output` flag
+//!
+//! Every report is serializable, so the `json` and `ndjson` formats are simply the report's serde
+//! representation.
+//! The `table`, `markdown`, and `csv` formats are built from the [Section](struct.Section.html)s
+//! that each report describes itself with.
+
+use anyhow::Result;
+use clap::ArgEnum;
+use num_format::ToFormattedString;
+use serde::Serialize;
+
+use crate::utils;
+
+/// The formats a report can be written to stdout in
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Human readable, aligned columns
+    Table,
+    /// Pretty-printed JSON that mirrors the report's documented schema
+    Json,
+    /// Comma separated values, with the section name as the first column of each record
+    Csv,
+    /// GitHub flavored Markdown tables
+    Markdown,
+    /// Newline delimited JSON. Reports that serialize as a list are written one item per line,
+    /// any other report as a single line.
+    Ndjson,
+}
+
+/// A report that can be written in any [OutputFormat](enum.OutputFormat.html)
+pub trait Report: Serialize {
+    /// The tabular layout of the report, used for every format except JSON
+    fn sections(&self) -> Vec<Section>;
+
+    /// The number of scrobbles that the exclusion rules left out of the report
+    fn excluded_scrobbles(&self) -> i32 {
+        0
+    }
+}
+
+/// A single value within a section
+///
+/// Numbers are formatted according to the system locale in human readable formats, and written
+/// as-is in CSV.
+pub enum Cell {
+    Integer(i64),
+    Decimal(f64),
+    Text(String),
+}
+
+impl Cell {
+    fn display(&self) -> String {
+        match self {
+            Cell::Integer(value) => value.to_formatted_string(&utils::get_locale()),
+            Cell::Decimal(value) => format!("{:.2}", value),
+            Cell::Text(value) => value.to_string(),
+        }
+    }
+
+    fn raw(&self) -> String {
+        match self {
+            Cell::Integer(value) => value.to_string(),
+            Cell::Decimal(value) => value.to_string(),
+            Cell::Text(value) => value.to_string(),
+        }
+    }
+}
+
+impl From<i32> for Cell {
+    fn from(value: i32) -> Self {
+        Cell::Integer(value as i64)
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(value: i64) -> Self {
+        Cell::Integer(value)
+    }
+}
+
+impl From<usize> for Cell {
+    fn from(value: usize) -> Self {
+        Cell::Integer(value as i64)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(value: f64) -> Self {
+        Cell::Decimal(value)
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        Cell::Text(value)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Cell::Text(value.to_string())
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => value.into(),
+            None => Cell::Text(String::new()),
+        }
+    }
+}
+
+enum SectionKind {
+    KeyValue,
+    Table,
+}
+
+/// A titled block of rows within a report
+pub struct Section {
+    title: String,
+    kind: SectionKind,
+    headers: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Section {
+    /// A section of labelled values, such as a list of summary statistics
+    pub fn key_values(title: &str, pairs: Vec<(&str, Cell)>) -> Self {
+        Self {
+            title: title.to_string(),
+            kind: SectionKind::KeyValue,
+            headers: vec!["Metric".to_string(), "Value".to_string()],
+            rows: pairs
+                .into_iter()
+                .map(|(label, value)| vec![Cell::from(label), value])
+                .collect(),
+        }
+    }
+
+    /// A section with a header row and any number of data rows
+    pub fn table(title: &str, headers: &[&str], rows: Vec<Vec<Cell>>) -> Self {
+        Self {
+            title: title.to_string(),
+            kind: SectionKind::Table,
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows,
+        }
+    }
+}
+
+/// Writes a report to stdout in the requested format
+pub fn print_report(report: &impl Report, format: OutputFormat) -> Result<()> {
+    println!("{}", render(report, format)?);
+
+    Ok(())
+}
+
+/// Renders a report to a string in the requested format
+pub fn render(report: &impl Report, format: OutputFormat) -> Result<String> {
+    let rendered = match format {
+        OutputFormat::Table => render_table(&sections(report)),
+        OutputFormat::Json => serde_json::to_string_pretty(report)?,
+        OutputFormat::Csv => render_csv(&sections(report))?,
+        OutputFormat::Markdown => render_markdown(&sections(report)),
+        OutputFormat::Ndjson => render_ndjson(report)?,
+    };
+
+    Ok(rendered)
+}
+
+/// The report's sections, followed by the number of excluded scrobbles when there are any
+fn sections(report: &impl Report) -> Vec<Section> {
+    let mut sections = report.sections();
+
+    let excluded = report.excluded_scrobbles();
+    if excluded > 0 {
+        sections.push(Section::key_values(
+            "Exclusions",
+            vec![("Excluded Scrobbles", excluded.into())],
+        ));
+    }
+
+    sections
+}
+
+fn render_ndjson(report: &impl Report) -> Result<String> {
+    let lines = match serde_json::to_value(report)? {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<serde_json::Result<Vec<String>>>()?,
+        value => vec![serde_json::to_string(&value)?],
+    };
+
+    Ok(lines.join("\n"))
+}
+
+fn display_rows(section: &Section) -> Vec<Vec<String>> {
+    section
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.display()).collect())
+        .collect()
+}
+
+fn render_table(sections: &[Section]) -> String {
+    let mut lines = Vec::new();
+
+    for section in sections {
+        let rows = display_rows(section);
+        lines.push(format!("{}:\n", section.title.to_uppercase()));
+
+        match section.kind {
+            SectionKind::KeyValue => {
+                let width = rows
+                    .iter()
+                    .map(|row| row[0].chars().count() + 1)
+                    .max()
+                    .unwrap_or(0);
+
+                for row in &rows {
+                    lines.push(format!("{:<width$} {}", format!("{}:", row[0]), row[1]));
+                }
+            }
+            SectionKind::Table => {
+                if rows.is_empty() {
+                    lines.push("(none)".to_string());
+                } else {
+                    let widths = column_widths(&section.headers, &rows);
+                    lines.push(pad_row(&section.headers, &widths));
+                    lines.push(
+                        widths
+                            .iter()
+                            .map(|w| "-".repeat(*w))
+                            .collect::<Vec<String>>()
+                            .join("  "),
+                    );
+                    for row in &rows {
+                        lines.push(pad_row(row, &widths));
+                    }
+                }
+            }
+        }
+
+        lines.push(String::new());
+    }
+
+    lines.join("\n").trim_end().to_string()
+}
+
+fn column_widths(headers: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            if i < widths.len() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+    }
+
+    widths
+}
+
+fn pad_row(row: &[String], widths: &[usize]) -> String {
+    row.iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+        .collect::<Vec<String>>()
+        .join("  ")
+        .trim_end()
+        .to_string()
+}
+
+fn render_csv(sections: &[Section]) -> Result<String> {
+    let mut wtr = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_writer(Vec::new());
+
+    for section in sections {
+        let mut header = vec!["section".to_string()];
+        header.extend(section.headers.iter().map(|h| to_snake_case(h)));
+        wtr.write_record(&header)?;
+
+        let name = to_snake_case(&section.title);
+        for row in &section.rows {
+            let mut record = vec![name.clone()];
+            record.extend(row.iter().map(|cell| cell.raw()));
+
+            // Labels become machine friendly keys, e.g. `average_tracks_per_day`
+            if let SectionKind::KeyValue = section.kind {
+                record[1] = to_snake_case(&record[1]);
+            }
+
+            wtr.write_record(&record)?;
+        }
+    }
+
+    let csv = String::from_utf8(wtr.into_inner()?)?;
+
+    Ok(csv.trim_end().to_string())
+}
+
+fn render_markdown(sections: &[Section]) -> String {
+    let mut lines = Vec::new();
+
+    for section in sections {
+        let rows = display_rows(section);
+        lines.push(format!("### {}\n", section.title));
+
+        if rows.is_empty() {
+            lines.push("_None_".to_string());
+        } else {
+            lines.push(markdown_row(&section.headers));
+            lines.push(markdown_row(
+                &section
+                    .headers
+                    .iter()
+                    .map(|_| "---".to_string())
+                    .collect::<Vec<String>>(),
+            ));
+            for row in &rows {
+                lines.push(markdown_row(row));
+            }
+        }
+
+        lines.push(String::new());
+    }
+
+    lines.join("\n").trim_end().to_string()
+}
+
+fn markdown_row(row: &[String]) -> String {
+    let cells = row
+        .iter()
+        .map(|cell| cell.replace('|', "\\|"))
+        .collect::<Vec<String>>();
+
+    format!("| {} |", cells.join(" | "))
+}
+
+fn to_snake_case(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_lowercase())
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::saved_scrobbles::{SavedScrobble, SavedScrobbles};
+    use crate::reports::query_results::{GroupBy, GroupCounts, ScrobbleCount};
+
+    #[test]
+    fn test_stats_json_schema() {
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", 1_600_000_000),
+            SavedScrobble::played(
+                "Paranoid Android",
+                "Radiohead",
+                "OK Computer",
+                1_600_086_400,
+            ),
+            SavedScrobble::played("Idioteque", "Radiohead", "Kid A", 1_600_172_800),
+        ]);
+
+        let stats = saved_scrobbles.generate_stats();
+        let json = render(&stats, OutputFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["total_scrobbles"], 3);
+        assert!(value["average_tracks_per_day"].is_number());
+        assert_eq!(value["best_month"]["scrobbles"], 3);
+    }
+
+    #[test]
+    fn test_render_lists() {
+        let jan_1_2021 = 1_609_502_400;
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 120),
+            SavedScrobble::played("Let Down", "Radiohead", "OK Computer", jan_1_2021 + 60),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+        ]);
+        let mut artists =
+            GroupCounts::new(saved_scrobbles.as_slice(), GroupBy::Artist, false, None);
+
+        let ndjson = render(&artists, OutputFormat::Ndjson).unwrap();
+        assert_eq!(ndjson.lines().count(), 2);
+        assert!(ndjson.starts_with("{\"name\":\"Radiohead\",\"scrobbles\":2}\n"));
+
+        // Excluded scrobbles are counted in every format, without changing the shape of lists
+        artists.excluded_scrobbles = 3;
+        let csv = render(&artists, OutputFormat::Csv).unwrap();
+        assert!(csv.ends_with("exclusions,excluded_scrobbles,3"));
+        let ndjson = render(&artists, OutputFormat::Ndjson).unwrap();
+        assert_eq!(ndjson.lines().count(), 2);
+        let count = ScrobbleCount {
+            scrobbles: 3,
+            excluded_scrobbles: 3,
+        };
+        let json = render(&count, OutputFormat::Json).unwrap();
+        assert!(json.contains("\"excluded_scrobbles\": 3"));
+    }
+}
@@ -0,0 +1,383 @@
+//! Restricts a listening history to a date range, an artist, album or track, or loved tracks
+//!
+//! A [ScrobbleFilter](struct.ScrobbleFilter.html) is matched against saved scrobbles directly, and
+//! the parts of it that Sqlite can evaluate are pushed down into the database query.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use regex::{Regex, RegexBuilder};
+
+use crate::models::saved_scrobbles::{SavedScrobble, SavedScrobbles};
+
+/// A day, month or year written the way a person would, covering every day from `start` to `end`
+///
+/// Accepts `2022`, `2022-03`, `March 2022`, `2022-03-21`, `today`, `yesterday`,
+/// `this week|month|year`, `last week|month|year` and `N days|weeks|months|years ago`. Weeks
+/// start on Monday.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HumanDate {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl HumanDate {
+    /// Parses a date, resolving relative dates such as `yesterday` against `today`
+    pub fn parse_relative_to(s: &str, today: NaiveDate) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        let words = s.split_whitespace().collect::<Vec<&str>>();
+
+        let range = match words.as_slice() {
+            ["today"] => day(today),
+            ["yesterday"] => day(today - Duration::days(1)),
+            ["this", unit] => period(parse_unit(unit)?, today, 0)?,
+            ["last", unit] => period(parse_unit(unit)?, today, 1)?,
+            [count, unit, "ago"] => {
+                let count = count.parse::<i32>()?;
+                match parse_unit(unit)? {
+                    Unit::Day => day(today - Duration::days(count as i64)),
+                    Unit::Week => day(today - Duration::weeks(count as i64)),
+                    Unit::Month => day(add_months(today, -count)?),
+                    Unit::Year => day(add_months(today, -12 * count)?),
+                }
+            }
+            [year] if year.len() == 4 => {
+                let year = year.parse::<i32>()?;
+                Self {
+                    start: ymd(year, 1, 1)?,
+                    end: ymd(year, 12, 31)?,
+                }
+            }
+            [date] if date.len() == 7 => month(NaiveDate::parse_from_str(
+                &format!("{}-01", date),
+                "%Y-%m-%d",
+            )?),
+            [date] => day(NaiveDate::parse_from_str(date, "%Y-%m-%d")?),
+            [name, year] => month(
+                NaiveDate::parse_from_str(&format!("1 {} {}", name, year), "%d %B %Y").or_else(
+                    |_| NaiveDate::parse_from_str(&format!("1 {} {}", name, year), "%d %b %Y"),
+                )?,
+            ),
+            _ => return Err(anyhow!("`{}` is not a date", s)),
+        };
+
+        Ok(range)
+    }
+}
+
+impl FromStr for HumanDate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HumanDate::parse_relative_to(s, Local::today().naive_local())
+    }
+}
+
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+fn parse_unit(unit: &str) -> Result<Unit> {
+    match unit.trim_end_matches('s') {
+        "day" => Ok(Unit::Day),
+        "week" => Ok(Unit::Week),
+        "month" => Ok(Unit::Month),
+        "year" => Ok(Unit::Year),
+        _ => Err(anyhow!("`{}` is not a day, week, month or year", unit)),
+    }
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> Result<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, day).ok_or_else(|| anyhow!("Invalid date"))
+}
+
+/// The same day `months` months later, or the last day of that month if it is shorter
+fn add_months(date: NaiveDate, months: i32) -> Result<NaiveDate> {
+    let index = date.year() * 12 + date.month0() as i32 + months;
+    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+
+    (0..4)
+        .find_map(|i| NaiveDate::from_ymd_opt(year, month, date.day() - i))
+        .ok_or_else(|| anyhow!("Invalid date"))
+}
+
+fn day(date: NaiveDate) -> HumanDate {
+    HumanDate {
+        start: date,
+        end: date,
+    }
+}
+
+fn month(first: NaiveDate) -> HumanDate {
+    HumanDate {
+        start: first,
+        end: add_months(first, 1).unwrap() - Duration::days(1),
+    }
+}
+
+/// The calendar week, month or year containing `today`, or `back` periods before it
+fn period(unit: Unit, today: NaiveDate, back: i32) -> Result<HumanDate> {
+    let range = match unit {
+        Unit::Day => day(today - Duration::days(back as i64)),
+        Unit::Week => {
+            let monday = today
+                - Duration::days(today.weekday().num_days_from_monday() as i64)
+                - Duration::weeks(back as i64);
+            HumanDate {
+                start: monday,
+                end: monday + Duration::days(6),
+            }
+        }
+        Unit::Month => month(add_months(today.with_day(1).unwrap(), -back)?),
+        Unit::Year => HumanDate {
+            start: ymd(today.year() - back, 1, 1)?,
+            end: ymd(today.year() - back, 12, 31)?,
+        },
+    };
+
+    Ok(range)
+}
+
+/// Matches an artist, album or track name, ignoring case
+#[derive(Clone, Debug)]
+pub enum TextFilter {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+impl TextFilter {
+    pub fn exact(text: &str) -> Self {
+        TextFilter::Exact(text.to_lowercase())
+    }
+
+    pub fn contains(text: &str) -> Self {
+        TextFilter::Contains(text.to_lowercase())
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let regex = RegexBuilder::new(pattern).case_insensitive(true).build()?;
+        Ok(TextFilter::Regex(regex))
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            TextFilter::Exact(exact) => text.to_lowercase() == *exact,
+            TextFilter::Contains(part) => text.to_lowercase().contains(part.as_str()),
+            TextFilter::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// The condition on `column` for Sqlite, which has no regular expressions and whose lower()
+    /// only folds ASCII. Other filters can only be evaluated in Rust.
+    fn sql_condition(&self, column: &str) -> Option<(String, SqlValue)> {
+        match self {
+            TextFilter::Exact(text) if text.is_ascii() => Some((
+                format!("lower({}) = ?", column),
+                SqlValue::Text(text.to_string()),
+            )),
+            TextFilter::Contains(text) if text.is_ascii() => Some((
+                format!("instr(lower({}), ?) > 0", column),
+                SqlValue::Text(text.to_string()),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// A value bound to a parameter of a Sqlite query
+#[derive(Clone, Debug, PartialEq)]
+pub enum SqlValue {
+    Integer(i64),
+    Text(String),
+}
+
+/// Conditions every scrobble must meet. Dates are local and inclusive.
+#[derive(Clone, Debug, Default)]
+pub struct ScrobbleFilter {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub artist: Option<TextFilter>,
+    pub album: Option<TextFilter>,
+    pub track: Option<TextFilter>,
+    /// Matches the artist, album or track
+    pub search: Option<TextFilter>,
+    pub loved_only: bool,
+}
+
+impl ScrobbleFilter {
+    /// Whether the filter lets every scrobble through
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none()
+            && self.to.is_none()
+            && self.artist.is_none()
+            && self.album.is_none()
+            && self.track.is_none()
+            && self.search.is_none()
+            && !self.loved_only
+    }
+
+    pub fn matches(&self, scrobble: &SavedScrobble) -> bool {
+        let date = scrobble.date();
+        let text = |filter: &Option<TextFilter>, text: &str| {
+            filter.as_ref().map_or(true, |f| f.matches(text))
+        };
+
+        self.from.map_or(true, |from| date >= from)
+            && self.to.map_or(true, |to| date <= to)
+            && text(&self.artist, &scrobble.artist)
+            && text(&self.album, &scrobble.album)
+            && text(&self.track, &scrobble.title)
+            && self.search.as_ref().map_or(true, |f| {
+                f.matches(&scrobble.artist)
+                    || f.matches(&scrobble.album)
+                    || f.matches(&scrobble.title)
+            })
+            && (!self.loved_only || scrobble.loved)
+    }
+
+    /// Keeps only the scrobbles that match the filter
+    pub fn apply(&self, mut scrobbles: SavedScrobbles) -> SavedScrobbles {
+        if !self.is_empty() {
+            scrobbles.retain(|s| self.matches(s));
+        }
+
+        scrobbles
+    }
+
+    /// Conditions on the `scrobbles` table, joined with `AND`, and the values of their `?`
+    /// parameters
+    ///
+    /// Sqlite has no regular expressions, so regex filters are left out and the results must
+    /// still be narrowed down with [apply](#method.apply).
+    pub fn sql_conditions(&self) -> (Vec<String>, Vec<SqlValue>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        if let Some(from) = self.from {
+            conditions.push("timestamp_utc + utc_offset >= ?".to_string());
+            values.push(SqlValue::Integer(local_seconds(from)));
+        }
+        if let Some(to) = self.to {
+            conditions.push("timestamp_utc + utc_offset < ?".to_string());
+            values.push(SqlValue::Integer(local_seconds(to + Duration::days(1))));
+        }
+
+        for (column, filter) in [
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("track", &self.track),
+        ] {
+            if let Some((condition, value)) = filter.as_ref().and_then(|f| f.sql_condition(column))
+            {
+                conditions.push(condition);
+                values.push(value);
+            }
+        }
+
+        if let Some(search) = &self.search {
+            let any = ["artist", "album", "track"]
+                .iter()
+                .filter_map(|column| search.sql_condition(column))
+                .collect::<Vec<(String, SqlValue)>>();
+
+            if !any.is_empty() {
+                let (any, any_values): (Vec<String>, Vec<SqlValue>) = any.into_iter().unzip();
+                conditions.push(format!("({})", any.join(" OR ")));
+                values.extend(any_values);
+            }
+        }
+
+        if self.loved_only {
+            conditions.push("loved = 1".to_string());
+        }
+
+        (conditions, values)
+    }
+}
+
+/// The first moment of a date as seconds since the unix epoch, to compare with a scrobble's local
+/// time, `timestamp_utc + utc_offset`
+fn local_seconds(date: NaiveDate) -> i64 {
+    date.and_hms(0, 0, 0).timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrobble_filter() {
+        let today = NaiveDate::from_ymd(2022, 3, 16);
+        let parse = |s: &str| HumanDate::parse_relative_to(s, today).unwrap();
+
+        assert_eq!(parse("2022").start, NaiveDate::from_ymd(2022, 1, 1));
+        assert_eq!(parse("2022").end, NaiveDate::from_ymd(2022, 12, 31));
+        assert_eq!(parse("2022-02").end, NaiveDate::from_ymd(2022, 2, 28));
+        assert_eq!(parse("March 2020").end, NaiveDate::from_ymd(2020, 3, 31));
+        assert_eq!(parse("yesterday").start, NaiveDate::from_ymd(2022, 3, 15));
+        assert_eq!(parse("last month").start, NaiveDate::from_ymd(2022, 2, 1));
+        assert_eq!(parse("last week").start, NaiveDate::from_ymd(2022, 3, 7));
+        assert_eq!(parse("1 month ago").start, NaiveDate::from_ymd(2022, 2, 16));
+        assert!(HumanDate::parse_relative_to("someday", today).is_err());
+
+        let day = 86_400;
+        let jan_1_2021 = 1_609_502_400;
+        let mut loved =
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021 + 400 * day);
+        loved.loved = true;
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + day),
+            loved,
+        ]);
+
+        let filter = ScrobbleFilter {
+            from: Some(parse("2021").start),
+            to: Some(parse("2021").end),
+            artist: Some(TextFilter::exact("RADIOHEAD")),
+            ..ScrobbleFilter::default()
+        };
+        let filtered = filter.apply(SavedScrobbles::new(saved_scrobbles.get_saved_scrobbles()));
+        assert_eq!(filtered.total_saved_scrobbles(), 1);
+
+        let (conditions, values) = filter.sql_conditions();
+        assert_eq!(conditions.len(), 3);
+        assert_eq!(values[2], SqlValue::Text("radiohead".to_string()));
+
+        let filter = ScrobbleFilter {
+            album: Some(TextFilter::regex("^ok").unwrap()),
+            loved_only: true,
+            ..ScrobbleFilter::default()
+        };
+        let filtered = filter.apply(saved_scrobbles);
+        assert_eq!(filtered.total_saved_scrobbles(), 1);
+        assert_eq!(filter.sql_conditions().0, vec!["loved = 1"]);
+    }
+
+    #[test]
+    fn test_search_filter() {
+        let jan_1_2021 = 1_609_502_400;
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 120),
+            SavedScrobble::played("Let Down", "Radiohead", "OK Computer", jan_1_2021 + 60),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+        ]);
+
+        let filter = ScrobbleFilter {
+            search: Some(TextFilter::contains("ok comp")),
+            ..ScrobbleFilter::default()
+        };
+        let (conditions, _) = filter.sql_conditions();
+        assert_eq!(
+            conditions,
+            vec!["(instr(lower(artist), ?) > 0 OR instr(lower(album), ?) > 0 OR instr(lower(track), ?) > 0)"]
+        );
+        let matching = filter.apply(saved_scrobbles);
+        assert_eq!(matching.total_saved_scrobbles(), 2);
+    }
+}
@@ -0,0 +1,209 @@
+//! Scrobbles that cannot be real plays, such as double scrobbles, bursts submitted after an
+//! offline sync, and timestamps in the future or before Last.fm existed
+
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Cell, Report, Section};
+
+/// 2002-01-01, the year Audioscrobbler, which became Last.fm, started recording scrobbles
+pub const FIRST_POSSIBLE_TIMESTAMP: i64 = 1_009_843_200;
+
+/// What makes a scrobble suspicious
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnomalyKind {
+    /// The same track as the previous scrobble, moments later
+    NearDuplicate,
+    /// Too soon after the previous scrobble for a track to have been played, e.g. a burst of
+    /// scrobbles with identical timestamps
+    ImpossibleDensity,
+    /// Scrobbled after the time of the check
+    FutureDated,
+    /// Scrobbled before 2002, usually at a 1970 timestamp from a device whose clock was not set
+    TooEarly,
+}
+
+impl AnomalyKind {
+    pub const ALL: [AnomalyKind; 4] = [
+        AnomalyKind::NearDuplicate,
+        AnomalyKind::ImpossibleDensity,
+        AnomalyKind::FutureDated,
+        AnomalyKind::TooEarly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::NearDuplicate => "Near Duplicate",
+            AnomalyKind::ImpossibleDensity => "Impossible Density",
+            AnomalyKind::FutureDated => "Future Dated",
+            AnomalyKind::TooEarly => "Too Early",
+        }
+    }
+}
+
+/// How close together scrobbles can be before they are suspicious
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Thresholds {
+    /// A repeat of the previous track within this many seconds is a near duplicate
+    pub duplicate_seconds: i64,
+    /// Last.fm only scrobbles tracks longer than 30 seconds, so scrobbles closer together than
+    /// this cannot both have been played
+    pub min_gap_seconds: i64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            duplicate_seconds: 60,
+            min_gap_seconds: 30,
+        }
+    }
+}
+
+/// A suspicious scrobble
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub detail: String,
+    #[serde(flatten)]
+    pub scrobble: SavedScrobble,
+    /// The position of the scrobble in the checked history
+    #[serde(skip)]
+    pub index: usize,
+}
+
+/// The suspicious scrobbles in a listening history, newest first
+#[derive(Serialize)]
+pub struct Anomalies {
+    pub total_scrobbles: i32,
+    pub thresholds: Thresholds,
+    pub anomalies: Vec<Anomaly>,
+    /// The number of anomalies listed in the table
+    #[serde(skip)]
+    pub limit: usize,
+}
+
+impl Anomalies {
+    /// Checks a history against the current unix timestamp `now`, keeping the anomalies of the
+    /// given kinds
+    ///
+    /// Each scrobble is flagged at most once. Near duplicates and density are only checked
+    /// between scrobbles with possible timestamps.
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        thresholds: Thresholds,
+        now: i64,
+        kinds: &[AnomalyKind],
+        limit: usize,
+    ) -> Self {
+        // Oldest first. Scrobbles with the same timestamp are ordered by artist and track, since
+        // the saved order no longer says which was scrobbled first once histories are merged.
+        let mut order = (0..scrobbles.len()).collect::<Vec<usize>>();
+        order.sort_by(|&a, &b| scrobbles[a].identity().cmp(&scrobbles[b].identity()));
+
+        let mut anomalies = Vec::new();
+        let mut previous: Option<&SavedScrobble> = None;
+
+        for index in order {
+            let scrobble = &scrobbles[index];
+            let timestamp = scrobble.timestamp_utc;
+            let flagged = if timestamp < FIRST_POSSIBLE_TIMESTAMP {
+                Some((
+                    AnomalyKind::TooEarly,
+                    format!("Scrobbled on {}", scrobble.date()),
+                ))
+            } else if timestamp > now {
+                Some((
+                    AnomalyKind::FutureDated,
+                    format!("Scrobbled on {}", scrobble.date()),
+                ))
+            } else {
+                let gap = previous.map(|p| timestamp - p.timestamp_utc);
+                let repeated = previous.map_or(false, |p| {
+                    p.title.to_lowercase() == scrobble.title.to_lowercase()
+                        && p.artist.to_lowercase() == scrobble.artist.to_lowercase()
+                });
+                previous = Some(scrobble);
+
+                match gap {
+                    Some(gap) if repeated && gap <= thresholds.duplicate_seconds => Some((
+                        AnomalyKind::NearDuplicate,
+                        format!("Repeats the previous scrobble {}s later", gap),
+                    )),
+                    Some(gap) if gap < thresholds.min_gap_seconds => Some((
+                        AnomalyKind::ImpossibleDensity,
+                        format!("{}s after the previous scrobble", gap),
+                    )),
+                    _ => None,
+                }
+            };
+
+            if let Some((kind, detail)) = flagged.filter(|(kind, _)| kinds.contains(kind)) {
+                anomalies.push(Anomaly {
+                    kind,
+                    detail,
+                    scrobble: scrobble.clone(),
+                    index,
+                });
+            }
+        }
+        anomalies.reverse();
+
+        Self {
+            total_scrobbles: scrobbles.len() as i32,
+            thresholds,
+            anomalies,
+            limit,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+
+    /// The positions of the suspicious scrobbles in the checked history
+    pub fn indices(&self) -> Vec<usize> {
+        self.anomalies.iter().map(|a| a.index).collect()
+    }
+
+    fn count(&self, kind: AnomalyKind) -> usize {
+        self.anomalies.iter().filter(|a| a.kind == kind).count()
+    }
+}
+
+impl Report for Anomalies {
+    fn sections(&self) -> Vec<Section> {
+        let mut summary = vec![("Scrobbles Checked", self.total_scrobbles.into())];
+        for kind in AnomalyKind::ALL {
+            summary.push((kind.as_str(), self.count(kind).into()));
+        }
+
+        vec![
+            Section::key_values("Anomalies", summary),
+            Section::table(
+                "Suspicious Scrobbles",
+                &["Date", "Kind", "Track", "Artist", "Detail"],
+                self.anomalies
+                    .iter()
+                    .take(self.limit)
+                    .map(|a| {
+                        vec![
+                            Cell::from(
+                                a.scrobble
+                                    .datetime_local
+                                    .format("%Y-%m-%d %H:%M:%S")
+                                    .to_string(),
+                            ),
+                            a.kind.as_str().into(),
+                            a.scrobble.title.as_str().into(),
+                            a.scrobble.artist.as_str().into(),
+                            a.detail.as_str().into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
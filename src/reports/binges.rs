@@ -0,0 +1,314 @@
+//! Binges on a single track, weeks dominated by a single artist, and how those phases fade
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Report, Section};
+
+/// The length of the window a binge was played in
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Window {
+    Day,
+    /// Weeks start on Monday
+    Week,
+}
+
+impl Window {
+    fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Window::Day => date,
+            Window::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Window::Day => "Day",
+            Window::Week => "Week",
+        }
+    }
+}
+
+/// What counts as a binge, an obsession, and a phase that has decayed
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Thresholds {
+    /// Plays of one track within a day that make a binge
+    pub day_plays: i32,
+    /// Plays of one track within a week that make a binge
+    pub week_plays: i32,
+    /// The percentage of a week's scrobbles, from 0 to 100, an artist needs to dominate the week
+    pub artist_share: f64,
+    /// Weeks with fewer scrobbles are too quiet to be dominated by an artist
+    pub min_week_scrobbles: i32,
+    /// A binged track has decayed once it hasn't been played for this many weeks before the
+    /// newest scrobble
+    pub decay_weeks: i64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            day_plays: 5,
+            week_plays: 15,
+            artist_share: 50.0,
+            min_week_scrobbles: 20,
+            decay_weeks: 8,
+        }
+    }
+}
+
+/// Phases of a listening history: binged tracks, dominant artists, and whether the binged tracks
+/// are still played
+#[derive(Serialize)]
+pub struct Binges {
+    pub thresholds: Thresholds,
+    /// Newest first
+    pub track_binges: Vec<TrackBinge>,
+    /// Newest first
+    pub artist_obsessions: Vec<ArtistObsession>,
+    /// Every binged track, by newest peak week first
+    pub phases: Vec<TrackPhase>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Serialize)]
+pub struct TrackBinge {
+    pub title: String,
+    pub artist: String,
+    pub window: Window,
+    /// The day, or the Monday of the week, of the binge
+    pub start: NaiveDate,
+    pub plays: i32,
+}
+
+#[derive(Serialize)]
+pub struct ArtistObsession {
+    pub artist: String,
+    /// The Monday of the week
+    pub week: NaiveDate,
+    pub scrobbles: i32,
+    pub week_scrobbles: i32,
+    /// The percentage of the week's scrobbles, from 0 to 100
+    pub share: f64,
+}
+
+#[derive(Serialize)]
+pub struct TrackPhase {
+    pub title: String,
+    pub artist: String,
+    pub total_plays: i32,
+    /// The Monday of the week with the most plays
+    pub peak_week: NaiveDate,
+    pub peak_plays: i32,
+    pub last_played: NaiveDate,
+    /// Whether the track is no longer played
+    pub decayed: bool,
+}
+
+/// Plays of a single track, keyed by the start of each window
+#[derive(Default)]
+struct TrackPlays {
+    days: HashMap<NaiveDate, i32>,
+    weeks: HashMap<NaiveDate, i32>,
+    last_played: Option<NaiveDate>,
+}
+
+impl Binges {
+    pub fn new(scrobbles: &[SavedScrobble], thresholds: Thresholds) -> Self {
+        let mut tracks: HashMap<(&str, &str), TrackPlays> = HashMap::new();
+        let mut weeks: HashMap<NaiveDate, HashMap<&str, i32>> = HashMap::new();
+
+        for scrobble in scrobbles {
+            let date = scrobble.date();
+            let week = Window::Week.start(date);
+
+            let plays = tracks
+                .entry((scrobble.artist.as_str(), scrobble.title.as_str()))
+                .or_default();
+            *plays.days.entry(date).or_insert(0) += 1;
+            *plays.weeks.entry(week).or_insert(0) += 1;
+            plays.last_played = plays.last_played.max(Some(date));
+
+            *weeks
+                .entry(week)
+                .or_default()
+                .entry(scrobble.artist.as_str())
+                .or_insert(0) += 1;
+        }
+
+        let mut track_binges = Vec::new();
+        let mut phases = Vec::new();
+        let decayed_before = scrobbles
+            .first()
+            .map(|newest| newest.date() - Duration::weeks(thresholds.decay_weeks));
+
+        for ((artist, title), plays) in &tracks {
+            let binges = [
+                (Window::Day, &plays.days, thresholds.day_plays),
+                (Window::Week, &plays.weeks, thresholds.week_plays),
+            ]
+            .iter()
+            .flat_map(|(window, counts, min_plays)| {
+                counts
+                    .iter()
+                    .filter(move |(_, count)| **count >= *min_plays)
+                    .map(move |(start, count)| TrackBinge {
+                        title: title.to_string(),
+                        artist: artist.to_string(),
+                        window: *window,
+                        start: *start,
+                        plays: *count,
+                    })
+            })
+            .collect::<Vec<TrackBinge>>();
+
+            if binges.is_empty() {
+                continue;
+            }
+            track_binges.extend(binges);
+
+            // The earliest week wins a tie, since that's when the phase peaked first
+            let (peak_week, peak_plays) = plays
+                .weeks
+                .iter()
+                .max_by_key(|(week, count)| (**count, Reverse(**week)))
+                .map(|(week, count)| (*week, *count))
+                .unwrap();
+            let last_played = plays.last_played.unwrap();
+
+            phases.push(TrackPhase {
+                title: title.to_string(),
+                artist: artist.to_string(),
+                total_plays: plays.weeks.values().sum(),
+                peak_week,
+                peak_plays,
+                last_played,
+                decayed: decayed_before.map_or(false, |before| last_played < before),
+            });
+        }
+
+        track_binges.sort_by(|a, b| {
+            b.start
+                .cmp(&a.start)
+                .then(b.plays.cmp(&a.plays))
+                .then(a.artist.cmp(&b.artist))
+                .then(a.title.cmp(&b.title))
+        });
+        phases.sort_by(|a, b| {
+            b.peak_week
+                .cmp(&a.peak_week)
+                .then(b.peak_plays.cmp(&a.peak_plays))
+                .then(a.artist.cmp(&b.artist))
+                .then(a.title.cmp(&b.title))
+        });
+
+        let mut artist_obsessions = weeks
+            .iter()
+            .flat_map(|(week, artists)| {
+                let week_scrobbles = artists.values().sum::<i32>();
+                artists
+                    .iter()
+                    .map(move |(artist, scrobbles)| ArtistObsession {
+                        artist: artist.to_string(),
+                        week: *week,
+                        scrobbles: *scrobbles,
+                        week_scrobbles,
+                        share: *scrobbles as f64 / week_scrobbles as f64 * 100.0,
+                    })
+                    .filter(|o| {
+                        o.week_scrobbles >= thresholds.min_week_scrobbles
+                            && o.share >= thresholds.artist_share
+                    })
+            })
+            .collect::<Vec<ArtistObsession>>();
+        artist_obsessions.sort_by(|a, b| {
+            b.week
+                .cmp(&a.week)
+                .then(b.scrobbles.cmp(&a.scrobbles))
+                .then(a.artist.cmp(&b.artist))
+        });
+
+        Self {
+            thresholds,
+            track_binges,
+            artist_obsessions,
+            phases,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for Binges {
+    fn sections(&self) -> Vec<Section> {
+        vec![
+            Section::table(
+                "Binges",
+                &["Start", "Window", "Track", "Artist", "Plays"],
+                self.track_binges
+                    .iter()
+                    .map(|b| {
+                        vec![
+                            b.start.to_string().into(),
+                            b.window.as_str().into(),
+                            b.title.as_str().into(),
+                            b.artist.as_str().into(),
+                            b.plays.into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+            Section::table(
+                "Obsessions",
+                &["Week", "Artist", "Scrobbles", "Week Scrobbles", "Share (%)"],
+                self.artist_obsessions
+                    .iter()
+                    .map(|o| {
+                        vec![
+                            o.week.to_string().into(),
+                            o.artist.as_str().into(),
+                            o.scrobbles.into(),
+                            o.week_scrobbles.into(),
+                            o.share.into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+            Section::table(
+                "Phases",
+                &[
+                    "Track",
+                    "Artist",
+                    "Peak Week",
+                    "Peak Plays",
+                    "Total Plays",
+                    "Last Played",
+                    "Decayed",
+                ],
+                self.phases
+                    .iter()
+                    .map(|p| {
+                        vec![
+                            p.title.as_str().into(),
+                            p.artist.as_str().into(),
+                            p.peak_week.to_string().into(),
+                            p.peak_plays.into(),
+                            p.total_plays.into(),
+                            p.last_played.to_string().into(),
+                            if p.decayed { "Yes" } else { "No" }.into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
@@ -0,0 +1,199 @@
+//! Rank movement over time, in the CSV layouts read by bar chart race tools
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate};
+use clap::ArgEnum;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::models::saved_weekly_charts::ChartKind;
+
+/// The length of each step of the race
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Interval {
+    Day,
+    /// Weeks start on Monday
+    Week,
+    Month,
+}
+
+impl Interval {
+    /// The first day of the interval containing `date`
+    fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Interval::Day => date,
+            Interval::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Interval::Month => date.with_day(1).unwrap(),
+        }
+    }
+
+    /// The first day of the interval after the one starting on `start`
+    fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Interval::Day => start + Duration::days(1),
+            Interval::Week => start + Duration::weeks(1),
+            Interval::Month if start.month() == 12 => NaiveDate::from_ymd(start.year() + 1, 1, 1),
+            Interval::Month => NaiveDate::from_ymd(start.year(), start.month() + 1, 1),
+        }
+    }
+}
+
+/// How play counts are accumulated from one interval to the next
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Every play since the first scrobble
+    Cumulative,
+    /// Only plays within the last `window` intervals
+    Rolling,
+}
+
+/// How the race is laid out as CSV
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    /// One row per name and one column per interval, as read by e.g. Flourish
+    Wide,
+    /// One row per interval and ranked name, with `date,name,value,rank` columns
+    Long,
+}
+
+/// The top ranked names at each interval of a listening history
+pub struct ChartRace {
+    /// The first day of each interval, from oldest to newest
+    pub dates: Vec<NaiveDate>,
+    /// The play count of every name at each interval, for names that were ever ranked
+    pub values: HashMap<String, Vec<i32>>,
+    /// The ranked names at each interval, from first to last
+    pub ranks: Vec<Vec<String>>,
+}
+
+impl ChartRace {
+    /// Ranks artists, albums or tracks at every interval from the first scrobble to the last
+    ///
+    /// With [Mode::Rolling](enum.Mode.html) the play counts only include the last `window`
+    /// intervals. Names are ranked by play count, with ties ranked alphabetically.
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        kind: ChartKind,
+        interval: Interval,
+        mode: Mode,
+        window: usize,
+        top: usize,
+    ) -> Self {
+        let mut dates: Vec<NaiveDate> = Vec::new();
+        let mut plays: Vec<HashMap<String, i32>> = Vec::new();
+
+        for scrobble in scrobbles.iter().rev() {
+            let name = match kind {
+                ChartKind::Artist => scrobble.artist.to_string(),
+                ChartKind::Album if scrobble.album.is_empty() => continue,
+                ChartKind::Album => scrobble.group_artist_album(),
+                ChartKind::Track => scrobble.group_song_artist(),
+            };
+
+            // Add every interval up to the scrobble's, including intervals without scrobbles
+            let start = interval.start(scrobble.date());
+            if dates.is_empty() {
+                dates.push(start);
+                plays.push(HashMap::new());
+            }
+            while *dates.last().unwrap() < start {
+                dates.push(interval.next(*dates.last().unwrap()));
+                plays.push(HashMap::new());
+            }
+
+            *plays.last_mut().unwrap().entry(name).or_insert(0) += 1;
+        }
+
+        let window = window.max(1);
+        let mut totals: HashMap<String, i32> = HashMap::new();
+        let mut history: Vec<HashMap<String, i32>> = Vec::new();
+        let mut ranks: Vec<Vec<String>> = Vec::new();
+
+        for (i, counts) in plays.iter().enumerate() {
+            for (name, count) in counts {
+                *totals.entry(name.to_string()).or_insert(0) += count;
+            }
+            if mode == Mode::Rolling && i >= window {
+                for (name, count) in &plays[i - window] {
+                    *totals.get_mut(name).unwrap() -= count;
+                }
+                totals.retain(|_, count| *count > 0);
+            }
+
+            let mut ranked = totals.iter().collect::<Vec<(&String, &i32)>>();
+            ranked.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            ranks.push(
+                ranked
+                    .into_iter()
+                    .take(top)
+                    .map(|(name, _)| name.to_string())
+                    .collect(),
+            );
+            history.push(totals.clone());
+        }
+
+        let ranked_names = ranks.iter().flatten().collect::<HashSet<&String>>();
+        let values = ranked_names
+            .into_iter()
+            .map(|name| {
+                let values = history
+                    .iter()
+                    .map(|totals| *totals.get(name).unwrap_or(&0))
+                    .collect();
+                (name.to_string(), values)
+            })
+            .collect();
+
+        Self {
+            dates,
+            values,
+            ranks,
+        }
+    }
+
+    /// Writes the race as CSV, with dates formatted as `%Y-%m-%d`
+    pub fn write_csv<W: Write>(&self, writer: W, layout: Layout) -> Result<()> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        let dates = self
+            .dates
+            .iter()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .collect::<Vec<String>>();
+
+        match layout {
+            Layout::Wide => {
+                let mut header = vec!["name".to_string()];
+                header.extend(dates);
+                wtr.write_record(&header)?;
+
+                let mut names = self.values.keys().collect::<Vec<&String>>();
+                names.sort();
+                for name in names {
+                    let mut record = vec![name.to_string()];
+                    record.extend(self.values[name].iter().map(|value| value.to_string()));
+                    wtr.write_record(&record)?;
+                }
+            }
+            Layout::Long => {
+                wtr.write_record(["date", "name", "value", "rank"])?;
+
+                for (i, date) in dates.iter().enumerate() {
+                    for (rank, name) in self.ranks[i].iter().enumerate() {
+                        wtr.write_record([
+                            date.to_string(),
+                            name.to_string(),
+                            self.values[name][i].to_string(),
+                            (rank + 1).to_string(),
+                        ])?;
+                    }
+                }
+            }
+        }
+
+        wtr.flush()?;
+
+        Ok(())
+    }
+}
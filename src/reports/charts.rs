@@ -0,0 +1,234 @@
+//! Weekly charts generated from saved scrobbles, and how they differ from the charts on Last.fm
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::models::saved_weekly_charts::{
+    ChartKind, SavedWeeklyCharts, WeeklyChart, WeeklyChartEntry,
+};
+use crate::output::{Report, Section};
+
+/// Generates the charts of the week from `from` to just before `to` from a history sorted from
+/// newest to oldest, the same way Last.fm does
+///
+/// Albums are charted only for scrobbles with an album. Entries are ranked by scrobbles, with
+/// ties ranked alphabetically.
+pub fn generate_weekly_chart(scrobbles: &[SavedScrobble], from: i64, to: i64) -> WeeklyChart {
+    let start = scrobbles.partition_point(|s| s.timestamp_utc >= to);
+    let end = scrobbles.partition_point(|s| s.timestamp_utc >= from);
+    let week = &scrobbles[start..end.max(start)];
+
+    WeeklyChart {
+        from,
+        to,
+        artists: chart(week, |s| Some((s.artist.as_str(), None))),
+        albums: chart(week, |s| {
+            if s.album.is_empty() {
+                None
+            } else {
+                Some((s.artist.as_str(), Some(s.album.as_str())))
+            }
+        }),
+        tracks: chart(week, |s| Some((s.artist.as_str(), Some(s.title.as_str())))),
+    }
+}
+
+fn chart<F>(scrobbles: &[SavedScrobble], entry: F) -> Vec<WeeklyChartEntry>
+where
+    F: Fn(&SavedScrobble) -> Option<(&str, Option<&str>)>,
+{
+    let mut entries: HashMap<(String, String), WeeklyChartEntry> = HashMap::new();
+
+    for (artist, name) in scrobbles.iter().filter_map(entry) {
+        let entry = WeeklyChartEntry {
+            rank: 0,
+            artist: artist.to_string(),
+            name: name.map(|n| n.to_string()),
+            scrobbles: 0,
+        };
+
+        entries.entry(entry.key()).or_insert(entry).scrobbles += 1;
+    }
+
+    let mut entries = entries.into_iter().map(|e| e.1).collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.scrobbles.cmp(&a.scrobbles).then(a.key().cmp(&b.key())));
+    entries
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, entry)| entry.rank = i as i32 + 1);
+
+    entries
+}
+
+/// Compares the weekly charts saved from Last.fm with charts generated from saved scrobbles
+///
+/// A week where Last.fm counted more scrobbles than were saved points to scrobbles that failed to
+/// be fetched. Last.fm counts every scrobble, so the exclusion rules are not applied.
+#[derive(Serialize)]
+pub struct ChartDiff {
+    pub kind: ChartKind,
+    pub weeks: i32,
+    pub matching_weeks: i32,
+    /// Scrobbles counted by Last.fm but missing from the saved scrobbles, over every week
+    pub missing_scrobbles: i32,
+    /// Saved scrobbles that Last.fm did not count, over every week
+    pub extra_scrobbles: i32,
+    /// Weeks where the charts differ, from oldest to newest
+    pub mismatched_weeks: Vec<WeekDiff>,
+}
+
+#[derive(Serialize)]
+pub struct WeekDiff {
+    /// The local date the week started on
+    pub week: NaiveDate,
+    pub from: i64,
+    pub to: i64,
+    pub lastfm_scrobbles: i32,
+    pub local_scrobbles: i32,
+    /// Entries whose scrobbles differ, including entries missing from either chart
+    pub entries: Vec<EntryDiff>,
+}
+
+#[derive(Serialize)]
+pub struct EntryDiff {
+    pub artist: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub lastfm_scrobbles: i32,
+    pub local_scrobbles: i32,
+}
+
+impl ChartDiff {
+    pub fn new(charts: &SavedWeeklyCharts, scrobbles: &[SavedScrobble], kind: ChartKind) -> Self {
+        let mut diff = Self {
+            kind,
+            weeks: 0,
+            matching_weeks: 0,
+            missing_scrobbles: 0,
+            extra_scrobbles: 0,
+            mismatched_weeks: Vec::new(),
+        };
+
+        for lastfm_chart in charts.iter() {
+            let local_chart = generate_weekly_chart(scrobbles, lastfm_chart.from, lastfm_chart.to);
+            let entries = diff_entries(lastfm_chart.entries(kind), local_chart.entries(kind));
+
+            diff.weeks += 1;
+            if entries.is_empty() {
+                diff.matching_weeks += 1;
+                continue;
+            }
+
+            for entry in &entries {
+                let difference = entry.lastfm_scrobbles - entry.local_scrobbles;
+                if difference > 0 {
+                    diff.missing_scrobbles += difference;
+                } else {
+                    diff.extra_scrobbles -= difference;
+                }
+            }
+
+            let total = |entries: &[WeeklyChartEntry]| entries.iter().map(|e| e.scrobbles).sum();
+            diff.mismatched_weeks.push(WeekDiff {
+                week: Local.timestamp(lastfm_chart.from, 0).naive_local().date(),
+                from: lastfm_chart.from,
+                to: lastfm_chart.to,
+                lastfm_scrobbles: total(lastfm_chart.entries(kind)),
+                local_scrobbles: total(local_chart.entries(kind)),
+                entries,
+            });
+        }
+
+        diff
+    }
+}
+
+impl Report for ChartDiff {
+    fn sections(&self) -> Vec<Section> {
+        vec![
+            Section::key_values(
+                "Chart Diff",
+                vec![
+                    ("Chart", self.kind.as_str().into()),
+                    ("Weeks Compared", self.weeks.into()),
+                    ("Matching Weeks", self.matching_weeks.into()),
+                    ("Mismatched Weeks", self.mismatched_weeks.len().into()),
+                    ("Missing Scrobbles", self.missing_scrobbles.into()),
+                    ("Extra Scrobbles", self.extra_scrobbles.into()),
+                ],
+            ),
+            Section::table(
+                "Mismatched Weeks",
+                &["Week", "Last.fm", "Local", "Difference", "Entries"],
+                self.mismatched_weeks
+                    .iter()
+                    .map(|w| {
+                        vec![
+                            w.week.to_string().into(),
+                            w.lastfm_scrobbles.into(),
+                            w.local_scrobbles.into(),
+                            (w.local_scrobbles - w.lastfm_scrobbles).into(),
+                            w.entries.len().into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+            Section::table(
+                "Mismatched Entries",
+                &["Week", "Artist", "Name", "Last.fm", "Local"],
+                self.mismatched_weeks
+                    .iter()
+                    .flat_map(|w| {
+                        w.entries.iter().map(move |e| {
+                            vec![
+                                w.week.to_string().into(),
+                                e.artist.as_str().into(),
+                                e.name.as_deref().into(),
+                                e.lastfm_scrobbles.into(),
+                                e.local_scrobbles.into(),
+                            ]
+                        })
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+fn diff_entries(lastfm: &[WeeklyChartEntry], local: &[WeeklyChartEntry]) -> Vec<EntryDiff> {
+    let lastfm_entries = lastfm
+        .iter()
+        .map(|e| (e.key(), e))
+        .collect::<HashMap<_, _>>();
+    let local_entries = local
+        .iter()
+        .map(|e| (e.key(), e))
+        .collect::<HashMap<_, _>>();
+
+    // Keep Last.fm's chart order, followed by entries only found locally
+    let mut seen = HashSet::new();
+    lastfm
+        .iter()
+        .chain(local.iter())
+        .filter(|e| seen.insert(e.key()))
+        .filter_map(|e| {
+            let key = e.key();
+            let lastfm_scrobbles = lastfm_entries.get(&key).map_or(0, |e| e.scrobbles);
+            let local_scrobbles = local_entries.get(&key).map_or(0, |e| e.scrobbles);
+
+            if lastfm_scrobbles == local_scrobbles {
+                return None;
+            }
+
+            Some(EntryDiff {
+                artist: e.artist.to_string(),
+                name: e.name.clone(),
+                lastfm_scrobbles,
+                local_scrobbles,
+            })
+        })
+        .collect()
+}
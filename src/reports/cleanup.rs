@@ -0,0 +1,127 @@
+//! A preview of the tracks and albums that the title cleanup merges when scrobbles are grouped
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::models::name_rules::TitleCleaner;
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Report, Section};
+use crate::stats::{self, RankedItem};
+
+/// A cleaned up track or album name, and the names it was scrobbled under
+#[derive(Serialize)]
+pub struct MergedName {
+    pub name: String,
+    pub artist: String,
+    pub scrobbles: i32,
+    /// Each name as scrobbled, most scrobbled first
+    pub variants: Vec<RankedItem>,
+}
+
+/// The tracks and albums scrobbled under more than one name that the cleanup merges, most
+/// scrobbled first
+#[derive(Serialize)]
+pub struct CleanupPreview {
+    pub tracks: Vec<MergedName>,
+    pub albums: Vec<MergedName>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+impl CleanupPreview {
+    pub fn new(scrobbles: &[SavedScrobble], cleaner: &TitleCleaner, limit: usize) -> Self {
+        Self {
+            tracks: merged_names(
+                scrobbles.iter().map(|s| (&s.title, &s.artist)),
+                cleaner,
+                limit,
+            ),
+            albums: merged_names(
+                scrobbles
+                    .iter()
+                    .filter(|s| !s.album.is_empty())
+                    .map(|s| (&s.album, &s.artist)),
+                cleaner,
+                limit,
+            ),
+            excluded_scrobbles: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty() && self.albums.is_empty()
+    }
+}
+
+/// Groups `(name, artist)` pairs by their cleaned up name, keeping the groups with more than one
+/// variant
+fn merged_names<'a, I>(names: I, cleaner: &TitleCleaner, limit: usize) -> Vec<MergedName>
+where
+    I: Iterator<Item = (&'a String, &'a String)>,
+{
+    let mut groups: HashMap<(String, String), HashMap<String, i32>> = HashMap::new();
+    for (name, artist) in names {
+        let variants = groups
+            .entry((cleaner.clean(name), artist.to_string()))
+            .or_default();
+        *variants.entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    let mut merged = groups
+        .into_iter()
+        .filter(|(_, variants)| variants.len() > 1)
+        .map(|((name, artist), variants)| MergedName {
+            name,
+            artist,
+            scrobbles: variants.values().sum(),
+            variants: stats::top_n(&variants, variants.len()),
+        })
+        .collect::<Vec<MergedName>>();
+
+    merged.sort_by(|a, b| {
+        b.scrobbles
+            .cmp(&a.scrobbles)
+            .then_with(|| a.artist.cmp(&b.artist))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    merged.truncate(limit);
+
+    merged
+}
+
+impl Report for CleanupPreview {
+    fn sections(&self) -> Vec<Section> {
+        let table = |title: &str, column: &str, merged: &[MergedName]| {
+            Section::table(
+                title,
+                &[column, "Artist", "Scrobbles", "Variants"],
+                merged
+                    .iter()
+                    .map(|m| {
+                        vec![
+                            m.name.as_str().into(),
+                            m.artist.as_str().into(),
+                            m.scrobbles.into(),
+                            m.variants
+                                .iter()
+                                .map(|v| format!("{} ({})", v.name, v.scrobbles))
+                                .collect::<Vec<String>>()
+                                .join(", ")
+                                .into(),
+                        ]
+                    })
+                    .collect(),
+            )
+        };
+
+        vec![
+            table("Merged Tracks", "Track", &self.tracks),
+            table("Merged Albums", "Album", &self.albums),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
@@ -0,0 +1,262 @@
+//! Taste compatibility between two users, calculated from their saved scrobbles
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Cell, Report, Section};
+use crate::stats::{self, Period, RankedItem};
+
+const TOP_ITEMS: usize = 10;
+
+/// Compares the artists two users listened to during a period
+#[derive(Serialize)]
+pub struct Compatibility {
+    pub user_a: String,
+    pub user_b: String,
+    pub period: Period,
+
+    pub scrobbles_a: i32,
+    pub scrobbles_b: i32,
+    pub artists_a: i32,
+    pub artists_b: i32,
+    pub shared_artists: i32,
+
+    /// Shared artists divided by the artists played by either user (Jaccard index), from 0 to 1
+    pub overlap: f64,
+    /// Cosine similarity of the two users' artist play counts, from 0 to 1
+    pub cosine_similarity: f64,
+    /// Cosine similarity of log-scaled play counts, from 0 to 100
+    ///
+    /// Scaling the play counts keeps a single heavily played artist from dominating the score.
+    pub score: f64,
+    /// A label for the score, from "Very Low" to "Super"
+    pub rating: String,
+
+    pub shared_top_artists: Vec<SharedArtist>,
+    /// Artists `user_a` loves that `user_b` has never played, ranked by `user_a`'s scrobbles of
+    /// loved tracks during the period
+    pub loved_only_a: Vec<RankedItem>,
+    /// Artists `user_b` loves that `user_a` has never played, ranked by `user_b`'s scrobbles of
+    /// loved tracks during the period
+    pub loved_only_b: Vec<RankedItem>,
+    /// The number of scrobbles of both users left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Serialize)]
+pub struct SharedArtist {
+    pub name: String,
+    pub scrobbles_a: i32,
+    pub scrobbles_b: i32,
+}
+
+impl Compatibility {
+    /// Compares two users' entire listening histories, restricted to `period` ending at `now`
+    pub fn new(
+        user_a: (&str, &[SavedScrobble]),
+        user_b: (&str, &[SavedScrobble]),
+        period: Period,
+        now: i64,
+    ) -> Self {
+        let (name_a, history_a) = user_a;
+        let (name_b, history_b) = user_b;
+
+        let scrobbles_a = period.filter(history_a, now);
+        let scrobbles_b = period.filter(history_b, now);
+
+        let artists_a = stats::count_by(&scrobbles_a, |s| s.artist.to_string());
+        let artists_b = stats::count_by(&scrobbles_b, |s| s.artist.to_string());
+
+        let shared = artists_a
+            .keys()
+            .filter(|artist| artists_b.contains_key(*artist))
+            .collect::<HashSet<&String>>();
+        let combined = artists_a.len() + artists_b.len() - shared.len();
+
+        let overlap = if combined > 0 {
+            shared.len() as f64 / combined as f64
+        } else {
+            0.0
+        };
+
+        let cosine_similarity = cosine_similarity(&artists_a, &artists_b, |plays| plays as f64);
+        let score = cosine_similarity_log(&artists_a, &artists_b) * 100.0;
+
+        let total_a = scrobbles_a.len().max(1) as f64;
+        let total_b = scrobbles_b.len().max(1) as f64;
+        let mut shared_top_artists = shared
+            .iter()
+            .map(|artist| SharedArtist {
+                name: artist.to_string(),
+                scrobbles_a: artists_a[*artist],
+                scrobbles_b: artists_b[*artist],
+            })
+            .collect::<Vec<SharedArtist>>();
+
+        // Rank shared artists by the smaller of their two shares, so an artist ranks highly
+        // only when both users listen to them a lot
+        let shared_share =
+            |a: &SharedArtist| (a.scrobbles_a as f64 / total_a).min(a.scrobbles_b as f64 / total_b);
+        shared_top_artists.sort_by(|a, b| {
+            shared_share(b)
+                .partial_cmp(&shared_share(a))
+                .unwrap()
+                .then(a.name.cmp(&b.name))
+        });
+        shared_top_artists.truncate(TOP_ITEMS);
+
+        Self {
+            user_a: name_a.to_string(),
+            user_b: name_b.to_string(),
+            period,
+
+            scrobbles_a: scrobbles_a.len() as i32,
+            scrobbles_b: scrobbles_b.len() as i32,
+            artists_a: artists_a.len() as i32,
+            artists_b: artists_b.len() as i32,
+            shared_artists: shared.len() as i32,
+
+            overlap,
+            cosine_similarity,
+            score,
+            rating: rating(score).to_string(),
+
+            shared_top_artists,
+            loved_only_a: loved_never_played_by(&scrobbles_a, history_b),
+            loved_only_b: loved_never_played_by(&scrobbles_b, history_a),
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for Compatibility {
+    fn sections(&self) -> Vec<Section> {
+        let ranked_rows = |items: &[RankedItem]| {
+            items
+                .iter()
+                .map(|item| vec![Cell::from(item.name.as_str()), item.scrobbles.into()])
+                .collect::<Vec<Vec<Cell>>>()
+        };
+
+        vec![
+            Section::key_values(
+                "Compatibility",
+                vec![
+                    ("Rating", self.rating.as_str().into()),
+                    ("Score", self.score.into()),
+                    ("Cosine Similarity", self.cosine_similarity.into()),
+                    ("Overlap", self.overlap.into()),
+                    ("Shared Artists", self.shared_artists.into()),
+                    (
+                        &format!("{} Scrobbles", self.user_a),
+                        self.scrobbles_a.into(),
+                    ),
+                    (
+                        &format!("{} Scrobbles", self.user_b),
+                        self.scrobbles_b.into(),
+                    ),
+                    (&format!("{} Artists", self.user_a), self.artists_a.into()),
+                    (&format!("{} Artists", self.user_b), self.artists_b.into()),
+                ],
+            ),
+            Section::table(
+                "Shared Top Artists",
+                &["Artist", &self.user_a, &self.user_b],
+                self.shared_top_artists
+                    .iter()
+                    .map(|artist| {
+                        vec![
+                            artist.name.as_str().into(),
+                            artist.scrobbles_a.into(),
+                            artist.scrobbles_b.into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+            Section::table(
+                &format!("Loved by {}, Never Played by {}", self.user_a, self.user_b),
+                &["Artist", "Loved Scrobbles"],
+                ranked_rows(&self.loved_only_a),
+            ),
+            Section::table(
+                &format!("Loved by {}, Never Played by {}", self.user_b, self.user_a),
+                &["Artist", "Loved Scrobbles"],
+                ranked_rows(&self.loved_only_b),
+            ),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+fn cosine_similarity<F>(a: &HashMap<String, i32>, b: &HashMap<String, i32>, weight: F) -> f64
+where
+    F: Fn(i32) -> f64,
+{
+    let dot: f64 = a
+        .iter()
+        .filter_map(|(artist, plays)| b.get(artist).map(|other| weight(*plays) * weight(*other)))
+        .sum();
+
+    let magnitude = |counts: &HashMap<String, i32>| {
+        counts
+            .values()
+            .map(|plays| weight(*plays).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+
+    let magnitudes = magnitude(a) * magnitude(b);
+    if magnitudes == 0.0 {
+        return 0.0;
+    }
+
+    dot / magnitudes
+}
+
+fn cosine_similarity_log(a: &HashMap<String, i32>, b: &HashMap<String, i32>) -> f64 {
+    cosine_similarity(a, b, |plays| (1.0 + plays as f64).ln())
+}
+
+fn rating(score: f64) -> &'static str {
+    if score >= 80.0 {
+        "Super"
+    } else if score >= 60.0 {
+        "Very High"
+    } else if score >= 40.0 {
+        "High"
+    } else if score >= 20.0 {
+        "Medium"
+    } else if score >= 10.0 {
+        "Low"
+    } else {
+        "Very Low"
+    }
+}
+
+/// The artists of the loved tracks in `scrobbles` that never appear anywhere in `other_history`,
+/// most scrobbled first
+fn loved_never_played_by(
+    scrobbles: &[SavedScrobble],
+    other_history: &[SavedScrobble],
+) -> Vec<RankedItem> {
+    let other_artists = other_history
+        .iter()
+        .map(|s| s.artist.as_str())
+        .collect::<HashSet<&str>>();
+
+    let loved = scrobbles
+        .iter()
+        .filter(|s| s.loved && !other_artists.contains(s.artist.as_str()))
+        .cloned()
+        .collect::<Vec<SavedScrobble>>();
+
+    stats::top_n(
+        &stats::count_by(&loved, |s| s.artist.to_string()),
+        TOP_ITEMS,
+    )
+}
@@ -0,0 +1,234 @@
+//! How evenly plays are spread across artists, overall and month over month
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Cell, Report, Section};
+use crate::stats::{self, Period};
+
+/// The share of artists, by rank, that make up the head of the distribution. Plays of every
+/// other artist count towards the long tail.
+const HEAD_SHARE: f64 = 0.2;
+
+/// Diversity measures of a period's scrobbles, along with the same measures for each month
+///
+/// Rising entropy and falling Gini coefficients from month to month mean listening is broadening
+/// across more artists, while the opposite means it is narrowing.
+#[derive(Serialize)]
+pub struct Diversity {
+    pub period: Period,
+    pub overall: DiversityMeasures,
+    /// Oldest first
+    pub months: Vec<MonthDiversity>,
+    /// The least squares slope of the monthly entropy, in bits per month, or `null` with fewer
+    /// than two months
+    pub entropy_trend: Option<f64>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Serialize)]
+pub struct MonthDiversity {
+    /// Formatted as `%Y-%m`
+    pub month: String,
+    #[serde(flatten)]
+    pub measures: DiversityMeasures,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DiversityMeasures {
+    pub scrobbles: i32,
+    pub artists: i32,
+    /// Shannon entropy of artist plays, in bits
+    pub entropy: f64,
+    /// Gini coefficient of artist plays, from 0 (every artist played equally) to 1
+    pub gini: f64,
+    /// The fewest artists whose plays make up half of all plays
+    pub artists_for_50: i32,
+    /// The fewest artists whose plays make up 80% of all plays
+    pub artists_for_80: i32,
+    /// The share of plays, from 0 to 1, of artists outside the top 20% of artists
+    pub long_tail_ratio: f64,
+}
+
+impl DiversityMeasures {
+    /// Calculates the measures from the number of plays of each artist
+    pub fn from_counts(counts: &HashMap<String, i32>) -> Self {
+        let mut plays = counts.values().copied().collect::<Vec<i32>>();
+        plays.sort_unstable_by(|a, b| b.cmp(a));
+
+        let total = plays.iter().sum::<i32>();
+        let artists = plays.len() as i32;
+        if total == 0 {
+            return Self {
+                scrobbles: 0,
+                artists: 0,
+                entropy: 0.0,
+                gini: 0.0,
+                artists_for_50: 0,
+                artists_for_80: 0,
+                long_tail_ratio: 0.0,
+            };
+        }
+
+        let entropy = plays
+            .iter()
+            .map(|p| *p as f64 / total as f64)
+            .map(|p| -p * p.log2())
+            .sum::<f64>()
+            // Keeps a single artist from showing as -0
+            .max(0.0);
+
+        // With plays sorted from most to least, G = (n + 1 - 2 * sum(i * x_i) / sum(x)) / n,
+        // where i is the 1-based rank of the artist
+        let weighted = plays
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i as f64 + 1.0) * *p as f64)
+            .sum::<f64>();
+        let n = artists as f64;
+        let gini = ((n + 1.0 - 2.0 * weighted / total as f64) / n).max(0.0);
+
+        let head = (n * HEAD_SHARE).ceil() as usize;
+        let tail = plays.iter().skip(head).sum::<i32>();
+
+        Self {
+            scrobbles: total,
+            artists,
+            entropy,
+            gini,
+            artists_for_50: artists_to_cover(&plays, total, 0.5),
+            artists_for_80: artists_to_cover(&plays, total, 0.8),
+            long_tail_ratio: tail as f64 / total as f64,
+        }
+    }
+}
+
+impl Diversity {
+    /// Calculates diversity measures of the scrobbles within `period`, ending at `now`
+    pub fn new(scrobbles: &[SavedScrobble], period: Period, now: i64) -> Self {
+        let scrobbles = period.filter(scrobbles, now);
+
+        let overall = DiversityMeasures::from_counts(&artist_counts(&scrobbles));
+
+        let mut months = stats::group_by(&scrobbles, |s| s.date().format("%Y-%m").to_string())
+            .into_iter()
+            .map(|(month, scrobbles)| MonthDiversity {
+                month,
+                measures: DiversityMeasures::from_counts(&artist_counts(&scrobbles)),
+            })
+            .collect::<Vec<MonthDiversity>>();
+        months.sort_by(|a, b| a.month.cmp(&b.month));
+
+        let entropy_trend = trend(
+            &months
+                .iter()
+                .map(|m| m.measures.entropy)
+                .collect::<Vec<f64>>(),
+        );
+
+        Self {
+            period,
+            overall,
+            months,
+            entropy_trend,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for Diversity {
+    fn sections(&self) -> Vec<Section> {
+        let overall = &self.overall;
+
+        vec![
+            Section::key_values(
+                "Diversity",
+                vec![
+                    ("Scrobbles", overall.scrobbles.into()),
+                    ("Artists", overall.artists.into()),
+                    ("Entropy (Bits)", overall.entropy.into()),
+                    ("Gini Coefficient", overall.gini.into()),
+                    ("Artists For 50% Of Plays", overall.artists_for_50.into()),
+                    ("Artists For 80% Of Plays", overall.artists_for_80.into()),
+                    ("Long Tail (%)", (overall.long_tail_ratio * 100.0).into()),
+                    ("Entropy Trend (Bits/Month)", self.entropy_trend.into()),
+                ],
+            ),
+            Section::table(
+                "Diversity By Month",
+                &[
+                    "Month",
+                    "Scrobbles",
+                    "Artists",
+                    "Entropy",
+                    "Gini",
+                    "50% Artists",
+                    "80% Artists",
+                    "Long Tail (%)",
+                ],
+                self.months
+                    .iter()
+                    .map(|m| {
+                        vec![
+                            Cell::from(m.month.as_str()),
+                            m.measures.scrobbles.into(),
+                            m.measures.artists.into(),
+                            m.measures.entropy.into(),
+                            m.measures.gini.into(),
+                            m.measures.artists_for_50.into(),
+                            m.measures.artists_for_80.into(),
+                            (m.measures.long_tail_ratio * 100.0).into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+fn artist_counts(scrobbles: &[SavedScrobble]) -> HashMap<String, i32> {
+    stats::count_by(scrobbles, |s| s.artist.to_string())
+}
+
+/// The number of artists, taken from most to least played, needed to reach `share` of `total`
+fn artists_to_cover(plays: &[i32], total: i32, share: f64) -> i32 {
+    let target = total as f64 * share;
+    let mut covered = 0;
+
+    for (i, p) in plays.iter().enumerate() {
+        covered += p;
+        if covered as f64 >= target {
+            return i as i32 + 1;
+        }
+    }
+
+    plays.len() as i32
+}
+
+/// The least squares slope of evenly spaced values
+fn trend(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+
+    let n = values.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<f64>() / n;
+
+    let (covariance, variance) = values
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(c, v), (x, y)| {
+            let dx = x as f64 - mean_x;
+            (c + dx * (y - mean_y), v + dx * dx)
+        });
+
+    Some(covariance / variance)
+}
@@ -0,0 +1,330 @@
+//! Projections of when scrobble and artist milestones will be reached, from recent listening
+
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Report, Section};
+use crate::stats::{self, Stats};
+
+/// The z-score of the 95% confidence bounds
+const Z_95: f64 = 1.96;
+
+/// The listening rate over the most recent days of a history
+///
+/// The window ends on the day projections are made from, so a history that has not been updated
+/// in a while counts the days since its newest scrobble as days without scrobbles.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct RecentRate {
+    pub window_days: i64,
+    /// The mean number of scrobbles per day, counting days without scrobbles
+    pub daily_mean: f64,
+    pub daily_std_dev: f64,
+}
+
+impl RecentRate {
+    /// Measures the rate over the `window_days` days up to and including `end`
+    pub fn ending_on(scrobbles: &[SavedScrobble], end: NaiveDate, window_days: i64) -> Self {
+        let start = end - Duration::days(window_days - 1);
+
+        let in_window = scrobbles
+            .iter()
+            .filter(|s| s.date() >= start && s.date() <= end)
+            .cloned()
+            .collect::<Vec<SavedScrobble>>();
+        let days = stats::count_by(&in_window, |s| s.date());
+
+        let counts = (0..window_days)
+            .map(|i| *days.get(&(start + Duration::days(i))).unwrap_or(&0) as f64)
+            .collect::<Vec<f64>>();
+        let n = counts.len().max(1) as f64;
+        let daily_mean = counts.iter().sum::<f64>() / n;
+        let variance = counts.iter().map(|c| (c - daily_mean).powi(2)).sum::<f64>() / n;
+
+        Self {
+            window_days,
+            daily_mean,
+            daily_std_dev: variance.sqrt(),
+        }
+    }
+
+    /// The days needed to add `remaining` scrobbles at this rate, along with the fewest and most
+    /// days within the confidence bounds. `None` if nothing was scrobbled within the window.
+    ///
+    /// Daily counts are treated as independent, so after `n` days the total is roughly normal
+    /// with a mean of `n * mean` and a standard deviation of `sqrt(n) * std_dev`.
+    fn days_to(&self, remaining: i32) -> Option<(i64, i64, i64)> {
+        if remaining <= 0 {
+            return Some((0, 0, 0));
+        }
+        if self.daily_mean <= 0.0 {
+            return None;
+        }
+
+        let (mean, spread, remaining) =
+            (self.daily_mean, Z_95 * self.daily_std_dev, remaining as f64);
+
+        // Solves mean * x^2 ± spread * x - remaining = 0 for x = sqrt(n)
+        let root = (spread.powi(2) + 4.0 * mean * remaining).sqrt();
+        let fewest = ((root - spread) / (2.0 * mean)).powi(2);
+        let most = ((root + spread) / (2.0 * mean)).powi(2);
+
+        // Rounds up to whole days, ignoring floating point noise from the square roots
+        let days = |n: f64| (n - 1e-9).ceil() as i64;
+
+        Some((days(remaining / mean), days(fewest), days(most)))
+    }
+}
+
+/// When a count will reach a target
+#[derive(Serialize)]
+pub struct Projection {
+    pub target: i32,
+    pub current: i32,
+    /// The projected date, or `null` if nothing was scrobbled recently
+    pub expected: Option<NaiveDate>,
+    /// The earliest date within the 95% confidence bounds
+    pub earliest: Option<NaiveDate>,
+    /// The latest date within the 95% confidence bounds
+    pub latest: Option<NaiveDate>,
+}
+
+impl Projection {
+    fn new(rate: &RecentRate, current: i32, target: i32, today: NaiveDate) -> Self {
+        let days = rate.days_to(target - current);
+
+        Self {
+            target,
+            current,
+            expected: days.map(|(expected, _, _)| today + Duration::days(expected)),
+            earliest: days.map(|(_, fewest, _)| today + Duration::days(fewest)),
+            latest: days.map(|(_, _, most)| today + Duration::days(most)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ArtistForecast {
+    pub artist: String,
+    pub rate: RecentRate,
+    pub projection: Projection,
+}
+
+/// Projected milestone dates, based on the listening rate of the last few months rather than the
+/// lifetime average
+#[derive(Serialize)]
+pub struct Forecast {
+    pub today: NaiveDate,
+    pub lifetime_daily_average: f64,
+    pub rate: RecentRate,
+    pub scrobbles: Projection,
+    pub artist: Option<ArtistForecast>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+impl Forecast {
+    /// Projects when the total `play_count` reaches `target`, or the next milestone without one
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        play_count: i32,
+        today: NaiveDate,
+        window_days: i64,
+        target: Option<i32>,
+    ) -> Self {
+        let rate = RecentRate::ending_on(scrobbles, today, window_days);
+        let target = target.unwrap_or_else(|| next_milestone(play_count));
+
+        Self {
+            today,
+            lifetime_daily_average: Stats::new(scrobbles).average_tracks_per_day,
+            rate,
+            scrobbles: Projection::new(&rate, play_count, target, today),
+            artist: None,
+            excluded_scrobbles: 0,
+        }
+    }
+
+    /// Adds a projection of when `artist` reaches `plays`, or their next milestone without one
+    ///
+    /// Returns `None` if the artist has never been scrobbled.
+    pub fn with_artist(
+        mut self,
+        scrobbles: &[SavedScrobble],
+        artist: &str,
+        plays: Option<i32>,
+    ) -> Option<Self> {
+        let artist = artist.to_lowercase();
+        let artist_scrobbles = scrobbles
+            .iter()
+            .filter(|s| s.artist.to_lowercase() == artist)
+            .cloned()
+            .collect::<Vec<SavedScrobble>>();
+        let name = artist_scrobbles.first()?.artist.to_string();
+
+        let current = artist_scrobbles.len() as i32;
+        let target = plays.unwrap_or_else(|| next_milestone(current));
+
+        let rate = RecentRate::ending_on(&artist_scrobbles, self.today, self.rate.window_days);
+
+        self.artist = Some(ArtistForecast {
+            artist: name,
+            rate,
+            projection: Projection::new(&rate, current, target, self.today),
+        });
+
+        Some(self)
+    }
+}
+
+impl Report for Forecast {
+    fn sections(&self) -> Vec<Section> {
+        let projection = |title: &str, rate: &RecentRate, projection: &Projection| {
+            Section::key_values(
+                title,
+                vec![
+                    ("Current", projection.current.into()),
+                    ("Target", projection.target.into()),
+                    ("Recent Daily Rate", rate.daily_mean.into()),
+                    (
+                        "Expected",
+                        projection.expected.map(|d| d.to_string()).into(),
+                    ),
+                    (
+                        "Earliest (95%)",
+                        projection.earliest.map(|d| d.to_string()).into(),
+                    ),
+                    (
+                        "Latest (95%)",
+                        projection.latest.map(|d| d.to_string()).into(),
+                    ),
+                ],
+            )
+        };
+
+        let mut sections = vec![
+            Section::key_values(
+                "Forecast",
+                vec![
+                    ("Window (Days)", self.rate.window_days.into()),
+                    ("Recent Daily Rate", self.rate.daily_mean.into()),
+                    ("Recent Daily Std Dev", self.rate.daily_std_dev.into()),
+                    ("Lifetime Daily Average", self.lifetime_daily_average.into()),
+                ],
+            ),
+            projection("Next Scrobble Milestone", &self.rate, &self.scrobbles),
+        ];
+
+        if let Some(artist) = &self.artist {
+            sections.push(projection(
+                &format!("{} Plays", artist.artist),
+                &artist.rate,
+                &artist.projection,
+            ));
+        }
+
+        sections
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// The next of 1, 2.5 and 5 times a power of ten above `count`, e.g. 100,000 and then 250,000
+pub fn next_milestone(count: i32) -> i32 {
+    let mut power = 10;
+    loop {
+        for step in [power, power * 5 / 2, power * 5] {
+            if step > count {
+                return step;
+            }
+        }
+        power *= 10;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::saved_scrobbles::{SavedScrobble, SavedScrobbles};
+
+    #[test]
+    fn test_forecast() {
+        assert_eq!(next_milestone(0), 10);
+        assert_eq!(next_milestone(99_999), 100_000);
+        assert_eq!(next_milestone(100_000), 250_000);
+
+        let day = 86_400;
+        let jan_1_2021 = 1_609_502_400;
+
+        // Two scrobbles every day for ten days, one of them by Radiohead
+        let scrobbles = (0..10)
+            .flat_map(|i| {
+                vec![
+                    SavedScrobble::played(
+                        "Airbag",
+                        "Radiohead",
+                        "OK Computer",
+                        jan_1_2021 + i * day,
+                    ),
+                    SavedScrobble::played(
+                        "Hyperballad",
+                        "Björk",
+                        "Post",
+                        jan_1_2021 + i * day + 600,
+                    ),
+                ]
+            })
+            .collect();
+        let saved_scrobbles = SavedScrobbles::new(scrobbles);
+
+        let today = NaiveDate::from_ymd(2021, 1, 10);
+        let forecast = Forecast::new(saved_scrobbles.as_slice(), 90, today, 10, Some(100))
+            .with_artist(saved_scrobbles.as_slice(), "radiohead", None)
+            .unwrap();
+
+        assert!((forecast.rate.daily_mean - 2.0).abs() < 1e-9);
+        assert_eq!(
+            forecast.scrobbles.expected,
+            Some(NaiveDate::from_ymd(2021, 1, 15))
+        );
+        // Without any variation, the bounds are the expected date
+        assert_eq!(forecast.scrobbles.earliest, forecast.scrobbles.expected);
+
+        let artist = forecast.artist.unwrap();
+        assert_eq!(artist.artist, "Radiohead");
+        assert_eq!(artist.projection.current, 10);
+        assert_eq!(artist.projection.target, 25);
+        assert_eq!(
+            artist.projection.expected,
+            Some(NaiveDate::from_ymd(2021, 1, 25))
+        );
+
+        // Five days after the newest scrobble, half of the window has no scrobbles, and the
+        // projections start from today rather than from the newest scrobble
+        let today = NaiveDate::from_ymd(2021, 1, 15);
+        let forecast = Forecast::new(saved_scrobbles.as_slice(), 90, today, 10, Some(100))
+            .with_artist(saved_scrobbles.as_slice(), "radiohead", None)
+            .unwrap();
+
+        assert!((forecast.rate.daily_mean - 1.0).abs() < 1e-9);
+        assert_eq!(
+            forecast.scrobbles.expected,
+            Some(NaiveDate::from_ymd(2021, 1, 25))
+        );
+
+        let artist = forecast.artist.unwrap();
+        assert!((artist.rate.daily_mean - 0.5).abs() < 1e-9);
+        assert_eq!(
+            artist.projection.expected,
+            Some(NaiveDate::from_ymd(2021, 2, 14))
+        );
+
+        // Nothing within the window leaves the dates unknown
+        let today = NaiveDate::from_ymd(2021, 3, 1);
+        let forecast = Forecast::new(saved_scrobbles.as_slice(), 90, today, 10, Some(100));
+        assert_eq!(forecast.rate.daily_mean, 0.0);
+        assert_eq!(forecast.scrobbles.expected, None);
+    }
+}
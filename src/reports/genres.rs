@@ -0,0 +1,301 @@
+//! Genre share over time, calculated from cached artist tags and the user's genre rules
+
+use std::collections::HashMap;
+
+use clap::ArgEnum;
+use serde::Serialize;
+
+use crate::models::artist_tags::ArtistTagsCache;
+use crate::models::genre_rules::GenreRules;
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Cell, Report, Section};
+use crate::stats;
+
+/// The genre of artists whose tags have not been looked up, or who have no tags
+pub const UNTAGGED: &str = "Untagged";
+/// The genre of tagged artists that match none of the genre rules
+pub const OTHER: &str = "Other";
+
+/// The length of the periods genre shares are compared over
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interval {
+    Month,
+    Year,
+}
+
+impl Interval {
+    fn key(&self, scrobble: &SavedScrobble) -> String {
+        match self {
+            Interval::Month => scrobble.date().format("%Y-%m").to_string(),
+            Interval::Year => scrobble.date().format("%Y").to_string(),
+        }
+    }
+}
+
+/// The share of scrobbles of each genre, overall and per period
+///
+/// Shares are percentages of every scrobble, so scrobbles of untagged artists are shown as their
+/// own genre instead of inflating the others.
+#[derive(Serialize)]
+pub struct GenreShares {
+    pub interval: Interval,
+    pub total_scrobbles: i32,
+    /// Scrobbles of artists that have tags
+    pub tagged_scrobbles: i32,
+
+    /// Every genre over the whole history, most played first
+    pub genres: Vec<GenreShare>,
+    /// The share of the top genres in each period, oldest first. The remaining genres are
+    /// combined into "Other".
+    pub periods: Vec<PeriodShares>,
+    /// How the share of each top genre changed from the second to last period to the last one,
+    /// biggest change first
+    pub changes: Vec<GenreChange>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GenreShare {
+    pub genre: String,
+    pub scrobbles: i32,
+    /// The percentage of scrobbles, from 0 to 100
+    pub share: f64,
+}
+
+#[derive(Serialize)]
+pub struct PeriodShares {
+    pub period: String,
+    pub scrobbles: i32,
+    pub genres: Vec<GenreShare>,
+}
+
+#[derive(Serialize)]
+pub struct GenreChange {
+    pub genre: String,
+    pub from_period: String,
+    pub from_share: f64,
+    pub to_period: String,
+    pub to_share: f64,
+    /// The difference in percentage points
+    pub change: f64,
+}
+
+impl GenreShares {
+    /// Calculates genre shares of a history, showing the `top` most played genres in each period
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        tags: &ArtistTagsCache,
+        rules: &GenreRules,
+        interval: Interval,
+        top: usize,
+    ) -> Self {
+        let genre_of = |scrobble: &SavedScrobble| match tags.get(&scrobble.artist) {
+            Some(artist_tags) if !artist_tags.tags.is_empty() => {
+                rules.genre_of(artist_tags).unwrap_or(OTHER).to_string()
+            }
+            _ => UNTAGGED.to_string(),
+        };
+
+        let genres = stats::count_by(scrobbles, genre_of);
+        let genres = shares(&genres, scrobbles.len() as i32);
+
+        let top_genres = genres
+            .iter()
+            .map(|g| g.genre.as_str())
+            .filter(|genre| *genre != OTHER && *genre != UNTAGGED)
+            .take(top)
+            .collect::<Vec<&str>>();
+
+        let mut by_period: HashMap<String, HashMap<String, i32>> = HashMap::new();
+        for scrobble in scrobbles {
+            let genre = genre_of(scrobble);
+            let genre = if genre == UNTAGGED || top_genres.contains(&genre.as_str()) {
+                genre
+            } else {
+                OTHER.to_string()
+            };
+
+            *by_period
+                .entry(interval.key(scrobble))
+                .or_default()
+                .entry(genre)
+                .or_insert(0) += 1;
+        }
+
+        let mut periods = by_period
+            .into_iter()
+            .map(|(period, counts)| {
+                let total = counts.values().sum();
+                let shares = shares(&counts, total);
+
+                // Every period lists the same genres in the same order, so periods can be compared
+                let genres = top_genres
+                    .iter()
+                    .chain([OTHER, UNTAGGED].iter())
+                    .map(|genre| {
+                        shares
+                            .iter()
+                            .find(|s| s.genre == *genre)
+                            .cloned()
+                            .unwrap_or(GenreShare {
+                                genre: genre.to_string(),
+                                scrobbles: 0,
+                                share: 0.0,
+                            })
+                    })
+                    .collect();
+
+                PeriodShares {
+                    period,
+                    scrobbles: total,
+                    genres,
+                }
+            })
+            .collect::<Vec<PeriodShares>>();
+        periods.sort_by(|a, b| a.period.cmp(&b.period));
+
+        let changes = calculate_changes(&periods, top_genres.len());
+
+        let untagged = genres
+            .iter()
+            .find(|g| g.genre == UNTAGGED)
+            .map_or(0, |g| g.scrobbles);
+
+        Self {
+            interval,
+            total_scrobbles: scrobbles.len() as i32,
+            tagged_scrobbles: scrobbles.len() as i32 - untagged,
+
+            genres,
+            periods,
+            changes,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for GenreShares {
+    fn sections(&self) -> Vec<Section> {
+        let interval = match self.interval {
+            Interval::Month => "Month",
+            Interval::Year => "Year",
+        };
+
+        let period_genres: Vec<&str> = self
+            .periods
+            .first()
+            .map(|p| p.genres.iter().map(|g| g.genre.as_str()).collect())
+            .unwrap_or_default();
+        let mut period_headers = vec![interval];
+        period_headers.extend(period_genres);
+
+        let coverage = self.tagged_scrobbles as f64 / self.total_scrobbles.max(1) as f64 * 100.0;
+
+        let mut sections = vec![
+            Section::key_values(
+                "Genres",
+                vec![
+                    ("Scrobbles", self.total_scrobbles.into()),
+                    ("Tagged Scrobbles", self.tagged_scrobbles.into()),
+                    ("Coverage (%)", coverage.into()),
+                ],
+            ),
+            Section::table(
+                "Genre Share",
+                &["Genre", "Scrobbles", "Share (%)"],
+                self.genres
+                    .iter()
+                    .map(|g| vec![g.genre.as_str().into(), g.scrobbles.into(), g.share.into()])
+                    .collect(),
+            ),
+            Section::table(
+                &format!("Genre Share (%) By {}", interval),
+                &period_headers,
+                self.periods
+                    .iter()
+                    .map(|p| {
+                        let mut row = vec![Cell::from(p.period.as_str())];
+                        row.extend(p.genres.iter().map(|g| Cell::from(g.share)));
+                        row
+                    })
+                    .collect(),
+            ),
+        ];
+
+        if let Some(change) = self.changes.first() {
+            sections.push(Section::table(
+                &format!("Changes In {}", change.to_period),
+                &[
+                    "Genre",
+                    &format!("{} (%)", change.from_period),
+                    &format!("{} (%)", change.to_period),
+                    "Change (Points)",
+                ],
+                self.changes
+                    .iter()
+                    .map(|c| {
+                        vec![
+                            c.genre.as_str().into(),
+                            c.from_share.into(),
+                            c.to_share.into(),
+                            c.change.into(),
+                        ]
+                    })
+                    .collect(),
+            ));
+        }
+
+        sections
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// Shares of `total` for each genre, most played first
+fn shares(counts: &HashMap<String, i32>, total: i32) -> Vec<GenreShare> {
+    stats::top_n(counts, counts.len())
+        .into_iter()
+        .map(|item| GenreShare {
+            genre: item.name,
+            scrobbles: item.scrobbles,
+            share: item.scrobbles as f64 / total.max(1) as f64 * 100.0,
+        })
+        .collect()
+}
+
+/// Compares the share of the first `top` genres of the last two periods
+fn calculate_changes(periods: &[PeriodShares], top: usize) -> Vec<GenreChange> {
+    let (from, to) = match periods {
+        [.., from, to] => (from, to),
+        _ => return Vec::new(),
+    };
+
+    let mut changes = from
+        .genres
+        .iter()
+        .zip(to.genres.iter())
+        .take(top)
+        .map(|(a, b)| GenreChange {
+            genre: a.genre.to_string(),
+            from_period: from.period.to_string(),
+            from_share: a.share,
+            to_period: to.period.to_string(),
+            to_share: b.share,
+            change: b.share - a.share,
+        })
+        .collect::<Vec<GenreChange>>();
+
+    changes.sort_by(|a, b| {
+        b.change
+            .abs()
+            .partial_cmp(&a.change.abs())
+            .unwrap()
+            .then(a.genre.cmp(&b.genre))
+    });
+
+    changes
+}
@@ -0,0 +1,201 @@
+//! Time spent listening, calculated from the durations looked up with `enrich durations`
+
+use std::collections::HashMap;
+
+use clap::ArgEnum;
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::models::track_durations::TrackDurations;
+use crate::output::{Report, Section};
+
+const MS_PER_HOUR: f64 = 3_600_000.0;
+
+/// How listening time is grouped
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeGrouping {
+    Day,
+    Month,
+    Year,
+    Artist,
+    Album,
+}
+
+impl TimeGrouping {
+    fn key(&self, scrobble: &SavedScrobble) -> String {
+        match self {
+            TimeGrouping::Day => scrobble.date().format("%Y-%m-%d").to_string(),
+            TimeGrouping::Month => scrobble.date().format("%Y-%m").to_string(),
+            TimeGrouping::Year => scrobble.date().format("%Y").to_string(),
+            TimeGrouping::Artist => scrobble.artist.to_string(),
+            TimeGrouping::Album if scrobble.album.is_empty() => {
+                format!("{} - (No Album)", scrobble.artist)
+            }
+            TimeGrouping::Album => scrobble.group_artist_album(),
+        }
+    }
+
+    fn is_chronological(&self) -> bool {
+        matches!(
+            self,
+            TimeGrouping::Day | TimeGrouping::Month | TimeGrouping::Year
+        )
+    }
+}
+
+/// Hours listened, counting only scrobbles whose track duration is known
+///
+/// Scrobbles with an unknown duration are never estimated. They are counted separately, so the
+/// totals can be judged by how much of the history they actually cover.
+#[derive(Serialize)]
+pub struct ListeningTime {
+    pub group_by: TimeGrouping,
+
+    pub total_scrobbles: i32,
+    pub scrobbles_with_duration: i32,
+    pub scrobbles_without_duration: i32,
+    /// The share of scrobbles with a known duration, from 0 to 1
+    pub coverage: f64,
+    pub hours: f64,
+
+    /// Newest first when grouped by date, otherwise most listened first
+    pub groups: Vec<ListeningTimeGroup>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Serialize)]
+pub struct ListeningTimeGroup {
+    pub name: String,
+    pub scrobbles: i32,
+    pub scrobbles_without_duration: i32,
+    pub hours: f64,
+}
+
+impl ListeningTime {
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        durations: &TrackDurations,
+        group_by: TimeGrouping,
+        limit: usize,
+    ) -> Self {
+        let mut groups: HashMap<String, ListeningTimeGroup> = HashMap::new();
+
+        for scrobble in scrobbles {
+            let name = group_by.key(scrobble);
+            let group = groups
+                .entry(name.to_string())
+                .or_insert(ListeningTimeGroup {
+                    name,
+                    scrobbles: 0,
+                    scrobbles_without_duration: 0,
+                    hours: 0.0,
+                });
+
+            group.scrobbles += 1;
+            match durations.duration_ms(scrobble) {
+                Some(duration) => group.hours += duration as f64 / MS_PER_HOUR,
+                None => group.scrobbles_without_duration += 1,
+            }
+        }
+
+        let mut groups = groups.into_iter().map(|g| g.1).collect::<Vec<_>>();
+        if group_by.is_chronological() {
+            groups.sort_by(|a, b| b.name.cmp(&a.name));
+        } else {
+            groups.sort_by(|a, b| {
+                b.hours
+                    .partial_cmp(&a.hours)
+                    .unwrap()
+                    .then(b.scrobbles.cmp(&a.scrobbles))
+                    .then(a.name.cmp(&b.name))
+            });
+        }
+
+        let total = scrobbles.len() as i32;
+        let without_duration = groups.iter().map(|g| g.scrobbles_without_duration).sum();
+        let hours = groups.iter().map(|g| g.hours).sum();
+        groups.truncate(limit);
+
+        Self {
+            group_by,
+            total_scrobbles: total,
+            scrobbles_with_duration: total - without_duration,
+            scrobbles_without_duration: without_duration,
+            coverage: if total > 0 {
+                (total - without_duration) as f64 / total as f64
+            } else {
+                0.0
+            },
+            hours,
+            groups,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for ListeningTime {
+    fn sections(&self) -> Vec<Section> {
+        let name = match self.group_by {
+            TimeGrouping::Day => "Day",
+            TimeGrouping::Month => "Month",
+            TimeGrouping::Year => "Year",
+            TimeGrouping::Artist => "Artist",
+            TimeGrouping::Album => "Album",
+        };
+
+        vec![
+            Section::key_values(
+                "Listening Time",
+                vec![
+                    ("Hours Listened", self.hours.into()),
+                    ("Scrobbles", self.total_scrobbles.into()),
+                    (
+                        "Scrobbles With Duration",
+                        self.scrobbles_with_duration.into(),
+                    ),
+                    (
+                        "Scrobbles Without Duration",
+                        self.scrobbles_without_duration.into(),
+                    ),
+                    ("Coverage (%)", (self.coverage * 100.0).into()),
+                ],
+            ),
+            Section::table(
+                &format!("Hours By {}", name),
+                &[name, "Hours", "Scrobbles", "Unknown Duration"],
+                self.groups
+                    .iter()
+                    .map(|g| {
+                        vec![
+                            g.name.as_str().into(),
+                            g.hours.into(),
+                            g.scrobbles.into(),
+                            g.scrobbles_without_duration.into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// Total minutes listened, or `None` if none of the scrobbles have a known duration
+pub fn minutes_listened(scrobbles: &[SavedScrobble], durations: &TrackDurations) -> Option<i64> {
+    let known = scrobbles
+        .iter()
+        .filter_map(|s| durations.duration_ms(s))
+        .map(|duration| duration as i64)
+        .collect::<Vec<i64>>();
+
+    if known.is_empty() {
+        return None;
+    }
+
+    Some(known.iter().sum::<i64>() / 60_000)
+}
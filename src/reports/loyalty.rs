@@ -0,0 +1,265 @@
+//! Artist lifecycles: when each artist was first and last played, and how loyally in between
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Report, Section};
+
+/// Artists played in at least this share of the months since their first scrobble are core
+const CORE_MONTH_SHARE: f64 = 0.5;
+/// Core artists need at least this many months with plays
+const CORE_MIN_MONTHS: i32 = 3;
+/// Seasonal artists play mostly within this many calendar months of the year...
+const SEASON_MONTHS: usize = 3;
+/// ...accounting for at least this share of their plays
+const SEASON_PLAY_SHARE: f64 = 0.75;
+
+/// How an artist has been listened to over time
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Loyalty {
+    /// Played in most months since the first scrobble
+    Core,
+    /// Played across several years, but mostly in the same few months of the year
+    Seasonal,
+    /// Played on and off
+    Occasional,
+    /// Only ever played within a single month
+    OneOff,
+    /// Played heavily, but not for a long time
+    Forgotten,
+}
+
+impl Loyalty {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Loyalty::Core => "Core",
+            Loyalty::Seasonal => "Seasonal",
+            Loyalty::Occasional => "Occasional",
+            Loyalty::OneOff => "One-Off",
+            Loyalty::Forgotten => "Forgotten",
+        }
+    }
+}
+
+/// What makes an artist a forgotten favourite
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ForgottenThresholds {
+    /// The fewest plays of a favourite
+    pub min_plays: i32,
+    /// Months without a play, up to the newest scrobble, after which a favourite is forgotten
+    pub months: i32,
+}
+
+impl Default for ForgottenThresholds {
+    fn default() -> Self {
+        Self {
+            min_plays: 50,
+            months: 12,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ArtistLifecycle {
+    pub artist: String,
+    pub scrobbles: i32,
+    pub first_scrobble: NaiveDate,
+    pub last_scrobble: NaiveDate,
+    /// Days from the first to the last scrobble
+    pub active_span_days: i64,
+    /// Distinct months with at least one play
+    pub active_months: i32,
+    pub loyalty: Loyalty,
+}
+
+#[derive(Serialize)]
+pub struct LoyaltyCount {
+    pub loyalty: Loyalty,
+    pub artists: i32,
+}
+
+/// The lifecycle of every artist in a history
+#[derive(Serialize)]
+pub struct ArtistLoyalty {
+    pub forgotten: ForgottenThresholds,
+    /// The number of artists in each class
+    pub classes: Vec<LoyaltyCount>,
+    /// Forgotten favourites, most played first
+    pub forgotten_favourites: Vec<ArtistLifecycle>,
+    /// The most played artists
+    pub artists: Vec<ArtistLifecycle>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+impl ArtistLoyalty {
+    /// Classifies every artist in a history, listing the `limit` most played artists
+    pub fn new(scrobbles: &[SavedScrobble], forgotten: ForgottenThresholds, limit: usize) -> Self {
+        let mut by_artist: HashMap<&str, Vec<NaiveDate>> = HashMap::new();
+        for scrobble in scrobbles {
+            by_artist
+                .entry(scrobble.artist.as_str())
+                .or_default()
+                .push(scrobble.date());
+        }
+
+        let mut lifecycles = match scrobbles.first() {
+            Some(newest) => by_artist
+                .into_iter()
+                .map(|(artist, dates)| lifecycle(artist, &dates, newest.date(), forgotten))
+                .collect::<Vec<ArtistLifecycle>>(),
+            None => Vec::new(),
+        };
+        lifecycles.sort_by(|a, b| b.scrobbles.cmp(&a.scrobbles).then(a.artist.cmp(&b.artist)));
+
+        let classes = [
+            Loyalty::Core,
+            Loyalty::Seasonal,
+            Loyalty::Occasional,
+            Loyalty::OneOff,
+            Loyalty::Forgotten,
+        ]
+        .iter()
+        .map(|loyalty| {
+            let artists = lifecycles.iter().filter(|l| l.loyalty == *loyalty).count();
+            LoyaltyCount {
+                loyalty: *loyalty,
+                artists: artists as i32,
+            }
+        })
+        .collect();
+
+        let forgotten_favourites = lifecycles
+            .iter()
+            .filter(|l| l.loyalty == Loyalty::Forgotten)
+            .cloned()
+            .collect();
+        lifecycles.truncate(limit);
+
+        Self {
+            forgotten,
+            classes,
+            forgotten_favourites,
+            artists: lifecycles,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for ArtistLoyalty {
+    fn sections(&self) -> Vec<Section> {
+        let headers = [
+            "Artist",
+            "Scrobbles",
+            "First Scrobble",
+            "Last Scrobble",
+            "Span (Days)",
+            "Active Months",
+            "Loyalty",
+        ];
+        let rows = |artists: &[ArtistLifecycle]| {
+            artists
+                .iter()
+                .map(|a| {
+                    vec![
+                        a.artist.as_str().into(),
+                        a.scrobbles.into(),
+                        a.first_scrobble.to_string().into(),
+                        a.last_scrobble.to_string().into(),
+                        a.active_span_days.into(),
+                        a.active_months.into(),
+                        a.loyalty.as_str().into(),
+                    ]
+                })
+                .collect()
+        };
+
+        vec![
+            Section::key_values(
+                "Artist Loyalty",
+                self.classes
+                    .iter()
+                    .map(|c| (c.loyalty.as_str(), c.artists.into()))
+                    .collect(),
+            ),
+            Section::table(
+                "Forgotten Favourites",
+                &headers,
+                rows(&self.forgotten_favourites),
+            ),
+            Section::table("Artists", &headers, rows(&self.artists)),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// Builds the lifecycle of an artist from the dates of its scrobbles
+fn lifecycle(
+    artist: &str,
+    dates: &[NaiveDate],
+    newest: NaiveDate,
+    forgotten: ForgottenThresholds,
+) -> ArtistLifecycle {
+    let first = *dates.iter().min().unwrap();
+    let last = *dates.iter().max().unwrap();
+    let scrobbles = dates.len() as i32;
+
+    let active_months = dates
+        .iter()
+        .map(|d| (d.year(), d.month()))
+        .collect::<HashSet<(i32, u32)>>()
+        .len() as i32;
+    let years = dates
+        .iter()
+        .map(|d| d.year())
+        .collect::<HashSet<i32>>()
+        .len();
+
+    let loyalty =
+        if scrobbles >= forgotten.min_plays && months_between(last, newest) >= forgotten.months {
+            Loyalty::Forgotten
+        } else if active_months == 1 {
+            Loyalty::OneOff
+        } else if active_months >= CORE_MIN_MONTHS
+            && active_months as f64 >= (months_between(first, newest) + 1) as f64 * CORE_MONTH_SHARE
+        {
+            Loyalty::Core
+        } else if years > 1 && season_share(dates) >= SEASON_PLAY_SHARE {
+            Loyalty::Seasonal
+        } else {
+            Loyalty::Occasional
+        };
+
+    ArtistLifecycle {
+        artist: artist.to_string(),
+        scrobbles,
+        first_scrobble: first,
+        last_scrobble: last,
+        active_span_days: (last - first).num_days(),
+        active_months,
+        loyalty,
+    }
+}
+
+/// The number of calendar months from `from` to `to`, ignoring the days
+fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32
+}
+
+/// The share of plays within the most played calendar months of the year
+fn season_share(dates: &[NaiveDate]) -> f64 {
+    let mut months = [0; 12];
+    for date in dates {
+        months[date.month0() as usize] += 1;
+    }
+    months.sort_unstable_by(|a, b| b.cmp(a));
+
+    months.iter().take(SEASON_MONTHS).sum::<i32>() as f64 / dates.len() as f64
+}
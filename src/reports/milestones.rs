@@ -0,0 +1,203 @@
+//! Milestone scrobbles, such as the 1st, 1,000th, and 10,000th tracks ever scrobbled
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Cell, Report, Section};
+
+/// The play counts that make an artist milestone
+pub const ARTIST_THRESHOLDS: [i32; 2] = [100, 1000];
+
+/// Scrobbles that mark a milestone, in chronological order
+#[derive(Serialize)]
+pub struct Milestones {
+    pub total_scrobbles: i32,
+    pub milestones: Vec<Milestone>,
+    /// Artists whose play count passed one of the [ARTIST_THRESHOLDS](constant.ARTIST_THRESHOLDS.html)
+    pub artist_milestones: Vec<ArtistMilestone>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+/// The scrobble with a given number, where the first scrobble ever is number 1
+#[derive(Serialize)]
+pub struct Milestone {
+    pub number: i32,
+    #[serde(flatten)]
+    pub scrobble: SavedScrobble,
+}
+
+#[derive(Serialize)]
+pub struct ArtistMilestone {
+    pub artist: String,
+    pub threshold: i32,
+    /// The artist's total scrobbles, which is at least `threshold`
+    pub total_scrobbles: i32,
+    /// The very first scrobble of the artist
+    pub first_scrobble: SavedScrobble,
+    /// The scrobble that brought the artist's play count to `threshold`
+    pub reached_at: SavedScrobble,
+}
+
+impl Milestones {
+    /// Finds milestones in a history sorted from newest to oldest
+    ///
+    /// Without `every`, the milestones are the 1st, 1,000th, and every 10,000th scrobble.
+    /// Otherwise they are the 1st and every `every`th scrobble.
+    pub fn new(scrobbles: &[SavedScrobble], every: Option<i32>) -> Self {
+        let total = scrobbles.len() as i32;
+
+        let mut numbers = vec![1];
+        match every {
+            Some(every) if every > 0 => numbers.extend((every..=total).step_by(every as usize)),
+            _ => {
+                numbers.push(1000);
+                numbers.extend((10_000..=total).step_by(10_000));
+            }
+        }
+        numbers.dedup();
+
+        let milestones = numbers
+            .into_iter()
+            .filter_map(|number| {
+                find_by_number(scrobbles, number).map(|scrobble| Milestone {
+                    number,
+                    scrobble: scrobble.clone(),
+                })
+            })
+            .collect();
+
+        Self {
+            total_scrobbles: total,
+            milestones,
+            artist_milestones: calculate_artist_milestones(scrobbles),
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for Milestones {
+    fn sections(&self) -> Vec<Section> {
+        vec![
+            Section::table(
+                "Milestones",
+                &["#", "Date", "Track", "Artist", "Album"],
+                self.milestones
+                    .iter()
+                    .map(|m| {
+                        vec![
+                            m.number.into(),
+                            m.scrobble.date().to_string().into(),
+                            m.scrobble.title.as_str().into(),
+                            m.scrobble.artist.as_str().into(),
+                            m.scrobble.album.as_str().into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+            Section::table(
+                "Artist Milestones",
+                &[
+                    "Artist",
+                    "Plays",
+                    "Reached",
+                    "Total Plays",
+                    "First Scrobbled",
+                    "First Track",
+                ],
+                self.artist_milestones
+                    .iter()
+                    .map(|m| {
+                        vec![
+                            m.artist.as_str().into(),
+                            m.threshold.into(),
+                            m.reached_at.date().to_string().into(),
+                            m.total_scrobbles.into(),
+                            m.first_scrobble.date().to_string().into(),
+                            m.first_scrobble.title.as_str().into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// A single scrobble looked up by its number
+#[derive(Serialize)]
+pub struct ScrobbleNumber {
+    pub number: i32,
+    pub total_scrobbles: i32,
+    pub scrobble: Option<SavedScrobble>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+impl Report for ScrobbleNumber {
+    fn sections(&self) -> Vec<Section> {
+        let scrobble = self.scrobble.as_ref();
+
+        vec![Section::key_values(
+            &format!("Scrobble #{}", self.number),
+            vec![
+                (
+                    "Date",
+                    Cell::from(scrobble.map(|s| s.datetime_local.to_rfc2822())),
+                ),
+                ("Track", scrobble.map(|s| s.title.as_str()).into()),
+                ("Artist", scrobble.map(|s| s.artist.as_str()).into()),
+                ("Album", scrobble.map(|s| s.album.as_str()).into()),
+                ("Total Scrobbles", self.total_scrobbles.into()),
+            ],
+        )]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// Finds the `number`th scrobble in chronological order in a history sorted from newest to oldest
+pub fn find_by_number(scrobbles: &[SavedScrobble], number: i32) -> Option<&SavedScrobble> {
+    if number <= 0 || number as usize > scrobbles.len() {
+        return None;
+    }
+
+    scrobbles.get(scrobbles.len() - number as usize)
+}
+
+fn calculate_artist_milestones(scrobbles: &[SavedScrobble]) -> Vec<ArtistMilestone> {
+    let mut plays: HashMap<&str, i32> = HashMap::new();
+    let mut first_scrobbles: HashMap<&str, &SavedScrobble> = HashMap::new();
+    let mut reached: Vec<(&str, i32, &SavedScrobble)> = Vec::new();
+
+    // Walk the history from oldest to newest so the play counts grow chronologically
+    for scrobble in scrobbles.iter().rev() {
+        let artist = scrobble.artist.as_str();
+        first_scrobbles.entry(artist).or_insert(scrobble);
+
+        let count = plays.entry(artist).or_insert(0);
+        *count += 1;
+
+        if ARTIST_THRESHOLDS.contains(count) {
+            reached.push((artist, *count, scrobble));
+        }
+    }
+
+    reached
+        .into_iter()
+        .map(|(artist, threshold, reached_at)| ArtistMilestone {
+            artist: artist.to_string(),
+            threshold,
+            total_scrobbles: plays[artist],
+            first_scrobble: first_scrobbles[artist].clone(),
+            reached_at: reached_at.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,44 @@
+//! Reports generated entirely from saved scrobbles, without calling the Last.fm API
+
+use clap::ArgEnum;
+
+pub mod anomalies;
+pub mod binges;
+pub mod chart_race;
+pub mod charts;
+pub mod cleanup;
+pub mod compare;
+pub mod diversity;
+pub mod forecast;
+pub mod genres;
+pub mod listening_time;
+pub mod loyalty;
+pub mod milestones;
+pub mod on_this_day;
+pub mod query_results;
+pub mod search;
+pub mod sessions;
+pub mod year_review;
+
+/// The document formats a standalone report can be written in
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DocumentFormat {
+    /// A single HTML file with inline styles and no external resources
+    Html,
+    /// GitHub flavored Markdown
+    Markdown,
+}
+
+/// Escapes text so it can be safely included in an HTML document
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Escapes text so it can be safely included in a Markdown table cell
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
@@ -0,0 +1,180 @@
+//! What was scrobbled on the same calendar day in previous years
+
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use chrono::{Datelike, NaiveDate};
+use serde::{Serialize, Serializer};
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Cell, Report, Section};
+use crate::stats::{self, RankedItem};
+
+/// A calendar day without a year, written as `MM-DD`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MonthDay {
+    pub month: u32,
+    pub day: u32,
+}
+
+impl MonthDay {
+    pub fn from_date(date: NaiveDate) -> Self {
+        Self {
+            month: date.month(),
+            day: date.day(),
+        }
+    }
+
+    /// The day in `year`, or `None` for February 29th outside leap years
+    pub fn in_year(&self, year: i32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, self.month, self.day)
+    }
+}
+
+impl FromStr for MonthDay {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (month, day) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Expected a date in MM-DD format, e.g. 03-21"))?;
+        let month_day = Self {
+            month: month.trim().parse()?,
+            day: day.trim().parse()?,
+        };
+
+        // 2000 is a leap year, so February 29th is accepted
+        match month_day.in_year(2000) {
+            Some(_) => Ok(month_day),
+            None => Err(anyhow!("{} is not a valid day of the year", s)),
+        }
+    }
+}
+
+impl fmt::Display for MonthDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}-{:02}", self.month, self.day)
+    }
+}
+
+impl Serialize for MonthDay {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// The scrobbles of one calendar day in each previous year
+#[derive(Serialize)]
+pub struct OnThisDay {
+    pub date: MonthDay,
+    pub total_scrobbles: i32,
+    /// Newest first, leaving out years without scrobbles on the day
+    pub years: Vec<YearOnThisDay>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Serialize)]
+pub struct YearOnThisDay {
+    pub year: i32,
+    pub scrobbles: i32,
+    /// The earliest scrobble of the day
+    pub first_scrobble: SavedScrobble,
+    /// Named "Title - Artist"
+    pub top_tracks: Vec<RankedItem>,
+    pub top_artists: Vec<RankedItem>,
+}
+
+impl OnThisDay {
+    /// Finds the scrobbles of `date` in every year before `before_year`, listing the `top` tracks
+    /// and artists of each year
+    pub fn new(scrobbles: &[SavedScrobble], date: MonthDay, before_year: i32, top: usize) -> Self {
+        let on_day = scrobbles
+            .iter()
+            .filter(|s| MonthDay::from_date(s.date()) == date && s.date().year() < before_year)
+            .cloned()
+            .collect::<Vec<SavedScrobble>>();
+
+        let mut years = stats::group_by(&on_day, |s| s.date().year())
+            .into_iter()
+            .map(|(year, scrobbles)| YearOnThisDay {
+                year,
+                scrobbles: scrobbles.len() as i32,
+                // Scrobbles are sorted from newest to oldest
+                first_scrobble: scrobbles.last().unwrap().clone(),
+                top_tracks: stats::top_n(
+                    &stats::count_by(&scrobbles, |s| s.group_song_artist()),
+                    top,
+                ),
+                top_artists: stats::top_n(
+                    &stats::count_by(&scrobbles, |s| s.artist.to_string()),
+                    top,
+                ),
+            })
+            .collect::<Vec<YearOnThisDay>>();
+        years.sort_by_key(|y| Reverse(y.year));
+
+        Self {
+            date,
+            total_scrobbles: on_day.len() as i32,
+            years,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for OnThisDay {
+    fn sections(&self) -> Vec<Section> {
+        let mut sections = vec![Section::table(
+            &format!("On This Day ({})", self.date),
+            &[
+                "Year",
+                "Scrobbles",
+                "First Scrobble",
+                "First Track",
+                "Top Artist",
+            ],
+            self.years
+                .iter()
+                .map(|y| {
+                    vec![
+                        y.year.to_string().into(),
+                        y.scrobbles.into(),
+                        y.first_scrobble.time().format("%H:%M").to_string().into(),
+                        y.first_scrobble.song_artist().into(),
+                        y.top_artists.first().map(|a| a.name.as_str()).into(),
+                    ]
+                })
+                .collect(),
+        )];
+
+        for year in &self.years {
+            let rows = year.top_tracks.len().max(year.top_artists.len());
+            sections.push(Section::table(
+                &year.year.to_string(),
+                &["#", "Track", "Plays", "Artist", "Plays"],
+                (0..rows)
+                    .map(|i| {
+                        let track = year.top_tracks.get(i);
+                        let artist = year.top_artists.get(i);
+                        vec![
+                            Cell::from(i + 1),
+                            track.map(|t| t.name.as_str()).into(),
+                            track.map(|t| t.scrobbles).into(),
+                            artist.map(|a| a.name.as_str()).into(),
+                            artist.map(|a| a.scrobbles).into(),
+                        ]
+                    })
+                    .collect(),
+            ));
+        }
+
+        sections
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
@@ -0,0 +1,206 @@
+//! Lists, counts and groups of the scrobbles matched by the `query` command
+
+use std::cmp::Reverse;
+
+use clap::ArgEnum;
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Cell, Report, Section};
+use crate::stats::{self, RankedItem};
+
+/// How listed scrobbles are sorted
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SortBy {
+    /// Newest first
+    Date,
+    /// Alphabetically, then newest first
+    Artist,
+    /// Alphabetically, then newest first
+    Album,
+    /// Alphabetically, then newest first
+    Track,
+}
+
+/// How scrobbles are counted
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GroupBy {
+    Artist,
+    Album,
+    Track,
+    Day,
+    Month,
+}
+
+impl GroupBy {
+    fn key(&self, scrobble: &SavedScrobble) -> String {
+        match self {
+            GroupBy::Artist => scrobble.artist.to_string(),
+            GroupBy::Album if scrobble.album.is_empty() => {
+                format!("{} - (No Album)", scrobble.artist)
+            }
+            GroupBy::Album => scrobble.group_artist_album(),
+            GroupBy::Track => scrobble.group_song_artist(),
+            GroupBy::Day => scrobble.date().format("%Y-%m-%d").to_string(),
+            GroupBy::Month => scrobble.date().format("%Y-%m").to_string(),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            GroupBy::Artist => "Artist",
+            GroupBy::Album => "Album",
+            GroupBy::Track => "Track",
+            GroupBy::Day => "Day",
+            GroupBy::Month => "Month",
+        }
+    }
+}
+
+/// Matching scrobbles, serialized as a plain list
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct ScrobbleList {
+    pub scrobbles: Vec<SavedScrobble>,
+    /// The number of scrobbles left out by the exclusion rules, which is not serialized so the
+    /// list keeps its shape
+    #[serde(skip)]
+    pub excluded_scrobbles: i32,
+}
+
+impl ScrobbleList {
+    /// Sorts scrobbles, and keeps the first `limit` of them
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        sort: SortBy,
+        reverse: bool,
+        limit: Option<usize>,
+    ) -> Self {
+        let mut scrobbles = scrobbles.to_vec();
+
+        // Scrobbles are already sorted from newest to oldest, and sorting is stable
+        match sort {
+            SortBy::Date => {}
+            SortBy::Artist => scrobbles.sort_by_key(|s| s.artist.to_lowercase()),
+            SortBy::Album => scrobbles.sort_by_key(|s| s.album.to_lowercase()),
+            SortBy::Track => scrobbles.sort_by_key(|s| s.title.to_lowercase()),
+        }
+        if reverse {
+            scrobbles.reverse();
+        }
+        if let Some(limit) = limit {
+            scrobbles.truncate(limit);
+        }
+
+        Self {
+            scrobbles,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for ScrobbleList {
+    fn sections(&self) -> Vec<Section> {
+        vec![Section::table(
+            "Scrobbles",
+            &["Date", "Track", "Artist", "Album", "Loved"],
+            self.scrobbles
+                .iter()
+                .map(|s| {
+                    vec![
+                        Cell::from(s.datetime_local.format("%Y-%m-%d %H:%M").to_string()),
+                        s.title.as_str().into(),
+                        s.artist.as_str().into(),
+                        s.album.as_str().into(),
+                        if s.loved { "Yes" } else { "" }.into(),
+                    ]
+                })
+                .collect(),
+        )]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// The number of matching scrobbles
+#[derive(Serialize)]
+pub struct ScrobbleCount {
+    pub scrobbles: i32,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+impl Report for ScrobbleCount {
+    fn sections(&self) -> Vec<Section> {
+        vec![Section::key_values(
+            "Count",
+            vec![("Scrobbles", self.scrobbles.into())],
+        )]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// The number of matching scrobbles in each group, serialized as a plain list
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct GroupCounts {
+    #[serde(skip)]
+    pub group_by: GroupBy,
+    pub groups: Vec<RankedItem>,
+    /// The number of scrobbles left out by the exclusion rules, which is not serialized so the
+    /// list keeps its shape
+    #[serde(skip)]
+    pub excluded_scrobbles: i32,
+}
+
+impl GroupCounts {
+    /// Counts scrobbles in each group, newest first when grouped by date and otherwise most
+    /// scrobbled first, keeping the first `limit` groups
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        group_by: GroupBy,
+        reverse: bool,
+        limit: Option<usize>,
+    ) -> Self {
+        let counts = stats::count_by(scrobbles, |s| group_by.key(s));
+        let mut groups = stats::top_n(&counts, counts.len());
+
+        if matches!(group_by, GroupBy::Day | GroupBy::Month) {
+            groups.sort_by_key(|g| Reverse(g.name.to_string()));
+        }
+        if reverse {
+            groups.reverse();
+        }
+        if let Some(limit) = limit {
+            groups.truncate(limit);
+        }
+
+        Self {
+            group_by,
+            groups,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for GroupCounts {
+    fn sections(&self) -> Vec<Section> {
+        vec![Section::table(
+            &format!("Scrobbles By {}", self.group_by.as_str()),
+            &[self.group_by.as_str(), "Scrobbles"],
+            self.groups
+                .iter()
+                .map(|g| vec![g.name.as_str().into(), g.scrobbles.into()])
+                .collect(),
+        )]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
@@ -0,0 +1,229 @@
+//! Ranked search of the tracks in a listening history, with prefix matching and corrections for
+//! misspelled words
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Report, Section};
+
+/// The words of a search. Every word must begin a word of the track, artist or album name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchTerms {
+    pub words: Vec<String>,
+}
+
+impl SearchTerms {
+    /// Splits a search into lowercase words without diacritics, the same way the Sqlite index
+    /// splits names
+    pub fn parse(search: &str) -> Self {
+        Self {
+            words: words(search),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// The query for the Sqlite full-text index, with each word quoted so it is never read as an
+    /// operator, and marked as a prefix
+    pub fn fts_query(&self) -> String {
+        self.words
+            .iter()
+            .map(|word| format!("\"{}\"*", word))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    pub fn matches(&self, scrobble: &SavedScrobble) -> bool {
+        let names = scrobble_words(scrobble);
+        self.words
+            .iter()
+            .all(|word| names.iter().any(|name| name.starts_with(word.as_str())))
+    }
+
+    /// Replaces words that do not begin any word in `vocabulary` with the closest word that
+    /// does, preferring the most common. Returns `None` when no word needs to or can be corrected.
+    pub fn corrected(&self, vocabulary: &HashMap<String, i64>) -> Option<Self> {
+        let mut changed = false;
+        let mut words = Vec::new();
+
+        for word in &self.words {
+            if vocabulary
+                .keys()
+                .any(|known| known.starts_with(word.as_str()))
+            {
+                words.push(word.to_string());
+                continue;
+            }
+
+            // Every short word is a typo away from many others
+            let max_distance = match word.chars().count() {
+                0..=2 => 0,
+                3..=4 => 1,
+                _ => 2,
+            };
+            let closest = vocabulary
+                .iter()
+                .map(|(known, count)| (edit_distance(word, known), known, count))
+                .filter(|(distance, _, _)| *distance <= max_distance)
+                .min_by_key(|(distance, known, count)| (*distance, Reverse(**count), *known));
+
+            match closest {
+                Some((_, known, _)) => {
+                    words.push(known.to_string());
+                    changed = true;
+                }
+                None => words.push(word.to_string()),
+            }
+        }
+
+        if changed {
+            Some(Self { words })
+        } else {
+            None
+        }
+    }
+}
+
+/// Lowercase runs of letters and digits
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| remove_diacritics(&word.to_lowercase()))
+        .collect()
+}
+
+/// Strips accents and other combining marks, so `Björk` is found by `bjork` as it is in the
+/// Sqlite index
+fn remove_diacritics(word: &str) -> String {
+    word.nfd().filter(|c| !is_combining_mark(*c)).collect()
+}
+
+fn scrobble_words(scrobble: &SavedScrobble) -> Vec<String> {
+    [&scrobble.title, &scrobble.artist, &scrobble.album]
+        .iter()
+        .flat_map(|name| words(name))
+        .collect()
+}
+
+/// The number of inserted, deleted or substituted characters that turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Counts how many scrobbles each word appears in, for correcting searches without the Sqlite
+/// index
+pub fn vocabulary(scrobbles: &[SavedScrobble]) -> HashMap<String, i64> {
+    let mut vocabulary = HashMap::new();
+    for scrobble in scrobbles {
+        for word in scrobble_words(scrobble)
+            .into_iter()
+            .collect::<HashSet<String>>()
+        {
+            *vocabulary.entry(word).or_insert(0) += 1;
+        }
+    }
+
+    vocabulary
+}
+
+/// A track that matched a search
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub track: String,
+    pub artist: String,
+    pub album: String,
+    pub scrobbles: i32,
+    pub last_scrobbled: NaiveDate,
+}
+
+/// The tracks that matched a search, best match first
+#[derive(Serialize)]
+pub struct SearchResults {
+    pub search: String,
+    /// The corrected search, when the original matched nothing
+    pub corrected: Option<String>,
+    pub results: Vec<SearchResult>,
+}
+
+/// Searches saved scrobbles directly, ranking the most scrobbled tracks first
+pub fn search_scrobbles(
+    scrobbles: &[SavedScrobble],
+    terms: &SearchTerms,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let mut tracks: HashMap<(&str, &str, &str), SearchResult> = HashMap::new();
+    for scrobble in scrobbles.iter().filter(|s| terms.matches(s)) {
+        let result = tracks
+            .entry((&scrobble.title, &scrobble.artist, &scrobble.album))
+            .or_insert_with(|| SearchResult {
+                track: scrobble.title.to_string(),
+                artist: scrobble.artist.to_string(),
+                album: scrobble.album.to_string(),
+                scrobbles: 0,
+                last_scrobbled: scrobble.date(),
+            });
+        result.scrobbles += 1;
+        result.last_scrobbled = result.last_scrobbled.max(scrobble.date());
+    }
+
+    let mut results = tracks.into_values().collect::<Vec<SearchResult>>();
+    results.sort_by(|a, b| {
+        b.scrobbles
+            .cmp(&a.scrobbles)
+            .then_with(|| b.last_scrobbled.cmp(&a.last_scrobbled))
+            .then_with(|| a.track.cmp(&b.track))
+    });
+    results.truncate(limit);
+
+    results
+}
+
+impl Report for SearchResults {
+    fn sections(&self) -> Vec<Section> {
+        let mut summary = vec![("Search", self.search.as_str().into())];
+        if let Some(corrected) = &self.corrected {
+            summary.push(("Showing Results For", corrected.as_str().into()));
+        }
+        summary.push(("Tracks", self.results.len().into()));
+
+        vec![
+            Section::key_values("Search", summary),
+            Section::table(
+                "Results",
+                &["Track", "Artist", "Album", "Scrobbles", "Last Scrobbled"],
+                self.results
+                    .iter()
+                    .map(|r| {
+                        vec![
+                            r.track.as_str().into(),
+                            r.artist.as_str().into(),
+                            r.album.as_str().into(),
+                            r.scrobbles.into(),
+                            r.last_scrobbled.to_string().into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
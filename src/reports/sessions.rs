@@ -0,0 +1,292 @@
+//! Splits a listening history into sessions of continuous listening
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Report, Section};
+
+/// A run of scrobbles where each scrobble followed the previous one within the idle gap
+pub struct Session<'a> {
+    /// The session's scrobbles, from oldest to newest
+    pub scrobbles: Vec<&'a SavedScrobble>,
+}
+
+impl<'a> Session<'a> {
+    pub fn start(&self) -> &SavedScrobble {
+        self.scrobbles[0]
+    }
+
+    pub fn end(&self) -> &SavedScrobble {
+        self.scrobbles[self.scrobbles.len() - 1]
+    }
+
+    /// Seconds between the first and last scrobble of the session
+    pub fn span(&self) -> i64 {
+        self.end().timestamp_utc - self.start().timestamp_utc
+    }
+}
+
+/// Groups a history sorted from newest to oldest into sessions, from oldest to newest
+///
+/// A new session begins whenever `idle_gap` seconds or more pass between two scrobbles.
+pub fn detect_sessions(scrobbles: &[SavedScrobble], idle_gap: i64) -> Vec<Session<'_>> {
+    let mut sessions: Vec<Session> = Vec::new();
+
+    for scrobble in scrobbles.iter().rev() {
+        match sessions.last_mut() {
+            Some(session) if scrobble.timestamp_utc - session.end().timestamp_utc < idle_gap => {
+                session.scrobbles.push(scrobble)
+            }
+            _ => sessions.push(Session {
+                scrobbles: vec![scrobble],
+            }),
+        }
+    }
+
+    sessions
+}
+
+/// Statistics about listening sessions
+#[derive(Serialize)]
+pub struct SessionStats {
+    pub idle_gap_minutes: i64,
+    pub sessions: i32,
+
+    /// The median time between two scrobbles within a session, used as the length of a typical track
+    pub typical_track_minutes: f64,
+    /// Session lengths include the estimated length of the final track of each session
+    pub average_length_minutes: f64,
+    pub average_tracks: f64,
+    pub longest_session: Option<SessionSummary>,
+
+    /// The total length of every session, which is a more honest estimate of time spent listening
+    /// than multiplying the number of scrobbles by an average track length
+    pub estimated_listening_minutes: f64,
+
+    /// The number of sessions by how many tracks were scrobbled in them
+    pub tracks_per_session: Vec<SessionBucket>,
+
+    /// Albums with at least `min_album_tracks` different tracks played back-to-back in a session
+    pub full_albums: Vec<FullAlbum>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Serialize)]
+pub struct SessionSummary {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    pub length_minutes: f64,
+    pub tracks: i32,
+}
+
+#[derive(Serialize)]
+pub struct SessionBucket {
+    pub tracks: String,
+    pub sessions: i32,
+}
+
+#[derive(Serialize)]
+pub struct FullAlbum {
+    pub artist: String,
+    pub album: String,
+    pub started: DateTime<FixedOffset>,
+    pub tracks: i32,
+}
+
+const BUCKETS: [(usize, usize, &str); 5] = [
+    (1, 1, "1"),
+    (2, 5, "2-5"),
+    (6, 10, "6-10"),
+    (11, 20, "11-20"),
+    (21, usize::MAX, "21+"),
+];
+
+impl SessionStats {
+    pub fn new(
+        scrobbles: &[SavedScrobble],
+        idle_gap_minutes: i64,
+        min_album_tracks: usize,
+    ) -> Self {
+        let sessions = detect_sessions(scrobbles, idle_gap_minutes * 60);
+
+        let typical_track = typical_track_length(&sessions);
+        let length = |session: &Session| (session.span() as f64 + typical_track) / 60.0;
+
+        let total_minutes: f64 = sessions.iter().map(&length).sum();
+        let count = sessions.len().max(1) as f64;
+
+        let longest_session =
+            sessions
+                .iter()
+                .max_by_key(|s| s.span())
+                .map(|session| SessionSummary {
+                    start: session.start().datetime_local,
+                    end: session.end().datetime_local,
+                    length_minutes: length(session),
+                    tracks: session.scrobbles.len() as i32,
+                });
+
+        let tracks_per_session = BUCKETS
+            .iter()
+            .map(|(min, max, label)| SessionBucket {
+                tracks: label.to_string(),
+                sessions: sessions
+                    .iter()
+                    .filter(|s| s.scrobbles.len() >= *min && s.scrobbles.len() <= *max)
+                    .count() as i32,
+            })
+            .collect();
+
+        let mut full_albums = sessions
+            .iter()
+            .flat_map(|session| find_full_albums(session, min_album_tracks))
+            .collect::<Vec<FullAlbum>>();
+        full_albums.reverse();
+
+        Self {
+            idle_gap_minutes,
+            sessions: sessions.len() as i32,
+
+            typical_track_minutes: typical_track / 60.0,
+            average_length_minutes: total_minutes / count,
+            average_tracks: scrobbles.len() as f64 / count,
+            longest_session,
+
+            estimated_listening_minutes: total_minutes,
+
+            tracks_per_session,
+            full_albums,
+            excluded_scrobbles: 0,
+        }
+    }
+}
+
+impl Report for SessionStats {
+    fn sections(&self) -> Vec<Section> {
+        let longest = self.longest_session.as_ref();
+
+        vec![
+            Section::key_values(
+                "Sessions",
+                vec![
+                    ("Idle Gap (Minutes)", self.idle_gap_minutes.into()),
+                    ("Sessions", self.sessions.into()),
+                    ("Typical Track (Minutes)", self.typical_track_minutes.into()),
+                    (
+                        "Average Length (Minutes)",
+                        self.average_length_minutes.into(),
+                    ),
+                    ("Average Tracks", self.average_tracks.into()),
+                    (
+                        "Longest Session",
+                        longest
+                            .map(|l| {
+                                format!("{} to {}", l.start.format("%F %R"), l.end.format("%F %R"))
+                            })
+                            .into(),
+                    ),
+                    (
+                        "Longest Session (Minutes)",
+                        longest.map(|l| l.length_minutes).into(),
+                    ),
+                    ("Longest Session Tracks", longest.map(|l| l.tracks).into()),
+                    (
+                        "Estimated Hours Listened",
+                        (self.estimated_listening_minutes / 60.0).into(),
+                    ),
+                ],
+            ),
+            Section::table(
+                "Tracks Per Session",
+                &["Tracks", "Sessions"],
+                self.tracks_per_session
+                    .iter()
+                    .map(|b| vec![b.tracks.as_str().into(), b.sessions.into()])
+                    .collect(),
+            ),
+            Section::table(
+                "Full Albums",
+                &["Date", "Artist", "Album", "Tracks"],
+                self.full_albums
+                    .iter()
+                    .map(|a| {
+                        vec![
+                            a.started.format("%F %R").to_string().into(),
+                            a.artist.as_str().into(),
+                            a.album.as_str().into(),
+                            a.tracks.into(),
+                        ]
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
+    }
+}
+
+/// The median number of seconds between consecutive scrobbles within a session
+fn typical_track_length(sessions: &[Session]) -> f64 {
+    let mut gaps = sessions
+        .iter()
+        .flat_map(|session| {
+            session
+                .scrobbles
+                .windows(2)
+                .map(|pair| pair[1].timestamp_utc - pair[0].timestamp_utc)
+        })
+        .collect::<Vec<i64>>();
+
+    if gaps.is_empty() {
+        return 0.0;
+    }
+
+    gaps.sort_unstable();
+
+    gaps[gaps.len() / 2] as f64
+}
+
+/// Finds runs of back-to-back scrobbles from the same album with at least `min_tracks` different tracks
+fn find_full_albums(session: &Session, min_tracks: usize) -> Vec<FullAlbum> {
+    let mut albums = Vec::new();
+    let mut run: Vec<&SavedScrobble> = Vec::new();
+
+    let mut finish_run = |run: &[&SavedScrobble]| {
+        let distinct = run
+            .iter()
+            .map(|s| s.title.as_str())
+            .collect::<HashSet<&str>>();
+
+        if !run.is_empty() && !run[0].album.is_empty() && distinct.len() >= min_tracks {
+            albums.push(FullAlbum {
+                artist: run[0].artist.to_string(),
+                album: run[0].album.to_string(),
+                started: run[0].datetime_local,
+                tracks: distinct.len() as i32,
+            });
+        }
+    };
+
+    for scrobble in &session.scrobbles {
+        let same_album = match run.last() {
+            Some(last) => last.album == scrobble.album && last.artist == scrobble.artist,
+            None => true,
+        };
+
+        if !same_album {
+            finish_run(&run);
+            run.clear();
+        }
+
+        run.push(scrobble);
+    }
+    finish_run(&run);
+
+    albums
+}
@@ -0,0 +1,457 @@
+//! A "year in review" summary of a single calendar year of listening history
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use num_format::ToFormattedString;
+use serde::Serialize;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::models::track_durations::TrackDurations;
+use crate::reports::listening_time;
+use crate::reports::{escape_html, escape_markdown, DocumentFormat};
+use crate::stats::{self, RankedItem};
+use crate::utils;
+
+const TOP_ITEMS: usize = 10;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// A summary of a single calendar year, calculated from saved scrobbles
+#[derive(Serialize)]
+pub struct YearReview {
+    pub year: i32,
+    pub total_scrobbles: i32,
+    /// Total minutes listened, counting only scrobbles whose track duration is known, or `None`
+    /// if no durations are known
+    pub minutes_listened: Option<i64>,
+
+    pub distinct_artists: i32,
+    pub distinct_albums: i32,
+    pub distinct_tracks: i32,
+
+    pub top_artists: Vec<RankedItem>,
+    pub top_albums: Vec<RankedItem>,
+    pub top_tracks: Vec<RankedItem>,
+
+    /// The month with the most scrobbles, named with its full English name
+    pub best_month: Option<RankedItem>,
+    /// The day with the most scrobbles, formatted as `%Y-%m-%d`
+    pub best_day: Option<RankedItem>,
+
+    /// The number of artists scrobbled for the first time during the year
+    pub new_artists: i32,
+    pub top_new_artists: Vec<RankedItem>,
+
+    /// The longest run of consecutive days with at least one scrobble
+    pub longest_streak: Option<Streak>,
+
+    /// Scrobbles for each month of the year, from January to December
+    pub months: Vec<RankedItem>,
+
+    /// Comparison with the previous calendar year, if it has any scrobbles
+    pub previous_year: Option<YearComparison>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
+
+#[derive(Serialize)]
+pub struct Streak {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub days: i64,
+}
+
+#[derive(Serialize)]
+pub struct YearComparison {
+    pub year: i32,
+    pub total_scrobbles: i32,
+    /// The change in scrobbles from the previous year to the reviewed year
+    pub difference: i32,
+    pub percent_change: f64,
+}
+
+impl YearReview {
+    /// Builds a review of `year` from a user's entire listening history
+    ///
+    /// Minutes listened only count scrobbles whose track duration is in `durations`.
+    pub fn new(scrobbles: &[SavedScrobble], year: i32, durations: &TrackDurations) -> Self {
+        let year_scrobbles = scrobbles_in_year(scrobbles, year);
+
+        let artists = stats::count_by(&year_scrobbles, |s| s.artist.to_string());
+        let albums = stats::count_by(
+            &year_scrobbles
+                .iter()
+                .filter(|s| !s.album.is_empty())
+                .cloned()
+                .collect::<Vec<SavedScrobble>>(),
+            |s| s.group_artist_album(),
+        );
+        let tracks = stats::count_by(&year_scrobbles, |s| s.group_song_artist());
+        let days = stats::count_by(&year_scrobbles, |s| s.date());
+        let months = stats::count_by(&year_scrobbles, |s| s.date().month0());
+
+        let new_artist_counts = calculate_new_artists(scrobbles, &artists, year);
+
+        let best_day = days
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(day, scrobbles)| RankedItem {
+                name: day.format("%Y-%m-%d").to_string(),
+                scrobbles: *scrobbles,
+            });
+
+        let months = MONTHS
+            .iter()
+            .enumerate()
+            .map(|(i, month)| RankedItem {
+                name: month.to_string(),
+                scrobbles: *months.get(&(i as u32)).unwrap_or(&0),
+            })
+            .collect::<Vec<RankedItem>>();
+
+        let best_month = months
+            .iter()
+            .filter(|m| m.scrobbles > 0)
+            .max_by(|a, b| a.scrobbles.cmp(&b.scrobbles))
+            .cloned();
+
+        let total_scrobbles = year_scrobbles.len() as i32;
+        let previous_total = scrobbles_in_year(scrobbles, year - 1).len() as i32;
+        let previous_year = if previous_total > 0 {
+            Some(YearComparison {
+                year: year - 1,
+                total_scrobbles: previous_total,
+                difference: total_scrobbles - previous_total,
+                percent_change: (total_scrobbles - previous_total) as f64 / previous_total as f64
+                    * 100.0,
+            })
+        } else {
+            None
+        };
+
+        Self {
+            year,
+            total_scrobbles,
+            minutes_listened: listening_time::minutes_listened(&year_scrobbles, durations),
+
+            distinct_artists: artists.len() as i32,
+            distinct_albums: albums.len() as i32,
+            distinct_tracks: tracks.len() as i32,
+
+            top_artists: stats::top_n(&artists, TOP_ITEMS),
+            top_albums: stats::top_n(&albums, TOP_ITEMS),
+            top_tracks: stats::top_n(&tracks, TOP_ITEMS),
+
+            best_month,
+            best_day,
+
+            new_artists: new_artist_counts.len() as i32,
+            top_new_artists: stats::top_n(&new_artist_counts, TOP_ITEMS),
+
+            longest_streak: calculate_longest_streak(days.keys().cloned().collect()),
+
+            months,
+            previous_year,
+            excluded_scrobbles: 0,
+        }
+    }
+
+    /// Renders the review as a standalone document
+    pub fn render(&self, format: DocumentFormat) -> String {
+        match format {
+            DocumentFormat::Html => self.to_html(),
+            DocumentFormat::Markdown => self.to_markdown(),
+        }
+    }
+
+    fn summary(&self) -> Vec<(&str, String)> {
+        let locale = utils::get_locale();
+
+        let minutes_listened = match self.minutes_listened {
+            Some(minutes) => minutes.to_formatted_string(&locale),
+            None => "Unknown".to_string(),
+        };
+
+        let best_month = match &self.best_month {
+            Some(month) => format!(
+                "{} ({} scrobbles)",
+                month.name,
+                month.scrobbles.to_formatted_string(&locale)
+            ),
+            None => "N/A".to_string(),
+        };
+
+        let best_day = match &self.best_day {
+            Some(day) => format!(
+                "{} ({} scrobbles)",
+                day.name,
+                day.scrobbles.to_formatted_string(&locale)
+            ),
+            None => "N/A".to_string(),
+        };
+
+        let longest_streak = match &self.longest_streak {
+            Some(streak) => format!(
+                "{} days ({} to {})",
+                streak.days.to_formatted_string(&locale),
+                streak.start,
+                streak.end
+            ),
+            None => "N/A".to_string(),
+        };
+
+        let previous_year = match &self.previous_year {
+            Some(previous) => format!(
+                "{:+.1}% ({} scrobbles in {})",
+                previous.percent_change,
+                previous.total_scrobbles.to_formatted_string(&locale),
+                previous.year
+            ),
+            None => "N/A".to_string(),
+        };
+
+        let mut summary = vec![(
+            "Total Scrobbles",
+            self.total_scrobbles.to_formatted_string(&locale),
+        )];
+        if self.excluded_scrobbles > 0 {
+            summary.push((
+                "Excluded Scrobbles",
+                self.excluded_scrobbles.to_formatted_string(&locale),
+            ));
+        }
+        summary.extend(vec![
+            ("Minutes Listened", minutes_listened),
+            (
+                "Artists",
+                self.distinct_artists.to_formatted_string(&locale),
+            ),
+            ("Albums", self.distinct_albums.to_formatted_string(&locale)),
+            ("Tracks", self.distinct_tracks.to_formatted_string(&locale)),
+            ("New Artists", self.new_artists.to_formatted_string(&locale)),
+            ("Best Month", best_month),
+            ("Best Day", best_day),
+            ("Longest Streak", longest_streak),
+            ("Compared To Previous Year", previous_year),
+        ]);
+
+        summary
+    }
+
+    fn ranked_lists(&self) -> Vec<(&str, &[RankedItem])> {
+        vec![
+            ("Top Artists", &self.top_artists),
+            ("Top Albums", &self.top_albums),
+            ("Top Tracks", &self.top_tracks),
+            ("New Discoveries", &self.top_new_artists),
+        ]
+    }
+
+    fn to_markdown(&self) -> String {
+        let locale = utils::get_locale();
+        let mut lines = vec![format!("# {} Year in Review\n", self.year)];
+
+        lines.push("| | |".to_string());
+        lines.push("| --- | --- |".to_string());
+        for (label, value) in self.summary() {
+            lines.push(format!("| {} | {} |", label, escape_markdown(&value)));
+        }
+        lines.push(String::new());
+
+        for (title, items) in self.ranked_lists() {
+            lines.push(format!("## {}\n", title));
+
+            if items.is_empty() {
+                lines.push("_None_\n".to_string());
+                continue;
+            }
+
+            lines.push("| # | Name | Scrobbles |".to_string());
+            lines.push("| --- | --- | --- |".to_string());
+            for (i, item) in items.iter().enumerate() {
+                lines.push(format!(
+                    "| {} | {} | {} |",
+                    i + 1,
+                    escape_markdown(&item.name),
+                    item.scrobbles.to_formatted_string(&locale)
+                ));
+            }
+            lines.push(String::new());
+        }
+
+        lines.push("## Scrobbles Per Month\n".to_string());
+        lines.push("```".to_string());
+        let max = self.months.iter().map(|m| m.scrobbles).max().unwrap_or(0);
+        for month in &self.months {
+            lines.push(format!(
+                "{} {:<40} {}",
+                &month.name[..3],
+                "█".repeat(scale(month.scrobbles, max, 40)),
+                month.scrobbles.to_formatted_string(&locale)
+            ));
+        }
+        lines.push("```".to_string());
+
+        lines.join("\n")
+    }
+
+    fn to_html(&self) -> String {
+        let locale = utils::get_locale();
+        let mut body = vec![format!("<h1>{} Year in Review</h1>", self.year)];
+
+        body.push("<table class=\"summary\">".to_string());
+        for (label, value) in self.summary() {
+            body.push(format!(
+                "<tr><th>{}</th><td>{}</td></tr>",
+                label,
+                escape_html(&value)
+            ));
+        }
+        body.push("</table>".to_string());
+
+        for (title, items) in self.ranked_lists() {
+            body.push(format!("<h2>{}</h2>", title));
+
+            if items.is_empty() {
+                body.push("<p><em>None</em></p>".to_string());
+                continue;
+            }
+
+            body.push("<ol>".to_string());
+            for item in items {
+                body.push(format!(
+                    "<li>{} <span class=\"count\">{}</span></li>",
+                    escape_html(&item.name),
+                    item.scrobbles.to_formatted_string(&locale)
+                ));
+            }
+            body.push("</ol>".to_string());
+        }
+
+        body.push("<h2>Scrobbles Per Month</h2>".to_string());
+        body.push("<div class=\"chart\">".to_string());
+        let max = self.months.iter().map(|m| m.scrobbles).max().unwrap_or(0);
+        for month in &self.months {
+            body.push(format!(
+                "<div class=\"row\"><span class=\"label\">{}</span><span class=\"bar\" style=\"width: {}%\"></span><span class=\"count\">{}</span></div>",
+                &month.name[..3],
+                scale(month.scrobbles, max, 100),
+                month.scrobbles.to_formatted_string(&locale)
+            ));
+        }
+        body.push("</div>".to_string());
+
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{year} Year in Review</title>
+<style>
+body {{ font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; color: #222; }}
+h1 {{ color: #d51007; }}
+table.summary th {{ text-align: left; padding-right: 2rem; }}
+.count {{ color: #777; margin-left: 0.5rem; }}
+.chart .row {{ display: flex; align-items: center; margin: 0.2rem 0; }}
+.chart .label {{ width: 3rem; }}
+.chart .bar {{ display: inline-block; height: 1rem; background: #d51007; }}
+</style>
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+            year = self.year,
+            body = body.join("\n")
+        )
+    }
+}
+
+fn scrobbles_in_year(scrobbles: &[SavedScrobble], year: i32) -> Vec<SavedScrobble> {
+    scrobbles
+        .iter()
+        .filter(|s| s.date().year() == year)
+        .cloned()
+        .collect()
+}
+
+/// Finds the artists whose first ever scrobble happened during `year`, along with their plays that year
+fn calculate_new_artists(
+    scrobbles: &[SavedScrobble],
+    year_artists: &HashMap<String, i32>,
+    year: i32,
+) -> HashMap<String, i32> {
+    let mut first_years: HashMap<&str, i32> = HashMap::new();
+
+    scrobbles.iter().for_each(|scrobble| {
+        let first_year = first_years
+            .entry(scrobble.artist.as_str())
+            .or_insert_with(|| scrobble.date().year());
+        *first_year = (*first_year).min(scrobble.date().year());
+    });
+
+    year_artists
+        .iter()
+        .filter(|(artist, _)| first_years.get(artist.as_str()) == Some(&year))
+        .map(|(artist, scrobbles)| (artist.to_string(), *scrobbles))
+        .collect()
+}
+
+fn calculate_longest_streak(days: HashSet<NaiveDate>) -> Option<Streak> {
+    let mut days = days.into_iter().collect::<Vec<NaiveDate>>();
+    days.sort_unstable();
+
+    let mut longest: Option<Streak> = None;
+    let mut start = *days.first()?;
+
+    for (i, day) in days.iter().enumerate() {
+        let next = days.get(i + 1);
+
+        if next != Some(&(*day + Duration::days(1))) {
+            let length = (*day - start).num_days() + 1;
+
+            let is_longest = match &longest {
+                Some(l) => length > l.days,
+                None => true,
+            };
+
+            if is_longest {
+                longest = Some(Streak {
+                    start,
+                    end: *day,
+                    days: length,
+                });
+            }
+
+            if let Some(next) = next {
+                start = *next;
+            }
+        }
+    }
+
+    longest
+}
+
+/// Scales `value` to a bar length between 0 and `width`, relative to `max`
+fn scale(value: i32, max: i32, width: usize) -> usize {
+    if max <= 0 {
+        return 0;
+    }
+
+    (value as f64 / max as f64 * width as f64).round() as usize
+}
@@ -1,108 +1,244 @@
 use std::collections::HashMap;
+use std::hash::Hash;
 
-use chrono::{Datelike, NaiveDate};
-use num_format::ToFormattedString;
+use chrono::{Datelike, Duration};
+use clap::ArgEnum;
+use serde::Serialize;
 
 use crate::models::saved_scrobbles::SavedScrobble;
+use crate::output::{Report, Section};
 use crate::utils;
 
+/// Statistics calculated from a user's saved scrobbles
+///
+/// When written with `--output json`, the fields below are serialized as-is:
+///
+/// ```json
+/// {
+///   "total_scrobbles": 104512,
+///   "average_tracks_per_day": 38.2,
+///   "average_tracks_per_week": 274.9,
+///   "average_tracks_per_month": 1216.5,
+///   "average_tracks_per_year": 14598.0,
+///   "best_month": { "month": "March-2021", "scrobbles": 2710 }
+/// }
+/// ```
+#[derive(Serialize)]
 pub struct Stats {
-    average_tracks_per_day: f64,
-    average_tracks_per_week: f64,
-    average_tracks_per_month: f64,
-    average_tracks_per_year: f64,
+    /// The number of scrobbles the statistics were calculated from
+    pub total_scrobbles: i32,
+
+    pub average_tracks_per_day: f64,
+    pub average_tracks_per_week: f64,
+    pub average_tracks_per_month: f64,
+    pub average_tracks_per_year: f64,
+
+    /// The month with the most scrobbles, or `null` if there are no scrobbles
+    pub best_month: Option<BestMonth>,
+    /// The number of scrobbles left out by the exclusion rules
+    pub excluded_scrobbles: i32,
+}
 
-    best_month: (String, i32),
+/// A period of time ending now, mirroring the periods offered by the Last.fm API
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Overall,
+    #[clap(name = "7day")]
+    #[serde(rename = "7day")]
+    Week,
+    #[clap(name = "1month")]
+    #[serde(rename = "1month")]
+    Month,
+    #[clap(name = "3month")]
+    #[serde(rename = "3month")]
+    Quarter,
+    #[clap(name = "6month")]
+    #[serde(rename = "6month")]
+    HalfYear,
+    #[clap(name = "12month")]
+    #[serde(rename = "12month")]
+    Year,
+}
+
+impl Period {
+    /// Keeps only the scrobbles that were scrobbled within the period ending at `now`
+    pub fn filter(&self, scrobbles: &[SavedScrobble], now: i64) -> Vec<SavedScrobble> {
+        let days = match self {
+            Period::Overall => return scrobbles.to_vec(),
+            Period::Week => 7,
+            Period::Month => 30,
+            Period::Quarter => 90,
+            Period::HalfYear => 180,
+            Period::Year => 365,
+        };
+
+        let start = now - Duration::days(days).num_seconds();
+
+        scrobbles
+            .iter()
+            .filter(|s| s.timestamp_utc >= start && s.timestamp_utc <= now)
+            .cloned()
+            .collect()
+    }
+}
+
+/// A name and the number of times it was scrobbled, e.g. an artist in a list of top artists
+#[derive(Clone, Serialize)]
+pub struct RankedItem {
+    pub name: String,
+    pub scrobbles: i32,
+}
+
+/// The month with the most scrobbles
+#[derive(Serialize)]
+pub struct BestMonth {
+    /// Month and year, formatted as `%B-%Y` (e.g. `March-2021`)
+    pub month: String,
+    pub scrobbles: i32,
 }
 
 impl Stats {
     pub fn new(scrobbles: &[SavedScrobble]) -> Self {
+        if scrobbles.is_empty() {
+            return Self {
+                total_scrobbles: 0,
+                average_tracks_per_day: 0.0,
+                average_tracks_per_week: 0.0,
+                average_tracks_per_month: 0.0,
+                average_tracks_per_year: 0.0,
+                best_month: None,
+                excluded_scrobbles: 0,
+            };
+        }
+
         Self {
+            total_scrobbles: scrobbles.len() as i32,
+
             average_tracks_per_day: calculate_daily_average(scrobbles),
             average_tracks_per_week: calculate_weekly_average(scrobbles),
             average_tracks_per_month: calculate_monthly_average(scrobbles),
             average_tracks_per_year: calculate_yearly_average(scrobbles),
 
             best_month: calculate_best_month(scrobbles),
+            excluded_scrobbles: 0,
         }
     }
+}
+
+impl Report for Stats {
+    fn sections(&self) -> Vec<Section> {
+        vec![Section::key_values(
+            "Stats",
+            vec![
+                ("Total Scrobbles", self.total_scrobbles.into()),
+                ("Average Tracks Per Day", self.average_tracks_per_day.into()),
+                (
+                    "Average Tracks Per Week",
+                    self.average_tracks_per_week.into(),
+                ),
+                (
+                    "Average Tracks Per Month",
+                    self.average_tracks_per_month.into(),
+                ),
+                (
+                    "Average Tracks Per Year",
+                    self.average_tracks_per_year.into(),
+                ),
+                (
+                    "Best Month",
+                    self.best_month.as_ref().map(|m| m.month.as_str()).into(),
+                ),
+                (
+                    "Best Month Scrobbles",
+                    self.best_month.as_ref().map(|m| m.scrobbles).into(),
+                ),
+            ],
+        )]
+    }
 
-    pub fn print(&self) {
-        println!("STATS:\n");
-
-        println!("Average Tracks Per Day:   {}", self.average_tracks_per_day);
-        println!("Average Tracks Per Week:  {}", self.average_tracks_per_week);
-        println!(
-            "Average Tracks Per Month: {}",
-            self.average_tracks_per_month
-        );
-        println!("Average Tracks Per Year:  {}", self.average_tracks_per_year);
-
-        println!(
-            "Best Month: {} ({} scrobbles)",
-            self.best_month.0,
-            self.best_month.1.to_formatted_string(&utils::get_locale())
-        );
+    fn excluded_scrobbles(&self) -> i32 {
+        self.excluded_scrobbles
     }
 }
 
 fn calculate_daily_average(scrobbles: &[SavedScrobble]) -> f64 {
-    let mut groups: HashMap<NaiveDate, i32> = HashMap::new();
-
-    scrobbles.iter().for_each(|scrobble| {
-        let group = groups.entry(scrobble.date()).or_insert(0);
-        *group += 1
-    });
-
-    groups.iter().map(|g| g.1).sum::<i32>() as f64 / utils::get_total_days(&scrobbles) as f64
+    let groups = count_by(scrobbles, |s| s.date());
+    groups.values().sum::<i32>() as f64 / utils::get_total_days(scrobbles) as f64
 }
 
 fn calculate_weekly_average(scrobbles: &[SavedScrobble]) -> f64 {
-    let mut groups: HashMap<u32, i32> = HashMap::new();
-
-    scrobbles.iter().for_each(|scrobble| {
-        let group = groups.entry(scrobble.date().iso_week().week()).or_insert(0);
-        *group += 1;
-    });
-
-    groups.iter().map(|g| g.1).sum::<i32>() as f64 / utils::get_total_weeks(&scrobbles) as f64
+    let groups = count_by(scrobbles, |s| s.date().iso_week().week());
+    groups.values().sum::<i32>() as f64 / utils::get_total_weeks(scrobbles)
 }
 
 fn calculate_monthly_average(scrobbles: &[SavedScrobble]) -> f64 {
-    let mut groups: HashMap<u32, i32> = HashMap::new();
+    let groups = count_by(scrobbles, |s| s.date().month());
+    groups.values().sum::<i32>() as f64 / utils::get_total_months(scrobbles)
+}
 
-    scrobbles.iter().for_each(|scrobble| {
-        let group = groups.entry(scrobble.date().month()).or_insert(0);
-        *group += 1;
-    });
+fn calculate_yearly_average(scrobbles: &[SavedScrobble]) -> f64 {
+    let groups = count_by(scrobbles, |s| s.date().year());
+    groups.values().sum::<i32>() as f64 / utils::get_total_years(scrobbles)
+}
+
+fn calculate_best_month(scrobbles: &[SavedScrobble]) -> Option<BestMonth> {
+    let groups = count_by(scrobbles, |s| s.month_year());
 
-    groups.iter().map(|g| g.1).sum::<i32>() as f64 / utils::get_total_months(&scrobbles) as f64
+    top_n(&groups, 1).pop().map(|best_month| BestMonth {
+        month: best_month.name,
+        scrobbles: best_month.scrobbles,
+    })
 }
 
-fn calculate_yearly_average(scrobbles: &[SavedScrobble]) -> f64 {
-    let mut groups: HashMap<i32, i32> = HashMap::new();
+/// Splits scrobbles into the groups produced by `key`, keeping their order within each group
+pub fn group_by<K, F>(scrobbles: &[SavedScrobble], key: F) -> HashMap<K, Vec<SavedScrobble>>
+where
+    K: Eq + Hash,
+    F: Fn(&SavedScrobble) -> K,
+{
+    let mut groups: HashMap<K, Vec<SavedScrobble>> = HashMap::new();
 
     scrobbles.iter().for_each(|scrobble| {
-        let group = groups.entry(scrobble.date().year()).or_insert(0);
-        *group += 1
+        groups
+            .entry(key(scrobble))
+            .or_default()
+            .push(scrobble.clone())
     });
 
-    groups.iter().map(|g| g.1).sum::<i32>() as f64 / utils::get_total_years(&scrobbles) as f64
+    groups
 }
 
-fn calculate_best_month(scrobbles: &[SavedScrobble]) -> (String, i32) {
-    let mut groups: HashMap<String, i32> = HashMap::new();
+/// Counts the number of scrobbles in each group produced by `key`
+pub fn count_by<K, F>(scrobbles: &[SavedScrobble], key: F) -> HashMap<K, i32>
+where
+    K: Eq + Hash,
+    F: Fn(&SavedScrobble) -> K,
+{
+    let mut groups: HashMap<K, i32> = HashMap::new();
 
     scrobbles.iter().for_each(|scrobble| {
-        let group = groups.entry(scrobble.month_year()).or_insert(0);
+        let group = groups.entry(key(scrobble)).or_insert(0);
         *group += 1
     });
 
-    let mut group_vec = groups.iter().collect::<Vec<(&String, &i32)>>();
-    group_vec.sort_by(|a, b| a.1.cmp(b.1));
-    group_vec.reverse();
-
-    let best_month = group_vec.get(0).expect("Error retrieving best month");
+    groups
+}
 
-    (best_month.0.to_string(), *best_month.1)
+/// Ranks grouped counts from most to least scrobbled, keeping the first `n` items
+///
+/// Ties are broken alphabetically so the ranking is stable between runs.
+pub fn top_n(groups: &HashMap<String, i32>, n: usize) -> Vec<RankedItem> {
+    let mut ranked = groups
+        .iter()
+        .map(|(name, scrobbles)| RankedItem {
+            name: name.to_string(),
+            scrobbles: *scrobbles,
+        })
+        .collect::<Vec<RankedItem>>();
+
+    ranked.sort_by(|a, b| b.scrobbles.cmp(&a.scrobbles).then(a.name.cmp(&b.name)));
+    ranked.truncate(n);
+
+    ranked
 }
@@ -0,0 +1,244 @@
+//! Bar charts, sparklines and histograms drawn with Unicode blocks, or plain ASCII as a fallback
+
+use std::env;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use console::Term;
+
+use crate::models::saved_scrobbles::SavedScrobble;
+use crate::stats;
+
+/// The width used when the terminal's width cannot be detected, e.g. when piping to a file
+const DEFAULT_WIDTH: usize = 80;
+/// The number of artists in the top artists bar chart
+const TOP_ARTISTS: usize = 10;
+/// The number of rows of the monthly histogram
+const HISTOGRAM_HEIGHT: usize = 8;
+
+/// The characters charts are drawn with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Charset {
+    /// Block elements, which draw bars in eighths of a character
+    Unicode,
+    Ascii,
+}
+
+impl Charset {
+    /// Uses Unicode when the locale's character encoding is UTF-8
+    pub fn detect() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if locale.contains("utf-8") || locale.contains("utf8") {
+            Charset::Unicode
+        } else {
+            Charset::Ascii
+        }
+    }
+
+    /// Levels from empty to full, used for sparklines and the top of histogram columns
+    fn levels(&self) -> &'static [char] {
+        match self {
+            Charset::Unicode => &[' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'],
+            Charset::Ascii => &[' ', '.', ':', '-', '=', '+', '*', '#', '@'],
+        }
+    }
+
+    /// Partial widths from empty to full, used for the ends of horizontal bars
+    fn widths(&self) -> &'static [char] {
+        match self {
+            Charset::Unicode => &[' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'],
+            Charset::Ascii => &[' ', ' ', ' ', ' ', '#', '#', '#', '#', '#'],
+        }
+    }
+}
+
+/// The width of the terminal stdout is attached to, falling back to `$COLUMNS` and then 80
+pub fn terminal_width() -> usize {
+    if let Some((_, columns)) = Term::stdout().size_checked() {
+        return columns as usize;
+    }
+
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Draws a horizontal bar for each labelled value, scaled so the largest fills the width
+pub fn bar_chart(items: &[(String, i32)], width: usize, charset: Charset) -> String {
+    let label_width = items
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let value_width = items
+        .iter()
+        .map(|(_, value)| value.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let bar_width = width.saturating_sub(label_width + value_width + 4).max(1);
+    let max = items
+        .iter()
+        .map(|(_, value)| *value)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let widths = charset.widths();
+    let steps = widths.len() - 1;
+
+    items
+        .iter()
+        .map(|(label, value)| {
+            let eighths =
+                (*value as f64 / max as f64 * (bar_width * steps) as f64).round() as usize;
+            let mut bar = widths[steps].to_string().repeat(eighths / steps);
+            if eighths % steps != 0 {
+                bar.push(widths[eighths % steps]);
+            }
+
+            format!(
+                "{:<label_width$}  {:>value_width$}  {}",
+                label,
+                value,
+                bar.trim_end(),
+                label_width = label_width,
+                value_width = value_width,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Draws values as a single line, one character per value. Only the last `width` values are
+/// drawn when there are more.
+pub fn sparkline(values: &[i32], width: usize, charset: Charset) -> String {
+    let values = &values[values.len().saturating_sub(width)..];
+    let levels = charset.levels();
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+
+    values
+        .iter()
+        .map(|value| {
+            // Any scrobbles at all show as at least the lowest level
+            let level = (*value as f64 / max as f64 * (levels.len() - 1) as f64).ceil();
+            levels[level as usize]
+        })
+        .collect()
+}
+
+/// Draws a column for each labelled value, `height` rows tall, labelling the first and last
+/// columns underneath. Only the last `width` values are drawn when there are more.
+pub fn histogram(items: &[(String, i32)], height: usize, width: usize, charset: Charset) -> String {
+    let items = &items[items.len().saturating_sub(width)..];
+    let levels = charset.levels();
+    let steps = levels.len() - 1;
+    let max = items
+        .iter()
+        .map(|(_, value)| *value)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let heights = items
+        .iter()
+        .map(|(_, value)| (*value as f64 / max as f64 * (height * steps) as f64).round() as usize)
+        .collect::<Vec<usize>>();
+
+    let mut lines = (0..height)
+        .rev()
+        .map(|row| {
+            heights
+                .iter()
+                .map(|h| levels[h.saturating_sub(row * steps).min(steps)])
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<String>>();
+
+    if let (Some((first, _)), Some((last, _))) = (items.first(), items.last()) {
+        let gap = items
+            .len()
+            .saturating_sub(first.chars().count() + last.chars().count());
+        lines.push(match gap {
+            0 => first.to_string(),
+            gap => format!("{}{}{}", first, " ".repeat(gap), last),
+        });
+    }
+
+    lines.join("\n")
+}
+
+/// Charts of a listening history: the top artists, sparklines of daily and weekly scrobbles, and a
+/// histogram of monthly scrobbles
+pub fn render_stats_charts(scrobbles: &[SavedScrobble], width: usize, charset: Charset) -> String {
+    let (newest, oldest) = match (scrobbles.first(), scrobbles.last()) {
+        (Some(newest), Some(oldest)) => (newest.date(), oldest.date()),
+        _ => return String::new(),
+    };
+
+    let artists = stats::count_by(scrobbles, |s| s.artist.to_string());
+    let top_artists = stats::top_n(&artists, TOP_ARTISTS)
+        .into_iter()
+        .map(|artist| (artist.name, artist.scrobbles))
+        .collect::<Vec<(String, i32)>>();
+
+    let days = stats::count_by(scrobbles, |s| s.date());
+    let daily = dates_between(oldest, newest, Duration::days(1))
+        .iter()
+        .map(|date| *days.get(date).unwrap_or(&0))
+        .collect::<Vec<i32>>();
+
+    let weeks = stats::count_by(scrobbles, |s| week_start(s.date()));
+    let weekly = dates_between(week_start(oldest), newest, Duration::weeks(1))
+        .iter()
+        .map(|week| *weeks.get(week).unwrap_or(&0))
+        .collect::<Vec<i32>>();
+
+    let months = stats::count_by(scrobbles, |s| s.date().format("%Y-%m").to_string());
+    let mut monthly = months.into_iter().collect::<Vec<(String, i32)>>();
+    monthly.sort();
+
+    let sections = [
+        ("Top Artists", bar_chart(&top_artists, width, charset)),
+        (
+            &format!("Daily Scrobbles (Last {} Days)", daily.len().min(width)),
+            sparkline(&daily, width, charset),
+        ),
+        (
+            &format!("Weekly Scrobbles (Last {} Weeks)", weekly.len().min(width)),
+            sparkline(&weekly, width, charset),
+        ),
+        (
+            "Monthly Scrobbles",
+            histogram(&monthly, HISTOGRAM_HEIGHT, width, charset),
+        ),
+    ];
+
+    sections
+        .iter()
+        .map(|(title, chart)| format!("{}:\n\n{}", title.to_uppercase(), chart))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn dates_between(from: NaiveDate, to: NaiveDate, step: Duration) -> Vec<NaiveDate> {
+    let mut dates = Vec::new();
+    let mut date = from;
+    while date <= to {
+        dates.push(date);
+        date += step;
+    }
+
+    dates
+}
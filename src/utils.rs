@@ -6,13 +6,18 @@ use num_format::{Locale, SystemLocale};
 use crate::models::saved_scrobbles::SavedScrobble;
 
 pub fn get_locale() -> Locale {
-    let system_locale = SystemLocale::default()
-        .expect("Error retrieving system locale")
-        .name()[..2]
-        .trim()
-        .to_lowercase();
-
-    // If the system locale cannot be used, default to American English locale
+    let system_locale = match SystemLocale::default() {
+        Ok(system_locale) => system_locale
+            .name()
+            .chars()
+            .take(2)
+            .collect::<String>()
+            .trim()
+            .to_lowercase(),
+        Err(_) => String::new(),
+    };
+
+    // If the system locale cannot be used (e.g. the `C` locale), default to American English locale
     Locale::from_name(system_locale).unwrap_or(Locale::en)
 }
 
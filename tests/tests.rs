@@ -1,10 +1,946 @@
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use assert_cmd::Command;
+    use chrono::prelude::*;
+    use proptest::prelude::*;
+
+    use rustfm_scraper::models::artist_tags::{ArtistTag, ArtistTags, ArtistTagsCache};
+    use rustfm_scraper::models::exclusion_rules::ExclusionRules;
+    use rustfm_scraper::models::genre_rules::GenreRules;
+    use rustfm_scraper::models::loved_tracks::LovedTracksResponse;
+    use rustfm_scraper::models::name_rules::NameRules;
+    use rustfm_scraper::models::saved_loved_tracks::SavedLovedTracks;
+    use rustfm_scraper::models::saved_scrobbles::{SavedScrobble, SavedScrobbles};
+    use rustfm_scraper::models::saved_weekly_charts::{ChartKind, SavedWeeklyCharts};
+    use rustfm_scraper::models::time_zones::{LocalTimes, TimeZoneOverride, TimeZoneOverrides};
+    use rustfm_scraper::models::top_tags::TopTagsResponse;
+    use rustfm_scraper::models::track_durations::{TrackDuration, TrackDurations};
+    use rustfm_scraper::reports::anomalies::{self, Anomalies, AnomalyKind};
+    use rustfm_scraper::reports::binges::{Binges, Thresholds, Window};
+    use rustfm_scraper::reports::chart_race::{self, ChartRace, Layout, Mode};
+    use rustfm_scraper::reports::charts::{self, ChartDiff};
+    use rustfm_scraper::reports::cleanup::CleanupPreview;
+    use rustfm_scraper::reports::compare::Compatibility;
+    use rustfm_scraper::reports::diversity::{Diversity, DiversityMeasures};
+    use rustfm_scraper::reports::genres::{self, GenreShares, Interval};
+    use rustfm_scraper::reports::listening_time::{ListeningTime, TimeGrouping};
+    use rustfm_scraper::reports::loyalty::{ArtistLoyalty, ForgottenThresholds, Loyalty};
+    use rustfm_scraper::reports::milestones::{self, Milestones};
+    use rustfm_scraper::reports::on_this_day::{MonthDay, OnThisDay};
+    use rustfm_scraper::reports::query_results::{GroupBy, GroupCounts, ScrobbleList, SortBy};
+    use rustfm_scraper::reports::search::{self, SearchTerms};
+    use rustfm_scraper::reports::sessions::{self, SessionStats};
+    use rustfm_scraper::reports::year_review::YearReview;
+    use rustfm_scraper::reports::DocumentFormat;
+    use rustfm_scraper::stats::Period;
+    use rustfm_scraper::terminal_charts::{self, Charset};
 
     #[test]
     fn test_no_args() {
         let mut cmd = Command::cargo_bin("rustfm-scraper").unwrap();
         cmd.assert().failure();
     }
+
+    #[test]
+    fn test_year_review() {
+        let day = 86_400;
+        let jan_1_2021 = 1_609_502_400; // 2021-01-01 12:00:00 UTC
+
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021 - 30 * day),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+            SavedScrobble::played("Idioteque", "Radiohead", "Kid A", jan_1_2021 + day),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 2 * day),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 10 * day),
+            SavedScrobble::played("Idioteque", "Radiohead", "Kid A", jan_1_2021 + 20 * day),
+        ]);
+
+        let review = YearReview::new(saved_scrobbles.as_slice(), 2021, &TrackDurations::default());
+
+        assert_eq!(review.total_scrobbles, 5);
+        assert_eq!(review.top_artists[0].name, "Radiohead");
+        assert_eq!(review.new_artists, 1);
+        assert_eq!(review.top_new_artists[0].name, "Björk");
+        assert_eq!(review.longest_streak.as_ref().unwrap().days, 3);
+        assert_eq!(review.previous_year.as_ref().unwrap().total_scrobbles, 1);
+
+        let html = review.render(DocumentFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Björk"));
+    }
+
+    #[test]
+    fn test_compare() {
+        let loved = |mut scrobble: SavedScrobble| {
+            scrobble.loved = true;
+            scrobble
+        };
+        let a = vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", 1_600_000_000),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", 1_600_000_300),
+            loved(SavedScrobble::played(
+                "Hyperballad",
+                "Björk",
+                "Post",
+                1_600_000_600,
+            )),
+            SavedScrobble::played("Creep", "Stone Temple Pilots", "", 1_600_000_900),
+        ];
+        let b = vec![
+            SavedScrobble::played("Idioteque", "Radiohead", "Kid A", 1_600_000_000),
+            loved(SavedScrobble::played(
+                "Roygbiv",
+                "Boards of Canada",
+                "Music Has the Right to Children",
+                1_600_000_300,
+            )),
+        ];
+
+        let identical = Compatibility::new(("a", &a), ("a", &a), Period::Overall, 1_700_000_000);
+        assert!((identical.score - 100.0).abs() < 1e-9);
+
+        let compatibility =
+            Compatibility::new(("a", &a), ("b", &b), Period::Overall, 1_700_000_000);
+        assert_eq!(compatibility.shared_artists, 1);
+        assert!((compatibility.overlap - 1.0 / 4.0).abs() < 1e-9);
+        // Stone Temple Pilots were never played by b either, but a does not love them
+        assert_eq!(compatibility.loved_only_a.len(), 1);
+        assert_eq!(compatibility.loved_only_a[0].name, "Björk");
+        assert_eq!(compatibility.loved_only_b[0].name, "Boards of Canada");
+
+        let last_week = Compatibility::new(("a", &a), ("b", &b), Period::Week, 1_700_000_000);
+        assert_eq!(last_week.scrobbles_a, 0);
+    }
+
+    #[test]
+    fn test_milestones() {
+        let saved_scrobbles = SavedScrobbles::new(
+            (0..2500)
+                .map(|i| {
+                    SavedScrobble::played(
+                        &format!("Track {}", i),
+                        "Radiohead",
+                        "",
+                        1_600_000_000 + i * 300,
+                    )
+                })
+                .collect(),
+        );
+        let scrobbles = saved_scrobbles.as_slice();
+
+        assert_eq!(
+            milestones::find_by_number(scrobbles, 1).unwrap().title,
+            "Track 0"
+        );
+        assert_eq!(
+            milestones::find_by_number(scrobbles, 1000).unwrap().title,
+            "Track 999"
+        );
+        assert!(milestones::find_by_number(scrobbles, 2501).is_none());
+
+        let milestones = Milestones::new(scrobbles, Some(1000));
+        let numbers = milestones
+            .milestones
+            .iter()
+            .map(|m| m.number)
+            .collect::<Vec<i32>>();
+        assert_eq!(numbers, vec![1, 1000, 2000]);
+
+        let thresholds = milestones
+            .artist_milestones
+            .iter()
+            .map(|m| (m.threshold, m.reached_at.title.as_str()))
+            .collect::<Vec<(i32, &str)>>();
+        assert_eq!(thresholds, vec![(100, "Track 99"), (1000, "Track 999")]);
+    }
+
+    #[test]
+    fn test_sessions() {
+        let start = 1_600_000_000;
+        let album = ["Airbag", "Paranoid Android", "Subterranean Homesick Alien"];
+
+        let mut scrobbles = album
+            .iter()
+            .enumerate()
+            .map(|(i, title)| {
+                SavedScrobble::played(title, "Radiohead", "OK Computer", start + i as i64 * 240)
+            })
+            .collect::<Vec<SavedScrobble>>();
+        // Two hours later, a second session
+        scrobbles.push(SavedScrobble::played(
+            "Hyperballad",
+            "Björk",
+            "Post",
+            start + 7200,
+        ));
+
+        let saved_scrobbles = SavedScrobbles::new(scrobbles);
+        let detected = sessions::detect_sessions(saved_scrobbles.as_slice(), 30 * 60);
+        assert_eq!(detected.len(), 2);
+        assert_eq!(detected[0].scrobbles.len(), 3);
+        assert_eq!(detected[0].span(), 480);
+
+        let session_stats = SessionStats::new(saved_scrobbles.as_slice(), 30, 3);
+        assert_eq!(session_stats.sessions, 2);
+        assert!((session_stats.typical_track_minutes - 4.0).abs() < 1e-9);
+        // 8 minutes of gaps, plus one typical track at the end of each session
+        assert!((session_stats.estimated_listening_minutes - 16.0).abs() < 1e-9);
+        assert_eq!(session_stats.full_albums.len(), 1);
+        assert_eq!(session_stats.full_albums[0].album, "OK Computer");
+    }
+
+    #[test]
+    fn test_binges() {
+        let day = 86_400;
+        // A Monday
+        let jan_4_2021 = 1_609_761_600;
+
+        let mut scrobbles = (0..5)
+            .map(|i| {
+                SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_4_2021 + i * 600)
+            })
+            .collect::<Vec<SavedScrobble>>();
+        scrobbles.push(SavedScrobble::played(
+            "Hyperballad",
+            "Björk",
+            "Post",
+            jan_4_2021 + day,
+        ));
+        scrobbles.extend((0..3).map(|i| {
+            SavedScrobble::played(
+                "Hyperballad",
+                "Björk",
+                "Post",
+                jan_4_2021 + 70 * day + i * 600,
+            )
+        }));
+        let saved_scrobbles = SavedScrobbles::new(scrobbles);
+
+        let thresholds = Thresholds {
+            day_plays: 5,
+            week_plays: 15,
+            artist_share: 80.0,
+            min_week_scrobbles: 3,
+            decay_weeks: 8,
+        };
+        let binges = Binges::new(saved_scrobbles.as_slice(), thresholds);
+
+        assert_eq!(binges.track_binges.len(), 1);
+        assert_eq!(binges.track_binges[0].title, "Airbag");
+        assert_eq!(binges.track_binges[0].window, Window::Day);
+        assert_eq!(binges.track_binges[0].plays, 5);
+
+        // Radiohead has 5 of 6 scrobbles in the first week, Björk all 3 in the last
+        assert_eq!(binges.artist_obsessions.len(), 2);
+        assert_eq!(binges.artist_obsessions[0].artist, "Björk");
+        assert_eq!(binges.artist_obsessions[1].artist, "Radiohead");
+
+        assert_eq!(binges.phases.len(), 1);
+        assert_eq!(binges.phases[0].peak_week, NaiveDate::from_ymd(2021, 1, 4));
+        assert!(binges.phases[0].decayed);
+    }
+
+    #[test]
+    fn test_loyalty() {
+        let day = 86_400;
+        let jan_1_2019 = 1_546_344_000;
+
+        let mut scrobbles = Vec::new();
+        // Played every month for three years
+        scrobbles.extend((0..36).map(|i| {
+            SavedScrobble::played(
+                "Airbag",
+                "Radiohead",
+                "OK Computer",
+                jan_1_2019 + i * 31 * day,
+            )
+        }));
+        // Played heavily in the first month, then never again
+        scrobbles.extend(
+            (0..5).map(|i| {
+                SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2019 + i * day)
+            }),
+        );
+        // Played every December
+        scrobbles.extend((0..3).map(|i| {
+            SavedScrobble::played(
+                "Roygbiv",
+                "Boards of Canada",
+                "Music Has the Right to Children",
+                jan_1_2019 + (334 + i * 365) * day,
+            )
+        }));
+        // Played once, in the last month
+        scrobbles.push(SavedScrobble::played(
+            "Something",
+            "The Beatles",
+            "Abbey Road",
+            jan_1_2019 + 35 * 31 * day,
+        ));
+        let saved_scrobbles = SavedScrobbles::new(scrobbles);
+
+        let forgotten = ForgottenThresholds {
+            min_plays: 5,
+            months: 12,
+        };
+        let loyalty = ArtistLoyalty::new(saved_scrobbles.as_slice(), forgotten, 10);
+
+        let class_of = |artist: &str| {
+            loyalty
+                .artists
+                .iter()
+                .find(|a| a.artist == artist)
+                .unwrap()
+                .loyalty
+        };
+        assert_eq!(class_of("Radiohead"), Loyalty::Core);
+        assert_eq!(class_of("Björk"), Loyalty::Forgotten);
+        assert_eq!(class_of("Boards of Canada"), Loyalty::Seasonal);
+        assert_eq!(class_of("The Beatles"), Loyalty::OneOff);
+
+        assert_eq!(loyalty.forgotten_favourites.len(), 1);
+        assert_eq!(loyalty.forgotten_favourites[0].artist, "Björk");
+        assert_eq!(loyalty.artists[0].active_months, 36);
+    }
+
+    #[test]
+    fn test_on_this_day() {
+        let year = 31_536_000;
+        let mar_21_2019 = 1_553_169_600;
+        let mar_21_2020 = mar_21_2019 + year + 86_400;
+
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", mar_21_2019),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", mar_21_2019 + 60),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", mar_21_2019 + 120),
+            SavedScrobble::played(
+                "Roygbiv",
+                "Boards of Canada",
+                "Music Has the Right to Children",
+                mar_21_2019 + 86_400,
+            ),
+            SavedScrobble::played("Idioteque", "Radiohead", "Kid A", mar_21_2020),
+        ]);
+
+        let date = "03-21".parse::<MonthDay>().unwrap();
+        assert!("02-30".parse::<MonthDay>().is_err());
+
+        let on_this_day = OnThisDay::new(saved_scrobbles.as_slice(), date, 2021, 5);
+        assert_eq!(on_this_day.total_scrobbles, 4);
+        assert_eq!(on_this_day.years.len(), 2);
+        assert_eq!(on_this_day.years[0].year, 2020);
+        assert_eq!(on_this_day.years[1].first_scrobble.title, "Airbag");
+        assert_eq!(on_this_day.years[1].top_artists[0].name, "Björk");
+        assert_eq!(
+            on_this_day.years[1].top_tracks[0].name,
+            "Hyperballad - Björk"
+        );
+
+        let before_2020 = OnThisDay::new(saved_scrobbles.as_slice(), date, 2020, 5);
+        assert_eq!(before_2020.years.len(), 1);
+    }
+
+    #[test]
+    fn test_listening_time() {
+        let day = 86_400;
+        let jan_1_2021 = 1_609_502_400;
+
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021 + day),
+            SavedScrobble::played("Idioteque", "Radiohead", "Kid A", jan_1_2021 + 2 * day),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 3 * day),
+        ]);
+
+        let duration = |artist: &str, title: &str, duration_ms: Option<u32>| TrackDuration {
+            artist: artist.to_string(),
+            title: title.to_string(),
+            duration_ms,
+            fetched_at: jan_1_2021,
+        };
+        let durations = TrackDurations::new(vec![
+            duration("Radiohead", "Airbag", Some(270_000)),
+            duration("radiohead", "IDIOTEQUE", Some(300_000)),
+            duration("Björk", "Hyperballad", None),
+        ]);
+
+        let time = ListeningTime::new(
+            saved_scrobbles.as_slice(),
+            &durations,
+            TimeGrouping::Artist,
+            10,
+        );
+
+        assert_eq!(time.scrobbles_with_duration, 3);
+        assert_eq!(time.scrobbles_without_duration, 1);
+        assert!((time.coverage - 0.75).abs() < 1e-9);
+        assert!((time.hours - 0.2333).abs() < 1e-3);
+        assert_eq!(time.groups[0].name, "Radiohead");
+        assert_eq!(time.groups[1].scrobbles_without_duration, 1);
+
+        let review = YearReview::new(saved_scrobbles.as_slice(), 2021, &durations);
+        assert_eq!(review.minutes_listened, Some(14));
+    }
+
+    #[test]
+    fn test_query() {
+        let day = 86_400;
+        let jan_1_2021 = 1_609_502_400;
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 40 * day),
+            SavedScrobble::played("Let Down", "Radiohead", "OK Computer", jan_1_2021 + day),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+        ]);
+
+        let list = ScrobbleList::new(saved_scrobbles.as_slice(), SortBy::Track, false, Some(1));
+        assert_eq!(list.scrobbles[0].title, "Airbag");
+        let list = ScrobbleList::new(saved_scrobbles.as_slice(), SortBy::Date, true, None);
+        assert_eq!(list.scrobbles[0].title, "Airbag");
+
+        let artists = GroupCounts::new(saved_scrobbles.as_slice(), GroupBy::Artist, false, None);
+        assert_eq!(artists.groups[0].name, "Radiohead");
+        assert_eq!(artists.groups[0].scrobbles, 2);
+        let months = GroupCounts::new(saved_scrobbles.as_slice(), GroupBy::Month, false, None);
+        assert_eq!(months.groups[0].name, "2021-02");
+    }
+
+    #[test]
+    fn test_search() {
+        let terms = SearchTerms::parse("Live at \"Leeds\"*");
+        assert_eq!(terms.words, vec!["live", "at", "leeds"]);
+        assert_eq!(terms.fts_query(), "\"live\"* \"at\"* \"leeds\"*");
+
+        let jan_1_2021 = 1_609_502_400;
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021 + 120),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 60),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+        ]);
+        let scrobbles = saved_scrobbles.as_slice();
+
+        let results = search::search_scrobbles(scrobbles, &SearchTerms::parse("radio ok"), 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].track, "Airbag");
+        assert_eq!(results[0].scrobbles, 2);
+
+        let misspelled = SearchTerms::parse("radoihead at");
+        assert!(search::search_scrobbles(scrobbles, &misspelled, 10).is_empty());
+        let corrected = misspelled
+            .corrected(&search::vocabulary(scrobbles))
+            .unwrap();
+        // Words of two letters are never corrected
+        assert_eq!(corrected.words, vec!["radiohead", "at"]);
+        assert!(SearchTerms::parse("airbag")
+            .corrected(&search::vocabulary(scrobbles))
+            .is_none());
+
+        // Diacritics are ignored, as in the Sqlite index
+        let results = search::search_scrobbles(scrobbles, &SearchTerms::parse("bjork"), 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].artist, "Björk");
+    }
+
+    #[test]
+    fn test_title_cleanup() {
+        let jan_1_2021 = 1_609_502_400;
+        let mut saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played(
+                "Something - Remastered 2009",
+                "The Beatles",
+                "Abbey Road (Remastered)",
+                jan_1_2021 + 180,
+            ),
+            SavedScrobble::played(
+                "Something - Remastered 2009",
+                "The Beatles",
+                "Abbey Road (Remastered)",
+                jan_1_2021 + 120,
+            ),
+            SavedScrobble::played("Something", "The Beatles", "Abbey Road", jan_1_2021 + 60),
+            SavedScrobble::played("Yesterday", "The Beatles", "Help!", jan_1_2021),
+        ]);
+        let cleaner = NameRules::default().title_cleaner().unwrap();
+
+        let preview = CleanupPreview::new(saved_scrobbles.as_slice(), &cleaner, 10);
+        assert_eq!(preview.tracks.len(), 1);
+        assert_eq!(preview.tracks[0].name, "Something");
+        assert_eq!(preview.tracks[0].scrobbles, 3);
+        assert_eq!(preview.tracks[0].variants.len(), 2);
+        assert_eq!(preview.albums[0].name, "Abbey Road");
+
+        // Merged names are counted under the cleaned up name from the preview, even though the
+        // remastered name was scrobbled most often, and never changed
+        assert_eq!(saved_scrobbles.clean_titles(&cleaner), 2);
+        let scrobbles = saved_scrobbles.get_saved_scrobbles();
+        assert_eq!(scrobbles[0].title, "Something - Remastered 2009");
+        assert_eq!(scrobbles[0].original_title, None);
+        let tracks = GroupCounts::new(saved_scrobbles.as_slice(), GroupBy::Track, false, None);
+        assert_eq!(
+            tracks.groups[0].name,
+            format!("{} - The Beatles", preview.tracks[0].name)
+        );
+        assert_eq!(tracks.groups[0].scrobbles, 3);
+        let albums = GroupCounts::new(saved_scrobbles.as_slice(), GroupBy::Album, false, None);
+        assert_eq!(
+            albums.groups[0].name,
+            format!("The Beatles - {}", preview.albums[0].name)
+        );
+        // A name without other variants is shown as scrobbled
+        assert_eq!(tracks.groups[1].name, "Yesterday - The Beatles");
+    }
+
+    #[test]
+    fn test_local_times() {
+        let overrides = TimeZoneOverrides {
+            overrides: vec![TimeZoneOverride {
+                from: NaiveDate::from_ymd(2021, 7, 1),
+                to: NaiveDate::from_ymd(2021, 7, 14),
+                timezone: "Asia/Tokyo".to_string(),
+            }],
+        };
+        let local_times = LocalTimes::new(Some("Europe/London"), &overrides).unwrap();
+
+        // 2021-01-01 12:00 UTC, 2021-07-01 12:00 UTC and 2021-07-15 12:00 UTC
+        let winter = 1_609_502_400;
+        let mut saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Creep", "Radiohead", "Pablo Honey", 1_626_350_400),
+            SavedScrobble::played("Army of Me", "Björk", "Post", 1_625_140_800),
+            SavedScrobble::played("Reckoner", "Radiohead", "In Rainbows", winter),
+        ]);
+        saved_scrobbles.retime(&local_times);
+
+        let times = saved_scrobbles
+            .as_slice()
+            .iter()
+            .map(|s| (s.utc_offset(), s.time().hour()))
+            .collect::<Vec<(i32, u32)>>();
+        assert_eq!(times, vec![(3600, 13), (32400, 21), (0, 12)]);
+        assert_eq!(saved_scrobbles.retime(&local_times), 0);
+
+        // The offset is kept when the scrobbles are read back
+        let json = serde_json::to_string(saved_scrobbles.as_slice()).unwrap();
+        let read_back: Vec<SavedScrobble> = serde_json::from_str(&json).unwrap();
+        assert_eq!(read_back[1].utc_offset(), 32400);
+        assert_eq!(read_back[1].date(), NaiveDate::from_ymd(2021, 7, 1));
+
+        assert!(LocalTimes::new(Some("Mars/Olympus_Mons"), &overrides).is_err());
+    }
+
+    #[test]
+    fn test_anomalies() {
+        let jan_1_2021 = 1_609_502_400;
+        let now = jan_1_2021 + 3600;
+        let mut saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Future", "Someone", "", now + 60),
+            SavedScrobble::played("Creep", "Radiohead", "Pablo Honey", jan_1_2021 + 620),
+            SavedScrobble::played("creep", "Radiohead", "Pablo Honey", jan_1_2021 + 600),
+            SavedScrobble::played("Burst", "Someone", "", jan_1_2021 + 310),
+            SavedScrobble::played("Burst", "Someone Else", "", jan_1_2021 + 300),
+            SavedScrobble::played("Reckoner", "Radiohead", "In Rainbows", jan_1_2021),
+            SavedScrobble::played("Epoch", "Someone", "", 0),
+            SavedScrobble::played(
+                "Early",
+                "Someone",
+                "",
+                anomalies::FIRST_POSSIBLE_TIMESTAMP - 1,
+            ),
+        ]);
+
+        let anomalies = Anomalies::new(
+            saved_scrobbles.as_slice(),
+            anomalies::Thresholds::default(),
+            now,
+            &AnomalyKind::ALL,
+            10,
+        );
+        let kinds = anomalies
+            .anomalies
+            .iter()
+            .map(|a| (a.kind, a.scrobble.artist.as_str()))
+            .collect::<Vec<(AnomalyKind, &str)>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (AnomalyKind::FutureDated, "Someone"),
+                (AnomalyKind::NearDuplicate, "Radiohead"),
+                (AnomalyKind::ImpossibleDensity, "Someone"),
+                (AnomalyKind::TooEarly, "Someone"),
+                (AnomalyKind::TooEarly, "Someone"),
+            ]
+        );
+
+        let only_duplicates = Anomalies::new(
+            saved_scrobbles.as_slice(),
+            anomalies::Thresholds::default(),
+            now,
+            &[AnomalyKind::NearDuplicate],
+            10,
+        );
+        assert_eq!(only_duplicates.indices(), vec![1]);
+
+        assert_eq!(saved_scrobbles.remove(&anomalies.indices()), 5);
+        let titles = saved_scrobbles
+            .get_saved_scrobbles()
+            .into_iter()
+            .map(|s| s.title)
+            .collect::<Vec<String>>();
+        assert_eq!(titles, vec!["creep", "Burst", "Reckoner"]);
+    }
+
+    #[test]
+    fn test_terminal_charts() {
+        let items = vec![("Radiohead".to_string(), 10), ("Björk".to_string(), 5)];
+        assert_eq!(
+            terminal_charts::bar_chart(&items, 25, Charset::Ascii),
+            "Radiohead  10  ##########\nBjörk       5  #####"
+        );
+        assert_eq!(
+            terminal_charts::bar_chart(&items, 20, Charset::Unicode),
+            "Radiohead  10  █████\nBjörk       5  ██▌"
+        );
+
+        assert_eq!(
+            terminal_charts::sparkline(&[0, 1, 4, 8], 10, Charset::Unicode),
+            " ▁▄█"
+        );
+        // Only the most recent values fit
+        assert_eq!(
+            terminal_charts::sparkline(&[8, 0, 1, 4, 8], 3, Charset::Ascii),
+            ".=@"
+        );
+
+        let months = vec![("2021-01".to_string(), 4), ("2021-02".to_string(), 8)];
+        assert_eq!(
+            terminal_charts::histogram(&months, 2, 20, Charset::Ascii),
+            " @\n@@\n2021-01"
+        );
+    }
+
+    #[test]
+    fn test_diversity() {
+        let even = [("A", 5), ("B", 5), ("C", 5), ("D", 5)]
+            .iter()
+            .map(|(artist, plays)| (artist.to_string(), *plays))
+            .collect();
+        let even = DiversityMeasures::from_counts(&even);
+        assert!((even.entropy - 2.0).abs() < 1e-9);
+        assert!(even.gini.abs() < 1e-9);
+        assert_eq!(even.artists_for_50, 2);
+        assert_eq!(even.artists_for_80, 4);
+        assert!((even.long_tail_ratio - 0.75).abs() < 1e-9);
+
+        let day = 86_400;
+        let jan_1_2021 = 1_609_502_400;
+        let feb_1_2021 = jan_1_2021 + 31 * day;
+
+        let mut scrobbles = vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + day),
+        ];
+        scrobbles.extend((0..4).map(|i| {
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", feb_1_2021 + i * day)
+        }));
+        let saved_scrobbles = SavedScrobbles::new(scrobbles);
+
+        let diversity = Diversity::new(
+            saved_scrobbles.as_slice(),
+            Period::Overall,
+            feb_1_2021 + 5 * day,
+        );
+        assert_eq!(diversity.overall.artists, 2);
+        assert_eq!(diversity.overall.artists_for_80, 1);
+        assert!((diversity.overall.gini - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(diversity.months.len(), 2);
+        assert_eq!(diversity.months[0].month, "2021-01");
+        assert!((diversity.months[0].measures.entropy - 1.0).abs() < 1e-9);
+        assert_eq!(diversity.months[1].measures.entropy, 0.0);
+        assert!((diversity.entropy_trend.unwrap() + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_genre_shares() {
+        let jan_1_2021 = 1_609_502_400;
+        let jan_1_2022 = 1_641_038_400;
+
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021 + 1),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021 + 2),
+            SavedScrobble::played("Roygbiv", "Boards of Canada", "", jan_1_2021 + 3),
+            SavedScrobble::played("Roygbiv", "Boards of Canada", "", jan_1_2022),
+            SavedScrobble::played("Roygbiv", "Boards of Canada", "", jan_1_2022 + 1),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2022 + 2),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2022 + 3),
+        ]);
+
+        let artist_tags = |artist: &str, tags: &[(&str, i32)]| ArtistTags {
+            artist: artist.to_string(),
+            tags: tags
+                .iter()
+                .map(|(name, weight)| ArtistTag {
+                    name: name.to_string(),
+                    weight: *weight,
+                })
+                .collect(),
+            fetched_at: jan_1_2022,
+        };
+        let tags = ArtistTagsCache::new(vec![
+            artist_tags("radiohead", &[("alternative", 100), ("Electronic", 60)]),
+            artist_tags("Boards of Canada", &[("IDM", 100), ("seen live", 5)]),
+        ]);
+
+        let shares = GenreShares::new(
+            saved_scrobbles.as_slice(),
+            &tags,
+            &GenreRules::default(),
+            Interval::Year,
+            5,
+        );
+
+        assert_eq!(shares.tagged_scrobbles, 7);
+        assert_eq!(shares.genres[0].genre, "Rock");
+        assert_eq!(shares.genres[2].genre, genres::UNTAGGED);
+        assert_eq!(shares.periods[0].period, "2021");
+        assert!((shares.periods[0].genres[1].share - 25.0).abs() < 1e-9);
+
+        let electronic = shares
+            .changes
+            .iter()
+            .find(|c| c.genre == "Electronic")
+            .unwrap();
+        assert!((electronic.from_share - 25.0).abs() < 1e-9);
+        assert!((electronic.to_share - 50.0).abs() < 1e-9);
+        assert_eq!(electronic.to_period, "2022");
+
+        let single_tag = r#"{"toptags":{"tag":{"count":100,"name":"idm"},"@attr":{"artist":"x"}}}"#;
+        let response: TopTagsResponse = serde_json::from_str(single_tag).unwrap();
+        assert_eq!(response.top_tags.tags()[0].name, "idm");
+    }
+
+    #[test]
+    fn test_update_loved() {
+        let response = r##"{"lovedtracks":{"track":{"name":"AIRBAG","artist":{"name":"Radiohead","url":""},"date":{"uts":"1641038400","#text":"01 Jan 2022, 12:00"}},"@attr":{"user":"x","totalPages":"1","page":"1","perPage":"50","total":"1"}}}"##;
+        let response: LovedTracksResponse = serde_json::from_str(response).unwrap();
+        let loved_tracks = SavedLovedTracks::from_loved_tracks(response.loved_tracks.tracks());
+
+        assert_eq!(loved_tracks.len(), 1);
+        assert_eq!(loved_tracks.as_slice()[0].timestamp_utc, 1_641_038_400);
+
+        let mut unloved = SavedScrobble::played("Idioteque", "Radiohead", "Kid A", 1_609_502_400);
+        unloved.loved = true;
+        let mut saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", 1_609_502_401),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", 1_609_502_402),
+            unloved,
+        ]);
+
+        assert_eq!(saved_scrobbles.update_loved(&loved_tracks), 3);
+        assert_eq!(saved_scrobbles.update_loved(&loved_tracks), 0);
+
+        let loved = saved_scrobbles
+            .as_slice()
+            .iter()
+            .filter(|s| s.loved)
+            .count();
+        assert_eq!(loved, 2);
+
+        // Loved tracks keep the names Last.fm knows, which name rules may have changed
+        let mut rules = NameRules::default();
+        rules
+            .artists
+            .insert("radiohead".to_string(), "Radiohead (UK)".to_string());
+        assert_eq!(
+            saved_scrobbles.normalize(&rules.canonicalizer().unwrap()),
+            3
+        );
+        assert_eq!(saved_scrobbles.update_loved(&loved_tracks), 0);
+        assert_eq!(saved_scrobbles.as_slice()[0].artist, "Radiohead (UK)");
+        assert!(saved_scrobbles.as_slice()[0].loved);
+    }
+
+    #[test]
+    fn test_weekly_charts() {
+        let week = 7 * 86_400;
+        let from = 1_609_675_200; // Sunday 2021-01-03 12:00:00 UTC
+
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", from - 1),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", from),
+            SavedScrobble::played("airbag", "radiohead", "OK Computer", from + 60),
+            SavedScrobble::played("Idioteque", "Radiohead", "Kid A", from + 120),
+            SavedScrobble::played("Hyperballad", "Björk", "", from + 180),
+            SavedScrobble::played("Hyperballad", "Björk", "", from + week),
+        ]);
+        let scrobbles = saved_scrobbles.as_slice();
+
+        let chart = charts::generate_weekly_chart(scrobbles, from, from + week);
+        assert_eq!(chart.total_scrobbles(), 4);
+        assert_eq!(chart.artists[0].artist, "Radiohead");
+        assert_eq!(chart.artists[0].scrobbles, 3);
+        assert_eq!(chart.albums.len(), 2);
+        assert_eq!(chart.tracks[0].rank, 1);
+        assert_eq!(chart.tracks[0].scrobbles, 2);
+
+        // Last.fm counted a scrobble that was never saved
+        let mut lastfm_chart = chart.clone();
+        lastfm_chart.tracks[1].scrobbles += 1;
+        let next_week = charts::generate_weekly_chart(scrobbles, from + week, from + 2 * week);
+        let lastfm_charts = SavedWeeklyCharts::new(vec![lastfm_chart, next_week]);
+
+        let diff = ChartDiff::new(&lastfm_charts, scrobbles, ChartKind::Track);
+        assert_eq!(diff.weeks, 2);
+        assert_eq!(diff.matching_weeks, 1);
+        assert_eq!(diff.missing_scrobbles, 1);
+        assert_eq!(diff.extra_scrobbles, 0);
+        assert_eq!(diff.mismatched_weeks[0].lastfm_scrobbles, 5);
+        assert_eq!(diff.mismatched_weeks[0].entries.len(), 1);
+
+        let matching = ChartDiff::new(&lastfm_charts, scrobbles, ChartKind::Artist);
+        assert!(matching.mismatched_weeks.is_empty());
+
+        // An excluded artist is in the Last.fm charts too, so the charts match without exclusions
+        let rules = ExclusionRules {
+            artists: vec!["Björk".to_string()],
+            ..ExclusionRules::default()
+        };
+        let mut excluded = SavedScrobbles::new(scrobbles.to_vec());
+        assert_eq!(
+            excluded.exclude(&rules.exclusions(&ArtistTagsCache::default()).unwrap()),
+            2
+        );
+        assert!(lastfm_charts
+            .iter()
+            .all(|c| c.artists.iter().any(|a| a.artist == "Björk")));
+        let excluded_diff = ChartDiff::new(&lastfm_charts, excluded.as_slice(), ChartKind::Artist);
+        assert_eq!(excluded_diff.missing_scrobbles, 2);
+    }
+
+    #[test]
+    fn test_chart_race() {
+        let day = 86_400;
+        let jan_1_2021 = 1_609_502_400;
+
+        let saved_scrobbles = SavedScrobbles::new(vec![
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021),
+            SavedScrobble::played("Airbag", "Radiohead", "OK Computer", jan_1_2021 + 60),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + day),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 3 * day),
+            SavedScrobble::played("Hyperballad", "Björk", "Post", jan_1_2021 + 3 * day + 60),
+        ]);
+        let scrobbles = saved_scrobbles.as_slice();
+
+        let race = ChartRace::new(
+            scrobbles,
+            ChartKind::Artist,
+            chart_race::Interval::Day,
+            Mode::Cumulative,
+            1,
+            1,
+        );
+        assert_eq!(race.dates.len(), 4);
+        assert_eq!(race.ranks[3], vec!["Björk".to_string()]);
+        assert_eq!(race.values["Radiohead"], vec![2, 2, 2, 2]);
+
+        let race = ChartRace::new(
+            scrobbles,
+            ChartKind::Artist,
+            chart_race::Interval::Day,
+            Mode::Rolling,
+            2,
+            2,
+        );
+        assert_eq!(race.values["Radiohead"], vec![2, 2, 0, 0]);
+        assert_eq!(race.values["Björk"], vec![0, 1, 1, 2]);
+        assert_eq!(race.ranks[2], vec!["Björk".to_string()]);
+
+        let mut csv = Vec::new();
+        race.write_csv(&mut csv, Layout::Wide).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("name,2021-01-01,2021-01-02,2021-01-03,2021-01-04\n"));
+        assert!(csv.contains("Radiohead,2,2,0,0\n"));
+    }
+
+    /// Scrobbles from a handful of seconds, artists and tracks, so many are copies of each other
+    fn arbitrary_scrobbles() -> impl Strategy<Value = Vec<SavedScrobble>> {
+        let plays = (0i64..20, 0usize..3, 0usize..3, any::<bool>(), any::<bool>());
+        prop::collection::vec(plays, 0..40).prop_map(|plays| {
+            plays
+                .into_iter()
+                .map(|(seconds, artist, title, album, loved)| {
+                    let mut scrobble = SavedScrobble::played(
+                        ["Creep", "Reckoner", "Army of Me"][title],
+                        ["Radiohead", "Björk", "The Beatles"][artist],
+                        if album { "An Album" } else { "" },
+                        1_609_502_400 + seconds,
+                    );
+                    scrobble.loved = loved;
+                    scrobble
+                })
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_sort_keeps_one_copy_of_each_scrobble(scrobbles in arbitrary_scrobbles()) {
+            let saved_scrobbles = SavedScrobbles::new(scrobbles.clone());
+            let sorted = saved_scrobbles.as_slice();
+
+            // Newest first, and every identity only once
+            prop_assert!(sorted.windows(2).all(|w| w[0].identity() > w[1].identity()));
+            let identities = scrobbles
+                .iter()
+                .map(|s| s.identity())
+                .collect::<HashSet<(i64, &str, &str)>>();
+            prop_assert_eq!(sorted.len(), identities.len());
+
+            for kept in sorted {
+                let copies = scrobbles
+                    .iter()
+                    .filter(|s| s.identity() == kept.identity())
+                    .collect::<Vec<&SavedScrobble>>();
+                prop_assert_eq!(kept.loved, copies.iter().any(|s| s.loved));
+                prop_assert_eq!(kept.album.is_empty(), copies.iter().all(|s| s.album.is_empty()));
+            }
+        }
+
+        #[test]
+        fn test_sort_ignores_fetch_order(scrobbles in arbitrary_scrobbles(), rotate in 0usize..40) {
+            let mut reordered = scrobbles.clone();
+            reordered.reverse();
+            if !reordered.is_empty() {
+                let len = reordered.len();
+                reordered.rotate_left(rotate % len);
+            }
+
+            let fields = |scrobbles: Vec<SavedScrobble>| {
+                SavedScrobbles::new(scrobbles)
+                    .get_saved_scrobbles()
+                    .into_iter()
+                    .map(|s| (s.timestamp_utc, s.artist, s.title, s.album, s.loved))
+                    .collect::<Vec<(i64, String, String, String, bool)>>()
+            };
+            prop_assert_eq!(fields(scrobbles), fields(reordered));
+        }
+
+        #[test]
+        fn test_refetching_never_grows_history(scrobbles in arbitrary_scrobbles(), refetched in 0usize..40) {
+            let mut saved_scrobbles = SavedScrobbles::new(scrobbles.clone());
+            let total = saved_scrobbles.total_saved_scrobbles();
+
+            // Fetching the same scrobbles again, after some were loved or unloved
+            let again = scrobbles
+                .into_iter()
+                .take(refetched)
+                .map(|mut s| {
+                    s.loved = !s.loved;
+                    s
+                })
+                .collect();
+            saved_scrobbles.append_new_scrobbles(SavedScrobbles::new(again));
+            prop_assert_eq!(saved_scrobbles.total_saved_scrobbles(), total);
+        }
+    }
 }